@@ -0,0 +1,236 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use image::ImageResult;
+
+use crate::{
+    backgrounds::Background,
+    utils::{
+        helpers::random_float,
+        vec::{Color, Vec3},
+    },
+};
+
+/// An equirectangular (latitude-longitude) image surrounding the scene, usually
+/// a high dynamic range photograph of a real environment which then lights it.
+///
+/// The center of the image is seen when looking down -Z, its top when looking
+/// up +Y.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    // Shared so that cloning the background into every job stays cheap
+    pub data: Arc<Vec<f32>>,
+    pub width: usize,
+    pub height: usize,
+    /// Turns the image around the Y axis, in degrees
+    pub rotation: f32,
+    /// Scales the brightness of the image
+    pub intensity: f32,
+    distribution: Arc<Distribution>,
+}
+
+// Picks pixels in proportion to the light coming from them: their luminance,
+// times the sine of their latitude as rows shrink toward the poles
+#[derive(Debug)]
+struct Distribution {
+    // Running totals of the row weights
+    rows: Vec<f32>,
+    // Running totals of the pixel weights, restarting at each row
+    pixels: Vec<f32>,
+}
+
+impl Distribution {
+    fn new(data: &[f32], width: usize, height: usize) -> Self {
+        let mut rows = Vec::with_capacity(height);
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut total = 0.0;
+        for j in 0..height {
+            let sin_theta = ((j as f32 + 0.5) / height as f32 * PI).sin();
+            let mut row = 0.0;
+            for i in 0..width {
+                let index = (j * width + i) * 3;
+                let luminance =
+                    0.2126 * data[index] + 0.7152 * data[index + 1] + 0.0722 * data[index + 2];
+                row += luminance.max(0.0) * sin_theta;
+                pixels.push(row);
+            }
+            total += row;
+            rows.push(total);
+        }
+        Self { rows, pixels }
+    }
+
+    fn total(&self) -> f32 {
+        self.rows.last().copied().unwrap_or_default()
+    }
+
+    // The probability of picking a pixel
+    fn probability(&self, i: usize, j: usize, width: usize) -> f32 {
+        let row = &self.pixels[j * width..(j + 1) * width];
+        let weight = row[i] - if i == 0 { 0.0 } else { row[i - 1] };
+        weight / self.total()
+    }
+
+    fn sample(&self, width: usize) -> (usize, usize) {
+        let target = random_float() * self.total();
+        let j = self
+            .rows
+            .partition_point(|&total| total <= target)
+            .min(self.rows.len() - 1);
+        let row = &self.pixels[j * width..(j + 1) * width];
+        let target = random_float() * row[width - 1];
+        let i = row.partition_point(|&total| total <= target).min(width - 1);
+        (i, j)
+    }
+}
+
+impl EnvironmentMap {
+    /// Wraps already decoded linear pixels, 3 floats per pixel, row by row from the top.
+    pub fn from_rgb(
+        data: Vec<f32>,
+        width: usize,
+        height: usize,
+        rotation: f32,
+        intensity: f32,
+    ) -> Self {
+        let distribution = Arc::new(Distribution::new(&data, width, height));
+        Self {
+            data: Arc::new(data),
+            width,
+            height,
+            rotation,
+            intensity,
+            distribution,
+        }
+    }
+
+    /// Loads a Radiance (.hdr) or OpenEXR (.exr) image. Other formats supported
+    /// by the `image` crate work too, but are limited to a low dynamic range.
+    /// # Arguments
+    /// * `filename` - The image file
+    /// * `rotation` - The rotation around the Y axis, in degrees
+    /// * `intensity` - The brightness multiplier
+    pub fn load(filename: &str, rotation: f32, intensity: f32) -> ImageResult<Self> {
+        let image = image::open(filename)?.into_rgb32f();
+        let (width, height) = image.dimensions();
+        Ok(Self::from_rgb(
+            image.into_raw(),
+            width as usize,
+            height as usize,
+            rotation,
+            intensity,
+        ))
+    }
+
+    // Where a direction lands on the image, from the top left corner
+    fn uv(&self, direction: &Vec3) -> (f32, f32) {
+        let direction = direction.unit_vector();
+        let longitude = direction.x.atan2(-direction.z) - self.rotation.to_radians();
+        let latitude = direction.y.clamp(-1.0, 1.0).acos();
+        (
+            (longitude / (2.0 * PI) + 0.5).rem_euclid(1.0),
+            latitude / PI,
+        )
+    }
+
+    fn pixel(&self, i: usize, j: usize) -> Color {
+        let index = (j * self.width + i) * 3;
+        Color::new(self.data[index], self.data[index + 1], self.data[index + 2])
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn color(&self, direction: &Vec3) -> Color {
+        if self.data.is_empty() {
+            return Color::zero();
+        }
+        let (u, v) = self.uv(direction);
+
+        // Bilinear filtering between the four closest pixel centers, wrapping around horizontally
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let i0 = (x.floor() as isize).rem_euclid(self.width as isize) as usize;
+        let i1 = (i0 + 1) % self.width;
+        let j0 = y.floor() as usize;
+        let j1 = (j0 + 1).min(self.height - 1);
+        let top = (1.0 - fx) * self.pixel(i0, j0) + fx * self.pixel(i1, j0);
+        let bottom = (1.0 - fx) * self.pixel(i0, j1) + fx * self.pixel(i1, j1);
+        self.intensity * ((1.0 - fy) * top + fy * bottom)
+    }
+
+    fn is_light(&self) -> bool {
+        self.intensity > 0.0 && self.distribution.total() > 0.0
+    }
+
+    fn sample(&self) -> (Vec3, f32) {
+        let (i, j) = self.distribution.sample(self.width);
+        let u = (i as f32 + random_float()) / self.width as f32;
+        let v = (j as f32 + random_float()) / self.height as f32;
+        let longitude = (u - 0.5) * 2.0 * PI + self.rotation.to_radians();
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        let direction = Vec3::new(
+            sin_theta * longitude.sin(),
+            cos_theta,
+            -sin_theta * longitude.cos(),
+        );
+        (direction, self.pdf(&direction))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f32 {
+        if self.distribution.total() <= 0.0 {
+            return 0.0;
+        }
+        let (u, v) = self.uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let i = ((u * self.width as f32) as usize).min(self.width - 1);
+        let j = ((v * self.height as f32) as usize).min(self.height - 1);
+        // From a density over the image to one over the sphere, whose rows of
+        // pixels cover less solid angle toward the poles
+        self.distribution.probability(i, j, self.width) * (self.width * self.height) as f32
+            / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::helpers::random_vector;
+
+    // A dim 8x4 map with one bright pixel, turned a little
+    fn environment() -> EnvironmentMap {
+        let (width, height) = (8, 4);
+        let mut data = vec![0.1; width * height * 3];
+        data[(width + 5) * 3..(width + 6) * 3].copy_from_slice(&[50.0, 40.0, 30.0]);
+        EnvironmentMap::from_rgb(data, width, height, 30.0, 1.0)
+    }
+
+    #[test]
+    fn samples_with_the_density_of_pdf() {
+        let environment = environment();
+        let mut bright = 0;
+        for _ in 0..10_000 {
+            let (direction, pdf) = environment.sample();
+            assert!((direction.length() - 1.0).abs() < 1e-4);
+            assert!((pdf - environment.pdf(&direction)).abs() <= 1e-3 * pdf);
+            if environment.color(&direction).x > 10.0 {
+                bright += 1;
+            }
+        }
+        // The bright pixel gives off most of the light, and is picked as often
+        assert!(bright > 7_000);
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let environment = environment();
+        let samples = 200_000;
+        let total: f32 = (0..samples)
+            .map(|_| environment.pdf(&random_vector()))
+            .sum();
+        let integral = 4.0 * PI * total / samples as f32;
+        assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+    }
+}
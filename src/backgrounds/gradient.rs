@@ -0,0 +1,50 @@
+use crate::{
+    backgrounds::Background,
+    utils::vec::{Color, Vec3},
+};
+
+/// A blend between two colors, going from `bottom` when looking down the `up`
+/// axis to `top` when looking up it.
+#[derive(Debug, Clone)]
+pub struct GradientBackground {
+    pub bottom: Color,
+    pub top: Color,
+    pub up: Vec3,
+}
+
+impl GradientBackground {
+    /// Create a new GradientBackground
+    /// # Arguments
+    /// * `bottom` - The color straight down
+    /// * `top` - The color straight up
+    /// * `up` - The axis of the gradient, does not need to be normalized
+    pub fn new(bottom: Color, top: Color, up: Vec3) -> Self {
+        Self {
+            bottom,
+            top,
+            up: up.unit_vector(),
+        }
+    }
+
+    /// The white to light blue sky of the built-in scene
+    pub fn sky() -> Self {
+        Self::new(
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.5, 0.7, 1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        )
+    }
+}
+
+impl Default for GradientBackground {
+    fn default() -> Self {
+        Self::sky()
+    }
+}
+
+impl Background for GradientBackground {
+    fn color(&self, direction: &Vec3) -> Color {
+        let t = 0.5 * (direction.unit_vector().dot(&self.up) + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+}
@@ -0,0 +1,42 @@
+use std::{f32::consts::PI, fmt::Debug};
+
+use dyn_clonable::clonable;
+
+use crate::utils::{
+    helpers::random_vector,
+    vec::{Color, Vec3},
+};
+
+pub mod environment;
+pub mod gradient;
+pub mod sky;
+pub mod solid;
+
+/// What rays see when they leave the scene without hitting anything.
+#[clonable]
+pub trait Background: Send + Sync + Clone {
+    /// The light coming from a direction, which does not need to be normalized
+    fn color(&self, direction: &Vec3) -> Color;
+
+    /// Whether the background lights the scene, and is sampled like its lights
+    fn is_light(&self) -> bool {
+        true
+    }
+
+    /// A random unit direction, ideally where the background is brightest, with
+    /// its probability density over solid angle. Uniform over the sphere by default.
+    fn sample(&self) -> (Vec3, f32) {
+        (random_vector(), 1.0 / (4.0 * PI))
+    }
+
+    /// The probability density of `sample` picking a direction
+    fn pdf(&self, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+impl Debug for dyn Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Background").finish()
+    }
+}
@@ -0,0 +1,388 @@
+use std::f32::consts::PI;
+
+use crate::{
+    backgrounds::Background,
+    lights::{directional::DirectionalLight, Light},
+    utils::{
+        helpers::{random_float, random_vector},
+        ray::Ray,
+        spectrum::xyz_to_rgb,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+// Luminances are divided by this many cd/m², so that a white surface facing the
+// sun at noon comes out about white
+const LUMINANCE_SCALE: f32 = 30_000.0;
+// The illuminance of the sun above the atmosphere, in lux
+const SOLAR_ILLUMINANCE: f32 = 128_000.0;
+// The angular diameter of the sun, in degrees
+const SUN_ANGLE: f32 = 0.53;
+// How often `sample` picks the sun rather than the sky
+const SUN_PROBABILITY: f32 = 0.5;
+
+/// The clear sky of the Preetham et al. analytic model ("A Practical Analytic
+/// Model for Daylight"), bluer and brighter toward the sun, with the disk of the
+/// sun itself which is sampled like a light. Below the horizon lies a flat
+/// ground of color `ground`, lit by both.
+///
+/// The sun is `elevation` degrees above the horizon, and `azimuth` degrees
+/// clockwise from the north, which is -Z, with the east toward +X.
+#[derive(Debug, Clone)]
+pub struct SkyBackground {
+    /// Where the sun is, from the scene toward it
+    pub sun_direction: Vec3,
+    /// The haziness of the air, from 2 for a very clear sky to 10 for a hazy one
+    pub turbidity: f32,
+    /// Scales the brightness of both the sky and the sun
+    pub intensity: f32,
+    sun: Option<DirectionalLight>,
+    // The luminance and chromaticity at the zenith, in cd/m²
+    zenith: Color,
+    perez: [[f32; 5]; 3],
+    ground: Color,
+}
+
+impl SkyBackground {
+    /// Create a new SkyBackground
+    /// # Arguments
+    /// * `elevation` - The angle of the sun above the horizon in degrees, night falls by -6
+    /// * `azimuth` - The angle of the sun clockwise from the north (-Z) in degrees
+    /// * `turbidity` - The haziness of the air, from 2 to 10
+    /// * `intensity` - Scales the brightness of both the sky and the sun
+    /// * `sun` - Whether to show and sample the disk of the sun
+    /// * `ground` - The color of the ground below the horizon
+    pub fn new(
+        elevation: f32,
+        azimuth: f32,
+        turbidity: f32,
+        intensity: f32,
+        sun: bool,
+        ground: Color,
+    ) -> Self {
+        let (elevation_rad, azimuth_rad) = (elevation.to_radians(), azimuth.to_radians());
+        let sun_direction = Vec3::new(
+            azimuth_rad.sin() * elevation_rad.cos(),
+            elevation_rad.sin(),
+            -azimuth_rad.cos() * elevation_rad.cos(),
+        );
+        let turbidity = turbidity.clamp(2.0, 10.0);
+        // The model only holds with the sun above the horizon
+        let theta_s = (PI / 2.0 - elevation_rad).clamp(0.0, PI / 2.0);
+        let t = turbidity;
+        let perez = [
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+        ];
+        let (t2, s, s2, s3) = (t * t, theta_s, theta_s * theta_s, theta_s.powi(3));
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith = Color::new(
+            t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+                + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+                + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886),
+            t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+                + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+                + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688),
+            // In kcd/m², fading out during civil twilight as the sun sets further
+            ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0)
+                * 1000.0
+                * ((elevation + 6.0) / 6.0).clamp(0.0, 1.0),
+        );
+
+        let sun = (sun && elevation > 0.0).then(|| {
+            DirectionalLight::new(
+                -sun_direction,
+                sun_transmittance(theta_s, turbidity) * SOLAR_ILLUMINANCE / LUMINANCE_SCALE
+                    * intensity,
+                SUN_ANGLE,
+            )
+        });
+        let mut sky = Self {
+            sun_direction,
+            turbidity,
+            intensity,
+            sun,
+            zenith,
+            perez,
+            ground: Color::zero(),
+        };
+        sky.ground = ground * sky.ground_irradiance() / PI;
+        sky
+    }
+
+    // The light of the sky alone, without the sun, coming from above the horizon
+    fn sky(&self, direction: &Vec3) -> Color {
+        let direction = direction.unit_vector();
+        let cos_theta = direction.y.max(0.01);
+        let cos_gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let theta_s = self.sun_direction.y.clamp(0.0, 1.0).acos();
+        let perez = |[a, b, c, d, e]: [f32; 5], cos_theta: f32, gamma: f32, cos_gamma: f32| {
+            (1.0 + a * (b / cos_theta).exp())
+                * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+        };
+        let value = |i: usize| {
+            perez(self.perez[i], cos_theta, gamma, cos_gamma)
+                / perez(self.perez[i], 1.0, theta_s, theta_s.cos())
+        };
+        let (x, y, luminance) = (
+            self.zenith.x * value(0),
+            self.zenith.y * value(1),
+            self.zenith.z * value(2),
+        );
+        if y <= 0.0 {
+            return Color::zero();
+        }
+        let xyz = Color::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        xyz_to_rgb(&xyz).clamp(0.0, f32::INFINITY) * (self.intensity / LUMINANCE_SCALE)
+    }
+
+    // The light received by the ground from the sky and the sun
+    fn ground_irradiance(&self) -> Color {
+        const STEPS: usize = 32;
+        let mut irradiance = Color::zero();
+        for i in 0..STEPS {
+            // Cosine weighted rings of the upper hemisphere
+            let cos_theta = ((i as f32 + 0.5) / STEPS as f32).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            for j in 0..STEPS * 2 {
+                let phi = (j as f32 + 0.5) / (STEPS * 2) as f32 * 2.0 * PI;
+                let direction = Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+                irradiance += self.sky(&direction);
+            }
+        }
+        irradiance = irradiance * PI / (STEPS * STEPS * 2) as f32;
+        if let Some(sun) = &self.sun {
+            irradiance += sun.irradiance * self.sun_direction.y.max(0.0);
+        }
+        irradiance
+    }
+}
+
+impl Background for SkyBackground {
+    fn color(&self, direction: &Vec3) -> Color {
+        if direction.y < 0.0 {
+            return self.ground;
+        }
+        let mut color = self.sky(direction);
+        if let Some(sun) = &self.sun {
+            color += sun.escaped(&Ray::new(Point3::zero(), *direction, 0.0));
+        }
+        color
+    }
+
+    fn sample(&self) -> (Vec3, f32) {
+        let direction = match &self.sun {
+            Some(sun) if random_float() < SUN_PROBABILITY => sun
+                .sample(&Point3::zero(), 0.0)
+                .map_or_else(random_vector, |sample| sample.direction),
+            _ => random_vector(),
+        };
+        (direction, self.pdf(&direction))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f32 {
+        let uniform = 1.0 / (4.0 * PI);
+        match &self.sun {
+            Some(sun) => {
+                let ray = Ray::new(Point3::zero(), *direction, 0.0);
+                (1.0 - SUN_PROBABILITY) * uniform + SUN_PROBABILITY * sun.pdf(&ray, f32::INFINITY)
+            }
+            None => uniform,
+        }
+    }
+}
+
+// The share of sunlight making it through the atmosphere, for red, green and
+// blue, scattered away by air molecules (Rayleigh) and haze (Ångström)
+fn sun_transmittance(theta_s: f32, turbidity: f32) -> Color {
+    // Relative optical air mass (Kasten and Young), 1 with the sun straight up
+    let air_mass =
+        1.0 / (theta_s.cos() + 0.50572 * (96.07995 - theta_s.to_degrees()).powf(-1.6364));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |wavelength: f32| {
+        let rayleigh = (-0.008735 * wavelength.powf(-4.08) * air_mass).exp();
+        let haze = (-beta * wavelength.powf(-1.3) * air_mass).exp();
+        rayleigh * haze
+    };
+    // In micrometers
+    Color::new(
+        transmittance(0.68),
+        transmittance(0.55),
+        transmittance(0.44),
+    )
+}
+
+/// Where the sun is seen from a place on Earth at a given time, as an elevation
+/// above the horizon and an azimuth clockwise from the north, both in degrees.
+/// # Arguments
+/// * `latitude` - In degrees, north of the equator
+/// * `longitude` - In degrees, east of Greenwich
+/// * `time` - An ISO 8601 date and time, `2024-06-21T18:30:00+02:00`, in UTC when the offset is left out
+pub fn sun_position(latitude: f32, longitude: f32, time: &str) -> Result<(f32, f32), String> {
+    // Days since noon on January 1st 2000, UTC
+    let n = julian_date(time)? - 2_451_545.0;
+    // The low precision formulas of the Astronomical Almanac, good to 0.01°
+    let mean_longitude = 280.460 + 0.985_647_4 * n;
+    let anomaly = (357.528 + 0.985_600_3 * n).to_radians();
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * anomaly.sin() + 0.020 * (2.0 * anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.000_000_4 * n).to_radians();
+    let right_ascension =
+        (obliquity.cos() * ecliptic_longitude.sin()).atan2(ecliptic_longitude.cos());
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+    let sidereal_time = (18.697_374_558 + 24.065_709_824_419_08 * n) * 15.0;
+    let hour_angle = (sidereal_time + longitude as f64).to_radians() - right_ascension;
+
+    let latitude = (latitude as f64).to_radians();
+    let elevation = (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .asin();
+    let azimuth = (-declination.cos() * hour_angle.sin()).atan2(
+        declination.sin() * latitude.cos() - declination.cos() * latitude.sin() * hour_angle.cos(),
+    );
+    Ok((
+        elevation.to_degrees() as f32,
+        azimuth.to_degrees().rem_euclid(360.0) as f32,
+    ))
+}
+
+// The Julian date of an ISO 8601 date and time
+fn julian_date(time: &str) -> Result<f64, String> {
+    let invalid = || {
+        format!(
+            "invalid time `{}`, expected YYYY-MM-DDTHH:MM[:SS][Z|±HH:MM]",
+            time
+        )
+    };
+    let number = |part: &str| part.parse::<i64>().map_err(|_| invalid());
+    let (date, clock) = time.split_once(['T', ' ']).ok_or_else(invalid)?;
+    let date = date.split('-').map(number).collect::<Result<Vec<_>, _>>()?;
+    let (year, month, day) = match date[..] {
+        [year, month, day] if (1..=12).contains(&month) => (year, month, day),
+        _ => return Err(invalid()),
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return Err(invalid());
+    }
+
+    // The offset from UTC, in minutes
+    let (clock, offset) = if let Some(clock) = clock.strip_suffix('Z') {
+        (clock, 0)
+    } else if let Some(index) = clock.rfind(['+', '-']) {
+        let sign = if clock[index..].starts_with('-') {
+            -1
+        } else {
+            1
+        };
+        let (hours, minutes) = clock[index + 1..]
+            .split_once(':')
+            .unwrap_or((&clock[index + 1..], "0"));
+        (
+            &clock[..index],
+            sign * (number(hours)? * 60 + number(minutes)?),
+        )
+    } else {
+        (clock, 0)
+    };
+    let clock = clock
+        .split(':')
+        .map(|part| part.parse::<f64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (hour, minute, second) = match clock[..] {
+        [hour, minute] => (hour, minute, 0.0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(invalid()),
+    };
+    // Leap seconds make some minutes 61 seconds long
+    if !(0.0..24.0).contains(&hour)
+        || !(0.0..60.0).contains(&minute)
+        || !(0.0..61.0).contains(&second)
+    {
+        return Err(invalid());
+    }
+
+    // Days since the beginning of the Julian period, at noon
+    let a = (14 - month) / 12;
+    let y = year + 4800 - a;
+    let m = month + 12 * a - 3;
+    let day_number = day + (153 * m + 2) / 5 + 365 * y + y / 4 - y / 100 + y / 400 - 32045;
+    let minutes = (hour - 12.0) * 60.0 + minute + second / 60.0 - offset as f64;
+    Ok(day_number as f64 + minutes / 1440.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn julian_date_of_known_days() {
+        assert_eq!(julian_date("2000-01-01T12:00Z"), Ok(2_451_545.0));
+        assert_eq!(julian_date("2000-01-01T00:00:00"), Ok(2_451_544.5));
+        assert_eq!(julian_date("1987-01-27T00:00Z"), Ok(2_446_822.5));
+        assert_eq!(julian_date("1988-06-19 12:00"), Ok(2_447_332.0));
+        // The same instant, given in other time zones
+        assert_eq!(julian_date("2000-01-01T13:30+01:30"), Ok(2_451_545.0));
+        assert_eq!(julian_date("2000-01-01T07:00-05"), Ok(2_451_545.0));
+    }
+
+    #[test]
+    fn julian_date_rejects_impossible_dates() {
+        assert!(julian_date("2023-02-31T12:00Z").is_err());
+        assert!(julian_date("2023-02-29T12:00Z").is_err());
+        assert!(julian_date("2024-02-29T12:00Z").is_ok());
+        assert!(julian_date("1900-02-29T12:00Z").is_err());
+        assert!(julian_date("2000-02-29T12:00Z").is_ok());
+        assert!(julian_date("2023-04-31T12:00Z").is_err());
+        assert!(julian_date("2023-13-01T12:00Z").is_err());
+        assert!(julian_date("2023-01-00T12:00Z").is_err());
+        assert!(julian_date("2023-01-01T25:00Z").is_err());
+        assert!(julian_date("2023-01-01T12:60Z").is_err());
+        assert!(julian_date("2023-01-01").is_err());
+    }
+
+    #[test]
+    fn sun_position_at_j2000() {
+        // At noon UTC over Greenwich and the equator, the sun is in the south,
+        // as high as the equator is tilted away from its declination of -23.0°
+        let (elevation, azimuth) = sun_position(0.0, 0.0, "2000-01-01T12:00Z").unwrap();
+        assert!((elevation - 67.0).abs() < 0.1, "elevation {}", elevation);
+        assert!((azimuth - 180.0).abs() < 2.0, "azimuth {}", azimuth);
+    }
+
+    #[test]
+    fn sun_position_at_solstice() {
+        // Overhead at noon on the Tropic of Cancer, and below the horizon
+        // on the other side of the Earth
+        let (elevation, _) = sun_position(23.44, 0.0, "2024-06-20T12:00Z").unwrap();
+        assert!(elevation > 89.0, "elevation {}", elevation);
+        let (elevation, _) = sun_position(23.44, 180.0, "2024-06-20T12:00Z").unwrap();
+        assert!(elevation < -40.0, "elevation {}", elevation);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::{
+    backgrounds::Background,
+    utils::vec::{Color, Vec3},
+};
+
+/// The same color in every direction, black for scenes lit only by their own lights.
+#[derive(Debug, Clone)]
+pub struct SolidBackground {
+    pub color: Color,
+}
+
+impl SolidBackground {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+
+    pub fn black() -> Self {
+        Self::new(Color::zero())
+    }
+}
+
+impl Background for SolidBackground {
+    fn color(&self, _direction: &Vec3) -> Color {
+        self.color
+    }
+
+    fn is_light(&self) -> bool {
+        !self.color.near_zero()
+    }
+}
@@ -0,0 +1,290 @@
+use std::{fs, path::Path, sync::Arc};
+
+use crate::{densities::Density, shapes::aabb::AxisAlignedBoundingBox, utils::vec::Point3};
+
+/// Densities stored in a 3D grid of voxels stretched over a box, interpolated
+/// between voxel centers and 0 outside of the box
+#[derive(Clone)]
+pub struct GridDensity {
+    /// Voxel values, x varying fastest, then y, then z
+    pub data: Arc<Vec<f32>>,
+    pub resolution: [usize; 3],
+    pub bounds: AxisAlignedBoundingBox,
+    pub scale: f32,
+    max: f32,
+}
+
+impl GridDensity {
+    /// Create a new GridDensity, or `None` if `data` does not match the resolution.
+    /// Negative (and NaN) voxels are clamped to 0, as a medium cannot be less than empty.
+    /// # Arguments
+    /// * `data` - The voxel values, x varying fastest, then y, then z
+    /// * `resolution` - The number of voxels along each axis
+    /// * `bounds` - The box the grid is stretched over
+    /// * `scale` - A multiplier of the voxel values
+    pub fn new(
+        mut data: Vec<f32>,
+        resolution: [usize; 3],
+        bounds: AxisAlignedBoundingBox,
+        scale: f32,
+    ) -> Option<Self> {
+        if resolution.contains(&0) || data.len() != resolution.iter().product::<usize>() {
+            return None;
+        }
+        data.iter_mut().for_each(|value| *value = value.max(0.0));
+        let max = data.iter().copied().fold(0.0, f32::max);
+        Some(Self {
+            data: Arc::new(data),
+            resolution,
+            bounds,
+            scale,
+            max,
+        })
+    }
+
+    /// Loads a grid from an NRRD file (raw encoded, of 8-bit or floating point
+    /// values), or from a raw file of `resolution` voxels, either bytes or
+    /// little-endian 32-bit floats. Bytes are brought to [0, 1].
+    /// # Arguments
+    /// * `filename` - The .nrrd or raw file
+    /// * `resolution` - The number of voxels along each axis, needed for raw files
+    /// * `bounds` - The box the grid is stretched over
+    /// * `scale` - A multiplier of the voxel values
+    pub fn load(
+        filename: &str,
+        resolution: Option<[usize; 3]>,
+        bounds: AxisAlignedBoundingBox,
+        scale: f32,
+    ) -> Result<Self, String> {
+        let bytes = fs::read(filename).map_err(|e| e.to_string())?;
+        let is_nrrd = Path::new(filename)
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("nrrd"));
+        let (data, resolution) = if is_nrrd {
+            read_nrrd(filename, &bytes)?
+        } else {
+            let resolution = resolution.ok_or("raw grids need a resolution")?;
+            let count = resolution.iter().product::<usize>();
+            let data = if bytes.len() == count {
+                decode(&bytes, "uchar", true)?
+            } else if bytes.len() == count * 4 {
+                decode(&bytes, "float", true)?
+            } else {
+                return Err(format!(
+                    "expected {} bytes or floats for a {}x{}x{} grid, found {} bytes",
+                    count,
+                    resolution[0],
+                    resolution[1],
+                    resolution[2],
+                    bytes.len()
+                ));
+            };
+            (data, resolution)
+        };
+        Self::new(data, resolution, bounds, scale)
+            .ok_or_else(|| "the voxel count does not match the grid size".to_string())
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        let [nx, ny, _] = self.resolution;
+        self.data[x + nx * (y + ny * z)]
+    }
+}
+
+impl Density for GridDensity {
+    fn value(&self, point: &Point3) -> f32 {
+        let size = self.bounds.max - self.bounds.min;
+        let local = *point - self.bounds.min;
+        let mut cell = [0; 3];
+        let mut weight = [0.0; 3];
+        for axis in 0..3 {
+            let relative = local[axis] / size[axis];
+            if !(0.0..=1.0).contains(&relative) {
+                return 0.0;
+            }
+            // Voxel values sit at the centers of their cells
+            let n = self.resolution[axis];
+            let position = (relative * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            cell[axis] = (position as usize).min(n.saturating_sub(2));
+            weight[axis] = position - cell[axis] as f32;
+        }
+
+        // Trilinear interpolation between the 8 voxels around the point
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut corner_weight = 1.0;
+            let mut index = [0; 3];
+            for axis in 0..3 {
+                index[axis] = (cell[axis] + offset[axis]).min(self.resolution[axis] - 1);
+                corner_weight *= if offset[axis] == 1 {
+                    weight[axis]
+                } else {
+                    1.0 - weight[axis]
+                };
+            }
+            if corner_weight > 0.0 {
+                value += corner_weight * self.voxel(index[0], index[1], index[2]);
+            }
+        }
+        self.scale * value
+    }
+
+    fn max_value(&self) -> f32 {
+        self.scale * self.max
+    }
+}
+
+// Reads the header of an NRRD file, followed by its data or naming the file holding it
+fn read_nrrd(filename: &str, bytes: &[u8]) -> Result<(Vec<f32>, [usize; 3]), String> {
+    if !bytes.starts_with(b"NRRD") {
+        return Err("missing NRRD magic".to_string());
+    }
+    let mut kind = None;
+    let mut sizes = None;
+    let mut little_endian = true;
+    let mut data_file = None;
+    let mut offset = 0;
+    for line in bytes.split_inclusive(|&b| b == b'\n') {
+        offset += line.len();
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        // The header ends with an empty line, right before the data
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with('#') || line.starts_with("NRRD") {
+            continue;
+        }
+        let (key, value) = match line.split_once(": ") {
+            Some(field) => field,
+            // `key:=value` pairs, which do not describe the grid
+            None => continue,
+        };
+        let value = value.trim();
+        match key {
+            "type" => kind = Some(value.to_string()),
+            "dimension" if value != "3" => {
+                return Err(format!("expected a 3D grid, found dimension {}", value))
+            }
+            "sizes" => {
+                let parsed = value
+                    .split_whitespace()
+                    .map(|s| s.parse::<usize>().ok())
+                    .collect::<Option<Vec<_>>>();
+                sizes = match parsed.as_deref() {
+                    Some(&[x, y, z]) => Some([x, y, z]),
+                    _ => return Err(format!("invalid sizes `{}`", value)),
+                };
+            }
+            "encoding" if value != "raw" => {
+                return Err(format!("unsupported encoding `{}`, expected raw", value))
+            }
+            "endian" => little_endian = value == "little",
+            "data file" | "datafile" => data_file = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    let kind = kind.ok_or("missing type")?;
+    let sizes = sizes.ok_or("missing sizes")?;
+    let data = match data_file {
+        Some(data_file) => {
+            let path = Path::new(filename)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(data_file);
+            let bytes = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            decode(&bytes, &kind, little_endian)?
+        }
+        None => decode(&bytes[offset.min(bytes.len())..], &kind, little_endian)?,
+    };
+    Ok((data, sizes))
+}
+
+fn decode(bytes: &[u8], kind: &str, little_endian: bool) -> Result<Vec<f32>, String> {
+    match kind {
+        "uchar" | "unsigned char" | "uint8" | "uint8_t" => {
+            Ok(bytes.iter().map(|&b| b as f32 / 255.0).collect())
+        }
+        "float" => Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| {
+                let chunk = [chunk[0], chunk[1], chunk[2], chunk[3]];
+                if little_endian {
+                    f32::from_le_bytes(chunk)
+                } else {
+                    f32::from_be_bytes(chunk)
+                }
+            })
+            .collect()),
+        _ => Err(format!(
+            "unsupported type `{}`, expected uchar or float",
+            kind
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nrrd(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = header.as_bytes().to_vec();
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn reads_inline_bytes() {
+        let bytes = nrrd(
+            "NRRD0004\n# a comment\ntype: uchar\ndimension: 3\nsizes: 2 1 2\nencoding: raw\nspace:=unused\n\n",
+            &[0, 51, 255, 102],
+        );
+        let (data, sizes) = read_nrrd("grid.nrrd", &bytes).unwrap();
+        assert_eq!(sizes, [2, 1, 2]);
+        assert_eq!(data, vec![0.0, 0.2, 1.0, 0.4]);
+    }
+
+    #[test]
+    fn reads_inline_big_endian_floats() {
+        let values = [0.5f32, -1.0, 2.0];
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        let bytes = nrrd(
+            "NRRD0004\r\ntype: float\r\ndimension: 3\r\nsizes: 3 1 1\r\nencoding: raw\r\nendian: big\r\n\r\n",
+            &data,
+        );
+        let (data, sizes) = read_nrrd("grid.nrrd", &bytes).unwrap();
+        assert_eq!(sizes, [3, 1, 1]);
+        assert_eq!(data, values);
+    }
+
+    #[test]
+    fn rejects_unsupported_headers() {
+        let header = |field: &str| {
+            nrrd(
+                &format!("NRRD0004\ntype: uchar\nsizes: 1 1 1\n{}\n\n", field),
+                &[0],
+            )
+        };
+        assert!(read_nrrd("grid.nrrd", &header("dimension: 2")).is_err());
+        assert!(read_nrrd("grid.nrrd", &header("encoding: gzip")).is_err());
+        assert!(read_nrrd("grid.nrrd", &header("sizes: 1 1")).is_err());
+        assert!(read_nrrd("grid.nrrd", &nrrd("P6\n\n", &[0])).is_err());
+        assert!(decode(&[0, 0], "short", true).is_err());
+    }
+
+    #[test]
+    fn clamps_negative_voxels() {
+        let bounds = AxisAlignedBoundingBox::new(Point3::zero(), Point3::new(1.0, 1.0, 1.0), None);
+        let grid = GridDensity::new(
+            vec![-3.0, f32::NAN, -1.0, 0.0],
+            [4, 1, 1],
+            bounds.clone(),
+            2.0,
+        )
+        .unwrap();
+        assert_eq!(*grid.data, vec![0.0; 4]);
+        assert_eq!(grid.max_value(), 0.0);
+        assert!(GridDensity::new(vec![1.0; 3], [2, 2, 1], bounds, 1.0).is_none());
+    }
+}
@@ -0,0 +1,16 @@
+use dyn_clonable::clonable;
+
+use crate::utils::vec::Point3;
+
+pub mod grid;
+pub mod noise;
+
+/// How dense a heterogeneous medium is at each point of its own space
+#[clonable]
+pub trait Density: Clone + Send + Sync {
+    /// The chance of scattering per unit of distance at `point`
+    fn value(&self, point: &Point3) -> f32;
+
+    /// An upper bound of `value` everywhere, which rays are tracked against
+    fn max_value(&self) -> f32;
+}
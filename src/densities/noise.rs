@@ -0,0 +1,36 @@
+use crate::{densities::Density, textures::noise::NoiseTexture, utils::vec::Point3};
+
+/// Clouds shaped by the Perlin fBm of a `NoiseTexture`, empty wherever the noise,
+/// brought to [0, 1], is below `coverage`, and up to `scale` where it is highest.
+#[derive(Clone)]
+pub struct NoiseDensity {
+    pub noise: NoiseTexture,
+    pub scale: f32,
+    pub coverage: f32,
+}
+
+impl NoiseDensity {
+    /// Create a new NoiseDensity
+    /// # Arguments
+    /// * `noise` - The noise the density follows, its own `scale` is ignored
+    /// * `scale` - The densest the medium gets
+    /// * `coverage` - The share of the noise range left empty, from 0 (none) to 1 (all)
+    pub fn new(noise: NoiseTexture, scale: f32, coverage: f32) -> Self {
+        Self {
+            noise,
+            scale,
+            coverage: coverage.clamp(0.0, 0.999),
+        }
+    }
+}
+
+impl Density for NoiseDensity {
+    fn value(&self, point: &Point3) -> f32 {
+        let noise = 0.5 * (self.noise.noise(point) + 1.0);
+        self.scale * ((noise - self.coverage) / (1.0 - self.coverage)).clamp(0.0, 1.0)
+    }
+
+    fn max_value(&self) -> f32 {
+        self.scale
+    }
+}
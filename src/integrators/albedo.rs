@@ -0,0 +1,22 @@
+use crate::{
+    integrators::Integrator,
+    scene::Scene,
+    utils::{hittable::Hittable, ray::Ray, splats::Splats, vec::Color},
+};
+
+/// Shows the unlit color of the surfaces seen by the camera, with their textures,
+/// and the background where it sees nothing
+#[derive(Debug, Clone, Copy)]
+pub struct AlbedoIntegrator;
+
+impl Integrator for AlbedoIntegrator {
+    fn color(&self, scene: &Scene, ray: &Ray, _splats: &mut Splats) -> Color {
+        match scene.world.hit(ray, 1e-3, f32::INFINITY) {
+            Some(hit) => match &hit.material {
+                Some(material) => material.albedo(&hit),
+                None => Color::zero(),
+            },
+            None => scene.background.color(&ray.direction.unit_vector()),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{
+    integrators::Integrator,
+    scene::Scene,
+    utils::{helpers::random_vector, hittable::Hittable, ray::Ray, splats::Splats, vec::Color},
+};
+
+/// Ambient occlusion: how much of the sky above the surfaces seen by the camera is
+/// left open by the objects within `radius` of them, weighted by the cosine like
+/// diffuse lighting. White when nothing is in the way.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusionIntegrator {
+    pub radius: f32,
+}
+
+impl AmbientOcclusionIntegrator {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn color(&self, scene: &Scene, ray: &Ray, _splats: &mut Splats) -> Color {
+        let hit = match scene.world.hit(ray, 1e-3, f32::INFINITY) {
+            Some(hit) => hit,
+            None => return Color::zero(),
+        };
+        // Cosine-weighted, so the fraction of unblocked rays is the occlusion itself
+        let mut direction = hit.normal + random_vector();
+        if direction.near_zero() {
+            direction = hit.normal;
+        }
+        let occlusion_ray = Ray::new(hit.point, direction.unit_vector(), ray.time);
+        match scene.world.hit(&occlusion_ray, 1e-3, self.radius) {
+            Some(_) => Color::zero(),
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
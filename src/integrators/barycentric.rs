@@ -0,0 +1,23 @@
+use crate::{
+    integrators::Integrator,
+    scene::Scene,
+    utils::{hittable::Hittable, ray::Ray, splats::Splats, vec::Color},
+};
+
+/// Shows where triangles are hit, with the weights of their first, second and
+/// third vertices in red, green and blue. Other shapes are black.
+#[derive(Debug, Clone, Copy)]
+pub struct BarycentricIntegrator;
+
+impl Integrator for BarycentricIntegrator {
+    fn color(&self, scene: &Scene, ray: &Ray, _splats: &mut Splats) -> Color {
+        match scene
+            .world
+            .hit(ray, 1e-3, f32::INFINITY)
+            .and_then(|hit| hit.barycentric)
+        {
+            Some((u, v)) => Color::new(1.0 - u - v, u, v),
+            None => Color::zero(),
+        }
+    }
+}
@@ -0,0 +1,753 @@
+use crate::{
+    integrators::Integrator,
+    materials::{dielectric::InteriorStack, Interior},
+    scene::Scene,
+    utils::{
+        helpers::random_float,
+        hittable::{HitRecord, Hittable},
+        ray::Ray,
+        spectrum::at_wavelength,
+        splats::Splats,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+/// Bidirectional path tracing: traces a path from the camera and another one
+/// from a light, and joins every vertex of one to every vertex of the other.
+/// Each way of building a path is weighted by MIS, so light which is hard to
+/// find from one end, such as caustics seen through a pinhole, is found from
+/// the other. Paths reaching the camera directly are splatted onto the film.
+/// Converges to the same image as the path tracer.
+///
+/// Like the path tracer, both paths keep track of the nested dielectrics they
+/// are inside of, and of the light those absorb along every segment,
+/// connections included.
+#[derive(Debug, Clone, Copy)]
+pub struct Bdpt {
+    /// The number of bounces after which paths are cut short
+    pub max_depth: i32,
+    /// The number of bounces before paths can be ended by Russian roulette
+    pub min_depth: i32,
+}
+
+impl Bdpt {
+    pub fn new(max_depth: i32, min_depth: i32) -> Self {
+        Self {
+            max_depth,
+            min_depth,
+        }
+    }
+}
+
+// What is at a vertex of a path
+#[derive(Debug)]
+enum Kind {
+    Camera,
+    // The start of a light path, at a single point for delta lights
+    Light { delta: bool },
+    // A surface, or a point inside a medium
+    Scatter(HitRecord),
+    // Light at infinity, in a unit direction leaving the scene
+    Infinite(Vec3),
+}
+
+#[derive(Debug)]
+struct Vertex {
+    kind: Kind,
+    point: Point3,
+    // The unit normal at the vertex, if it is on a surface
+    normal: Option<Vec3>,
+    // The light, or the importance, carried by the path up to the vertex
+    beta: Color,
+    // The probability density of the path picking the vertex, over area
+    pdf_forward: f32,
+    // The same, had the path been traced from the other end
+    pdf_reverse: f32,
+    // Whether the material scattered the path specularly, so the vertex cannot
+    // be connected to
+    delta: bool,
+    // The dielectric the path went through to reach the vertex, on the side
+    // `normal` faces, and the one on the other side of the surface
+    interior: Option<Interior>,
+    beyond: Option<Interior>,
+}
+
+impl Vertex {
+    // The unit vector from this vertex toward another one
+    fn direction_to(&self, other: &Vertex) -> Vec3 {
+        match other.kind {
+            Kind::Infinite(direction) => direction,
+            _ => (other.point - self.point).unit_vector(),
+        }
+    }
+
+    // Turns the density, over solid angle, of picking the direction toward
+    // `next` from this vertex into a density over area at `next`
+    fn to_area(&self, pdf: f32, next: &Vertex) -> f32 {
+        if let Kind::Infinite(_) = next.kind {
+            return pdf;
+        }
+        let offset = next.point - self.point;
+        let distance_squared = offset.length_squared();
+        if distance_squared <= 0.0 {
+            return 0.0;
+        }
+        let cosine = match next.normal {
+            Some(normal) => normal.dot(&offset).abs() / distance_squared.sqrt(),
+            None => 1.0,
+        };
+        pdf * cosine / distance_squared
+    }
+
+    // The unit direction reaching this vertex from `previous`
+    fn incoming(&self, previous: &Vertex) -> Vec3 {
+        -self.direction_to(previous)
+    }
+
+    // The share of light left after going straight to `other`, through the
+    // dielectric on that side of the surface
+    fn transmittance_to(&self, other: &Vertex, wavelength: Option<f32>) -> Color {
+        let interior = match self.normal {
+            Some(normal) if normal.dot(&self.direction_to(other)) < 0.0 => self.beyond,
+            _ => self.interior,
+        };
+        match interior {
+            Some(interior) => {
+                // Lights at infinity are as far as floats go
+                let distance = (other.point - self.point).length().min(f32::MAX);
+                at_wavelength(interior.transmittance(distance), wavelength)
+            }
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    // The BSDF times the cosine of the unit `out` direction, for a path arriving
+    // along the unit `incoming` direction
+    fn eval_along(&self, incoming: &Vec3, out: &Vec3, wavelength: Option<f32>) -> Color {
+        let hit = match &self.kind {
+            Kind::Scatter(hit) if !self.delta => hit,
+            _ => return Color::zero(),
+        };
+        match &hit.material {
+            Some(material) => at_wavelength(material.eval(incoming, out, hit), wavelength),
+            None => Color::zero(),
+        }
+    }
+
+    // The same for light paths, which carry light the other way around: light
+    // arrives along `incoming` and leaves along `out`. Materials are not always
+    // symmetric, such as fuzzy metals, so the BSDF is evaluated backwards.
+    fn eval_adjoint(&self, incoming: &Vec3, out: &Vec3, wavelength: Option<f32>) -> Color {
+        let forward = self.eval_along(incoming, out, wavelength);
+        let normal = match self.normal {
+            Some(normal) if !forward.near_zero() => normal,
+            // Phase functions only depend on the angle between both directions
+            _ => return forward,
+        };
+        let cosine = normal.dot(incoming).abs();
+        if cosine <= 0.0 {
+            return Color::zero();
+        }
+        self.eval_along(&-*out, &-*incoming, wavelength) * (normal.dot(out).abs() / cosine)
+    }
+
+    // The probability density of this vertex picking `next`, over area, when
+    // the path reached it from `previous`
+    fn pdf(&self, scene: &Scene, previous: Option<&Vertex>, next: &Vertex) -> f32 {
+        let direction = self.direction_to(next);
+        let pdf = match (&self.kind, previous) {
+            (Kind::Camera, _) => scene.camera.pdf(&direction),
+            (Kind::Scatter(hit), Some(previous)) => match &hit.material {
+                Some(material) => material.pdf(&self.incoming(previous), &direction, hit),
+                None => 0.0,
+            },
+            _ => 0.0,
+        };
+        self.to_area(pdf, next)
+    }
+
+    // The BSDF times the cosine toward `next`, the path having reached this
+    // vertex from `previous`
+    fn eval(&self, previous: &Vertex, next: &Vertex, wavelength: Option<f32>) -> Color {
+        self.eval_along(
+            &-self.direction_to(previous),
+            &self.direction_to(next),
+            wavelength,
+        )
+    }
+
+    // The same for a vertex of a light path, with light coming from `previous`
+    fn eval_light(&self, previous: &Vertex, next: &Vertex, wavelength: Option<f32>) -> Color {
+        self.eval_adjoint(
+            &-self.direction_to(previous),
+            &self.direction_to(next),
+            wavelength,
+        )
+    }
+
+    fn is_connectible(&self) -> bool {
+        matches!(self.kind, Kind::Scatter(_)) && !self.delta
+    }
+}
+
+// The densities of a connected path which differ from those of its two
+// subpaths, around the vertices joined together
+struct Connection {
+    // The reverse densities of the last two camera vertices
+    camera_reverse: f32,
+    camera_previous_reverse: f32,
+    // The reverse densities of the last two light vertices
+    light_reverse: f32,
+    light_previous_reverse: f32,
+    // For a light vertex sampled for the connection rather than taken from the
+    // light path: its forward density, and whether the light is a single point
+    // or direction, which camera paths can never find
+    light_forward: f32,
+    delta_light: bool,
+    // The most vertices a light path could have had: lights at infinity and
+    // lights missing from the scene's list never start paths
+    max_light_vertices: usize,
+}
+
+impl Integrator for Bdpt {
+    fn color(&self, scene: &Scene, ray: &Ray, splats: &mut Splats) -> Color {
+        let camera_path = self.camera_path(scene, ray);
+        let light_path = self.light_path(scene, ray.time, ray.wavelength);
+
+        let mut radiance = Color::zero();
+        for t in 1..=camera_path.len() {
+            // Lights are sampled afresh when joining a single light vertex, so
+            // even without a light path
+            for s in 0..=light_path.len().max(1) {
+                let depth = (s + t) as i32 - 2;
+                if (s == 1 && t == 1) || depth < 0 || depth > self.max_depth {
+                    continue;
+                }
+                // Only the lights at infinity are found past the scene
+                if s > 0 && matches!(camera_path[t - 1].kind, Kind::Infinite(_)) {
+                    continue;
+                }
+                radiance += self.connect(scene, &camera_path[..t], &light_path, s, ray, splats);
+            }
+        }
+        radiance
+    }
+}
+
+impl Bdpt {
+    fn camera_path(&self, scene: &Scene, ray: &Ray) -> Vec<Vertex> {
+        let mut path = vec![Vertex {
+            kind: Kind::Camera,
+            point: ray.origin,
+            normal: None,
+            beta: Color::new(1.0, 1.0, 1.0),
+            pdf_forward: 1.0,
+            pdf_reverse: 0.0,
+            delta: false,
+            interior: None,
+            beyond: None,
+        }];
+        let pdf = scene.camera.pdf(&ray.direction);
+        self.random_walk(
+            scene,
+            *ray,
+            Color::new(1.0, 1.0, 1.0),
+            pdf,
+            self.max_depth as usize + 2,
+            &mut path,
+        );
+        path
+    }
+
+    // A path leaving a light picked at random, empty when that light cannot
+    // start paths
+    fn light_path(&self, scene: &Scene, time: f32, wavelength: Option<f32>) -> Vec<Vertex> {
+        let mut path = Vec::new();
+        let count = scene.light_count();
+        if count == 0 {
+            return path;
+        }
+        let index = ((random_float() * count as f32) as usize).min(count - 1);
+        let emission = match scene.lights.lights.get(index) {
+            Some(light) => light.sample_emission(time),
+            None => None,
+        };
+        let emission = match emission {
+            Some(emission) if emission.pdf_position > 0.0 && emission.pdf_direction > 0.0 => {
+                emission
+            }
+            _ => return path,
+        };
+
+        let pdf_position = emission.pdf_position / count as f32;
+        let radiance = at_wavelength(emission.radiance, wavelength);
+        let cosine = emission
+            .normal
+            .map_or(1.0, |normal| normal.dot(&emission.ray.direction).abs());
+        path.push(Vertex {
+            kind: Kind::Light {
+                delta: emission.delta,
+            },
+            point: emission.ray.origin,
+            normal: emission.normal,
+            beta: radiance,
+            pdf_forward: pdf_position,
+            pdf_reverse: 0.0,
+            delta: false,
+            interior: None,
+            beyond: None,
+        });
+        self.random_walk(
+            scene,
+            Ray {
+                wavelength,
+                ..emission.ray
+            },
+            radiance * (cosine / (pdf_position * emission.pdf_direction)),
+            emission.pdf_direction,
+            self.max_depth as usize + 1,
+            &mut path,
+        );
+        path
+    }
+
+    // Extends a path by bouncing `ray` around the scene, which left its last
+    // vertex with the given pdf over solid angle. Camera paths keep a last
+    // vertex at infinity when they leave the scene.
+    fn random_walk(
+        &self,
+        scene: &Scene,
+        ray: Ray,
+        beta: Color,
+        pdf: f32,
+        max_vertices: usize,
+        path: &mut Vec<Vertex>,
+    ) {
+        let mut ray = ray;
+        let mut beta = beta;
+        let mut pdf_forward = pdf;
+        let wavelength = ray.wavelength;
+        let from_camera = matches!(path[0].kind, Kind::Camera);
+        // Both paths start outside of every dielectric, like the path tracer's
+        let mut interiors = InteriorStack::default();
+
+        for bounce in 0.. {
+            if path.len() >= max_vertices {
+                break;
+            }
+            let hit = match scene.world.hit(&ray, 1e-3, f32::INFINITY) {
+                Some(hit) => hit,
+                None => {
+                    if from_camera {
+                        path.push(Vertex {
+                            kind: Kind::Infinite(ray.direction.unit_vector()),
+                            point: ray.origin,
+                            normal: None,
+                            beta,
+                            pdf_forward,
+                            pdf_reverse: 0.0,
+                            delta: false,
+                            interior: interiors.current().copied(),
+                            beyond: interiors.current().copied(),
+                        });
+                    }
+                    break;
+                }
+            };
+            // Light is absorbed along the way inside tinted dielectrics
+            let interior = interiors.current().copied();
+            if let Some(interior) = interior {
+                let distance = hit.t * ray.direction.length();
+                beta *= at_wavelength(interior.transmittance(distance), wavelength);
+            }
+            let previous = path.len() - 1;
+            let material = hit.material.clone();
+            let beyond = match material.as_ref().and_then(|material| material.interior()) {
+                Some(crossed) => interiors.beyond(&hit, crossed),
+                None => interior,
+            };
+            let sample = material
+                .as_ref()
+                .and_then(|material| match material.interior() {
+                    Some(crossed) => interiors.scatter(&ray, &hit, crossed, material.as_ref()),
+                    None => material.sample(&ray, &hit),
+                });
+            // The density of the path going the other way, coming back along the
+            // scattered ray and leaving toward the previous vertex
+            let pdf_reverse = match (&sample, &material) {
+                (Some(sample), Some(material)) if !sample.specular => {
+                    material.pdf(&-sample.direction, &-ray.direction.unit_vector(), &hit)
+                }
+                _ => 0.0,
+            };
+            let point = hit.point;
+            let mut vertex = Vertex {
+                point,
+                normal: (!hit.in_medium).then_some(hit.normal),
+                kind: Kind::Scatter(hit),
+                beta,
+                pdf_forward: 0.0,
+                pdf_reverse: 0.0,
+                delta: false,
+                interior,
+                beyond,
+            };
+            vertex.pdf_forward = path[previous].to_area(pdf_forward, &vertex);
+            path[previous].pdf_reverse = vertex.to_area(pdf_reverse, &path[previous]);
+
+            let sample = match sample {
+                Some(sample) => sample,
+                None => {
+                    path.push(vertex);
+                    break;
+                }
+            };
+            // Specular bounces are never picked by a connection, their densities
+            // only come up as ratios which cancel out
+            vertex.delta = sample.specular;
+            pdf_forward = sample.pdf;
+            let attenuation = if !from_camera && !sample.specular && sample.pdf > 0.0 {
+                let incoming = ray.direction.unit_vector();
+                vertex.eval_adjoint(&incoming, &sample.direction, wavelength) / sample.pdf
+            } else {
+                at_wavelength(sample.throughput, wavelength)
+            };
+            path.push(vertex);
+            beta *= attenuation;
+
+            // Past the first bounces, end paths carrying little light at random, and make
+            // up for it in the ones which go on
+            if bounce + 1 >= self.min_depth {
+                let survival = beta.max_component().min(1.0);
+                if survival <= 0.0 || random_float() >= survival {
+                    break;
+                }
+                beta /= survival;
+            }
+
+            ray = Ray {
+                wavelength,
+                ..Ray::new(point, sample.direction, ray.time)
+            };
+        }
+    }
+
+    // The light carried by the path made of the first `s` vertices of the light
+    // path and all of `camera`, weighted by MIS. Light reaching the camera
+    // vertex directly is splatted rather than returned.
+    fn connect(
+        &self,
+        scene: &Scene,
+        camera: &[Vertex],
+        light: &[Vertex],
+        s: usize,
+        ray: &Ray,
+        splats: &mut Splats,
+    ) -> Color {
+        let t = camera.len();
+        let wavelength = ray.wavelength;
+        let time = ray.time;
+        let count = scene.light_count() as f32;
+        let pt = &camera[t - 1];
+
+        if s == 0 {
+            // The camera path found a light by itself
+            let pt_minus = &camera[t - 2];
+            let (emitted, connection) = match &pt.kind {
+                Kind::Infinite(direction) => {
+                    let escaped = Ray::new(pt_minus.point, *direction, time);
+                    let emitted = scene.escaped(&escaped, None);
+                    let connection = Connection {
+                        camera_reverse: scene.infinite_light_pdf(&escaped),
+                        camera_previous_reverse: 0.0,
+                        light_reverse: 0.0,
+                        light_previous_reverse: 0.0,
+                        light_forward: 0.0,
+                        delta_light: false,
+                        max_light_vertices: 1,
+                    };
+                    (emitted, connection)
+                }
+                Kind::Scatter(hit) => {
+                    let emitted = match &hit.material {
+                        Some(material) => material.emitted(hit.u, hit.v, &hit.point),
+                        None => return Color::zero(),
+                    };
+                    if emitted.near_zero() {
+                        return Color::zero();
+                    }
+                    let incoming = Ray::new(pt_minus.point, pt.point - pt_minus.point, time);
+                    let emission = scene
+                        .lights
+                        .lights
+                        .iter()
+                        .find_map(|light| light.emission(&incoming, 1.0));
+                    let connection = match emission {
+                        Some(emission) => Connection {
+                            camera_reverse: emission.pdf_position / count,
+                            camera_previous_reverse: pt.to_area(emission.pdf_direction, pt_minus),
+                            light_reverse: 0.0,
+                            light_previous_reverse: 0.0,
+                            light_forward: 0.0,
+                            delta_light: false,
+                            max_light_vertices: usize::MAX,
+                        },
+                        // Emissive shapes which are not sampled as lights are
+                        // only ever found this way
+                        None => Connection {
+                            camera_reverse: 0.0,
+                            camera_previous_reverse: 0.0,
+                            light_reverse: 0.0,
+                            light_previous_reverse: 0.0,
+                            light_forward: 0.0,
+                            delta_light: false,
+                            max_light_vertices: 0,
+                        },
+                    };
+                    (emitted, connection)
+                }
+                _ => return Color::zero(),
+            };
+            let contribution = pt.beta * at_wavelength(emitted, wavelength);
+            if contribution.near_zero() {
+                return Color::zero();
+            }
+            return contribution * mis_weight(camera, light, s, &connection);
+        }
+
+        if s == 1 {
+            // Next-event estimation, with a light vertex sampled from `pt`
+            if !pt.is_connectible() || count == 0.0 {
+                return Color::zero();
+            }
+            let pt_minus = &camera[t - 2];
+            let index = ((random_float() * count) as usize).min(scene.light_count() - 1);
+            let sample = match scene.sample_light_at(index, &pt.point, time) {
+                Some(sample) if sample.pdf > 0.0 => sample,
+                _ => return Color::zero(),
+            };
+            let toward = Ray::new(pt.point, sample.direction, time);
+            let qs = Vertex {
+                kind: if sample.distance.is_finite() {
+                    Kind::Light {
+                        delta: sample.delta,
+                    }
+                } else {
+                    Kind::Infinite(sample.direction)
+                },
+                point: toward.at(sample.distance.min(f32::MAX)),
+                normal: None,
+                beta: Color::zero(),
+                pdf_forward: 0.0,
+                pdf_reverse: 0.0,
+                delta: sample.delta,
+                interior: None,
+                beyond: None,
+            };
+            let bsdf = pt.eval(pt_minus, &qs, wavelength);
+            if bsdf.near_zero() {
+                return Color::zero();
+            }
+            let transmittance = sample.transmittance(&scene.world, &pt.point, time);
+            if transmittance <= 0.0 {
+                return Color::zero();
+            }
+            let contribution = pt.beta
+                * bsdf
+                * pt.transmittance_to(&qs, wavelength)
+                * at_wavelength(sample.radiance, wavelength)
+                * (transmittance * count / sample.pdf);
+
+            let emission = scene
+                .lights
+                .lights
+                .get(index)
+                .filter(|_| sample.distance.is_finite())
+                .and_then(|light| light.emission(&toward, sample.distance));
+            let connection = match emission {
+                Some(emission) => {
+                    let qs = Vertex {
+                        normal: emission.normal,
+                        ..qs
+                    };
+                    Connection {
+                        camera_reverse: qs.to_area(emission.pdf_direction, pt),
+                        camera_previous_reverse: pt.pdf(scene, Some(&qs), pt_minus),
+                        light_reverse: pt.pdf(scene, Some(pt_minus), &qs),
+                        light_previous_reverse: 0.0,
+                        light_forward: emission.pdf_position / count,
+                        delta_light: emission.delta,
+                        max_light_vertices: usize::MAX,
+                    }
+                }
+                None => Connection {
+                    camera_reverse: 0.0,
+                    camera_previous_reverse: pt.pdf(scene, Some(&qs), pt_minus),
+                    light_reverse: pt.pdf(scene, Some(pt_minus), &qs),
+                    light_previous_reverse: 0.0,
+                    light_forward: if sample.distance.is_finite() {
+                        0.0
+                    } else {
+                        scene.infinite_light_pdf(&toward)
+                    },
+                    delta_light: sample.delta,
+                    max_light_vertices: 1,
+                },
+            };
+            return contribution * mis_weight(camera, light, s, &connection);
+        }
+
+        let qs = &light[s - 1];
+        let qs_minus = &light[s - 2];
+        if !qs.is_connectible() {
+            return Color::zero();
+        }
+
+        if t == 1 {
+            // Light tracing, straight into the camera
+            let (u, v) = match scene.camera.project(&qs.point) {
+                Some(uv) => uv,
+                None => return Color::zero(),
+            };
+            let offset = qs.point - pt.point;
+            let distance = offset.length();
+            let importance = scene.camera.pdf(&offset);
+            let bsdf = qs.eval_light(qs_minus, pt, wavelength);
+            if bsdf.near_zero() || importance <= 0.0 {
+                return Color::zero();
+            }
+            let transmittance = visibility(scene, pt, qs, time);
+            if transmittance <= 0.0 {
+                return Color::zero();
+            }
+            let contribution = qs.beta
+                * bsdf
+                * qs.transmittance_to(pt, wavelength)
+                * (importance * transmittance / (distance * distance));
+            let connection = Connection {
+                camera_reverse: 0.0,
+                camera_previous_reverse: 0.0,
+                light_reverse: pt.pdf(scene, None, qs),
+                light_previous_reverse: qs.pdf(scene, Some(pt), qs_minus),
+                light_forward: 0.0,
+                delta_light: false,
+                max_light_vertices: usize::MAX,
+            };
+            splats.add(
+                u,
+                v,
+                contribution * mis_weight(camera, light, s, &connection),
+            );
+            return Color::zero();
+        }
+
+        // Both paths meet in the middle
+        if !pt.is_connectible() {
+            return Color::zero();
+        }
+        let pt_minus = &camera[t - 2];
+        let distance_squared = (qs.point - pt.point).length_squared();
+        let contribution = qs.beta
+            * qs.eval_light(qs_minus, pt, wavelength)
+            * pt.eval(pt_minus, qs, wavelength)
+            * pt.beta
+            / distance_squared;
+        if contribution.near_zero() {
+            return Color::zero();
+        }
+        let transmittance = visibility(scene, pt, qs, time);
+        if transmittance <= 0.0 {
+            return Color::zero();
+        }
+        let connection = Connection {
+            camera_reverse: qs.pdf(scene, Some(qs_minus), pt),
+            camera_previous_reverse: pt.pdf(scene, Some(qs), pt_minus),
+            light_reverse: pt.pdf(scene, Some(pt_minus), qs),
+            light_previous_reverse: qs.pdf(scene, Some(pt), qs_minus),
+            light_forward: 0.0,
+            delta_light: false,
+            max_light_vertices: usize::MAX,
+        };
+        contribution
+            * pt.transmittance_to(qs, wavelength)
+            * (transmittance * mis_weight(camera, light, s, &connection))
+    }
+}
+
+// The share of light going from one vertex to the other
+fn visibility(scene: &Scene, from: &Vertex, to: &Vertex, time: f32) -> f32 {
+    let offset = to.point - from.point;
+    let distance = offset.length();
+    let ray = Ray::new(from.point, offset / distance, time);
+    scene
+        .world
+        .transmittance(&ray, 1e-3, distance * (1.0 - 1e-3))
+}
+
+// The weight of the path built from `s` light vertices and all of `camera`, by
+// the power heuristic against every other way of building it. The densities
+// of the other ways are found from the ratios of neighbouring ones, moving the
+// connection one vertex at a time.
+fn mis_weight(camera: &[Vertex], light: &[Vertex], s: usize, connection: &Connection) -> f32 {
+    let t = camera.len();
+    // Specular vertices have no density, which cancels out in the ratios
+    let remap = |pdf: f32| if pdf != 0.0 { pdf } else { 1.0 };
+    let camera_vertex = |i: usize| {
+        let vertex = &camera[i];
+        let reverse = if i + 1 == t {
+            connection.camera_reverse
+        } else if i + 2 == t {
+            connection.camera_previous_reverse
+        } else {
+            vertex.pdf_reverse
+        };
+        (vertex.pdf_forward, reverse, vertex.delta && i + 1 != t)
+    };
+    let light_vertex = |i: usize| {
+        if s == 1 {
+            return (connection.light_forward, connection.light_reverse, false);
+        }
+        let vertex = &light[i];
+        let reverse = if i + 1 == s {
+            connection.light_reverse
+        } else if i + 2 == s {
+            connection.light_previous_reverse
+        } else {
+            vertex.pdf_reverse
+        };
+        (vertex.pdf_forward, reverse, vertex.delta && i + 1 != s)
+    };
+
+    let mut sum = 0.0;
+    let mut ratio = 1.0;
+    for i in (1..t).rev() {
+        // The way with the camera path ending at `i`
+        if s + t - i > connection.max_light_vertices {
+            break;
+        }
+        let (forward, reverse, delta) = camera_vertex(i);
+        ratio *= remap(reverse) / remap(forward);
+        // A single light vertex is never joined to the camera
+        let light_to_camera = i == 1 && s + t == 2;
+        if !delta && !camera_vertex(i - 1).2 && !light_to_camera {
+            sum += ratio * ratio;
+        }
+    }
+    let mut ratio = 1.0;
+    for i in (0..s).rev() {
+        // The way with the light path ending before `i`
+        let (forward, reverse, delta) = light_vertex(i);
+        ratio *= remap(reverse) / remap(forward);
+        let delta_light = if i > 0 {
+            light_vertex(i - 1).2
+        } else if s == 1 {
+            connection.delta_light
+        } else {
+            matches!(light[0].kind, Kind::Light { delta: true })
+        };
+        if !delta && !delta_light {
+            sum += ratio * ratio;
+        }
+    }
+    1.0 / (1.0 + sum)
+}
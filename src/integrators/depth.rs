@@ -0,0 +1,31 @@
+use crate::{
+    integrators::Integrator,
+    scene::Scene,
+    utils::{hittable::Hittable, ray::Ray, splats::Splats, vec::Color},
+};
+
+/// Shows the distance from the camera to the surfaces it sees, white right in
+/// front of it and fading to black at `far`
+#[derive(Debug, Clone, Copy)]
+pub struct DepthIntegrator {
+    pub far: f32,
+}
+
+impl DepthIntegrator {
+    pub fn new(far: f32) -> Self {
+        Self { far }
+    }
+}
+
+impl Integrator for DepthIntegrator {
+    fn color(&self, scene: &Scene, ray: &Ray, _splats: &mut Splats) -> Color {
+        match scene.world.hit(ray, 1e-3, f32::INFINITY) {
+            Some(hit) => {
+                let distance = hit.t * ray.direction.length();
+                let brightness = (1.0 - distance / self.far).max(0.0);
+                Color::new(brightness, brightness, brightness)
+            }
+            None => Color::zero(),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::Debug;
+
+use crate::{
+    scene::Scene,
+    utils::{ray::Ray, splats::Splats, vec::Color},
+};
+
+pub mod albedo;
+pub mod ao;
+pub mod barycentric;
+pub mod bdpt;
+pub mod depth;
+pub mod normals;
+pub mod path;
+pub mod uv;
+
+/// Computes the color seen along the rays leaving the camera, either the light
+/// reaching it or some property of the surfaces, for debugging
+pub trait Integrator: Send + Sync {
+    /// The color seen along a camera ray
+    /// # Arguments
+    /// * `scene` - The objects, lights and background to trace through
+    /// * `ray` - The ray leaving the camera
+    /// * `splats` - Where to add light reaching the camera through other pixels
+    fn color(&self, scene: &Scene, ray: &Ray, splats: &mut Splats) -> Color;
+}
+
+impl Debug for dyn Integrator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Integrator").finish()
+    }
+}
+
+/// The weight of an estimate picked with a pdf of `a`, against another strategy
+/// which would have picked it with a pdf of `b`
+pub fn power_heuristic(a: f32, b: f32) -> f32 {
+    if a <= 0.0 {
+        return 0.0;
+    }
+    // Written with the ratio of the pdfs, which stays finite for very peaked ones
+    let ratio = b / a;
+    1.0 / (1.0 + ratio * ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_heuristic_weights_sum_to_one() {
+        for (a, b) in [
+            (1.0, 1.0),
+            (0.3, 7.0),
+            (1e-6, 1e3),
+            (25.0, 0.0),
+            (1e30, 1e-30),
+        ] {
+            let sum = power_heuristic(a, b) + power_heuristic(b, a);
+            assert!((sum - 1.0).abs() < 1e-6, "{} + {}", a, b);
+        }
+    }
+
+    #[test]
+    fn power_heuristic_favors_the_likelier_strategy() {
+        assert_eq!(power_heuristic(1.0, 1.0), 0.5);
+        assert!((power_heuristic(3.0, 1.0) - 0.9).abs() < 1e-6);
+        // Strategies which cannot pick a direction get nothing
+        assert_eq!(power_heuristic(0.0, 1.0), 0.0);
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+        assert!(power_heuristic(1e-30, 1e30).is_finite());
+    }
+}
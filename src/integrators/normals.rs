@@ -0,0 +1,26 @@
+use crate::{
+    integrators::Integrator,
+    scene::Scene,
+    utils::{hittable::Hittable, ray::Ray, splats::Splats, vec::Color},
+};
+
+/// Shows the shading normals of the surfaces seen by the camera, facing outward
+/// whatever side they are seen from, with each axis mapped from [-1, 1] to [0, 1]
+#[derive(Debug, Clone, Copy)]
+pub struct NormalsIntegrator;
+
+impl Integrator for NormalsIntegrator {
+    fn color(&self, scene: &Scene, ray: &Ray, _splats: &mut Splats) -> Color {
+        match scene.world.hit(ray, 1e-3, f32::INFINITY) {
+            Some(hit) => {
+                let normal = if hit.front_face {
+                    hit.normal
+                } else {
+                    -hit.normal
+                };
+                (normal + Color::new(1.0, 1.0, 1.0)) * 0.5
+            }
+            None => Color::zero(),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use crate::{
+    integrators::{power_heuristic, Integrator},
+    materials::{dielectric::InteriorStack, Material},
+    scene::Scene,
+    utils::{
+        helpers::random_float,
+        hittable::{HitRecord, Hittable},
+        photons::PhotonMap,
+        ray::Ray,
+        spectrum::at_wavelength,
+        splats::Splats,
+        vec::Color,
+    },
+};
+
+/// Traces paths bouncing around the scene from the camera, sampling the lights
+/// at every bounce. Converges to the actual image.
+#[derive(Debug, Clone)]
+pub struct PathTracer {
+    /// The number of bounces after which paths are cut short
+    pub max_depth: i32,
+    /// The number of bounces before paths can be ended by Russian roulette
+    pub min_depth: i32,
+    /// Photons which reached surfaces through specular bounces, to look up the
+    /// caustics there instead of finding them by chance
+    pub caustics: Option<Arc<PhotonMap>>,
+}
+
+impl PathTracer {
+    pub fn new(max_depth: i32, min_depth: i32) -> Self {
+        Self {
+            max_depth,
+            min_depth,
+            caustics: None,
+        }
+    }
+
+    /// Look up caustics in a photon map, see `photons::trace_photons`
+    pub fn with_caustics(mut self, caustics: Arc<PhotonMap>) -> Self {
+        self.caustics = Some(caustics);
+        self
+    }
+}
+
+impl Integrator for PathTracer {
+    fn color(&self, scene: &Scene, ray: &Ray, _splats: &mut Splats) -> Color {
+        let mut radiance = Color::zero();
+        // The share of the light found further along the path making it back to the camera
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // The pdf of the material which picked `ray`, `None` for camera rays and specular
+        // bounces. Lights found by such rays were also sampled directly at the previous
+        // bounce, so both estimates are weighted by MIS.
+        let mut scatter_pdf = None;
+        let mut interiors = InteriorStack::default();
+        // Whether the path bounced off a diffuse or glossy surface yet, whether
+        // caustics were looked up at the last one, and whether it only bounced
+        // off specular surfaces since: lights it finds are then already in the
+        // caustics
+        let mut scattered = false;
+        let mut gathered = false;
+        let mut caustic = false;
+        // In spectral mode, colors only count at the wavelength of the path
+        let wavelength = ray.wavelength;
+
+        for depth in 0..self.max_depth {
+            // Ignore hits right at the origin, which come from floating point error when a
+            // scattered ray re-intersects the surface it starts on (shadow acne)
+            let hit = match scene.world.hit(&ray, 1e-3, f32::INFINITY) {
+                Some(hit) => hit,
+                None => {
+                    radiance +=
+                        throughput * at_wavelength(scene.escaped(&ray, scatter_pdf), wavelength);
+                    break;
+                }
+            };
+            // Light is absorbed along the way inside tinted dielectrics
+            if let Some(interior) = interiors.current() {
+                let distance = hit.t * ray.direction.length();
+                throughput *= at_wavelength(interior.transmittance(distance), wavelength);
+            }
+            let material = match hit.material.clone() {
+                Some(material) => material,
+                None => break,
+            };
+
+            // Lights add their own energy to whatever they reflect, unless the path
+            // reached them through caustics which were already looked up
+            let emitted = at_wavelength(material.emitted(hit.u, hit.v, &hit.point), wavelength);
+            let found = caustic && scene.light_pdf(&ray, hit.t) > 0.0;
+            if !emitted.near_zero() && !found {
+                radiance += throughput * emitted * light_weight(scene, &ray, hit.t, scatter_pdf);
+            }
+
+            let sample = match material.interior() {
+                Some(interior) => interiors.scatter(&ray, &hit, interior, material.as_ref()),
+                None => material.sample(&ray, &hit),
+            };
+            let sample = match sample {
+                Some(sample) => sample,
+                None => break,
+            };
+            if !sample.specular {
+                radiance += throughput * direct_lighting(scene, &ray, &hit, material.as_ref());
+                gathered = false;
+                // Caustics are only looked up where the camera first sees a rough
+                // surface, further bounces find what little of them they need
+                match &self.caustics {
+                    Some(caustics) if !scattered && !hit.in_medium => {
+                        radiance +=
+                            throughput * caustic_lighting(caustics, &ray, &hit, material.as_ref());
+                        gathered = true;
+                    }
+                    _ => {}
+                }
+                scattered = true;
+            }
+            caustic = gathered && sample.specular;
+            throughput *= at_wavelength(sample.throughput, wavelength);
+
+            // Past the first bounces, end paths carrying little light at random, and make
+            // up for it in the ones which go on so the image stays unbiased
+            if depth + 1 >= self.min_depth {
+                let survival = throughput.max_component().min(1.0);
+                if survival <= 0.0 || random_float() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+
+            scatter_pdf = (!sample.specular).then_some(sample.pdf);
+            ray = Ray {
+                wavelength,
+                ..Ray::new(hit.point, sample.direction, ray.time)
+            };
+        }
+
+        radiance
+    }
+}
+
+// The MIS weight of light found by `ray` at `distance`
+fn light_weight(scene: &Scene, ray: &Ray, distance: f32, scatter_pdf: Option<f32>) -> f32 {
+    match scatter_pdf {
+        Some(scatter_pdf) => power_heuristic(scatter_pdf, scene.light_pdf(ray, distance)),
+        None => 1.0,
+    }
+}
+
+// Next-event estimation: the light reaching a point straight from one of the
+// lights, picked at random, weighted against the material finding it by MIS
+fn direct_lighting(
+    scene: &Scene,
+    r_in: &Ray,
+    hit: &HitRecord,
+    material: &(dyn Material + Send + Sync),
+) -> Color {
+    let sample = match scene.sample_light(&hit.point, r_in.time) {
+        Some(sample) => sample,
+        None => return Color::zero(),
+    };
+    let bsdf = material.eval(&r_in.direction, &sample.direction, hit);
+    if bsdf.near_zero() {
+        return Color::zero();
+    }
+    let transmittance = sample.transmittance(&scene.world, &hit.point, r_in.time);
+    if transmittance <= 0.0 {
+        return Color::zero();
+    }
+    // Materials never scatter rays toward a point or a single direction
+    let weight = if sample.delta {
+        1.0
+    } else {
+        power_heuristic(
+            sample.pdf,
+            material.pdf(&r_in.direction, &sample.direction, hit),
+        )
+    };
+    at_wavelength(bsdf, r_in.wavelength)
+        * at_wavelength(sample.radiance, r_in.wavelength)
+        * (transmittance * weight / sample.pdf)
+}
+
+// The light reaching a point through specular bounces, from the photons around it
+fn caustic_lighting(
+    caustics: &PhotonMap,
+    r_in: &Ray,
+    hit: &HitRecord,
+    material: &(dyn Material + Send + Sync),
+) -> Color {
+    caustics.estimate(&hit.point, &hit.normal, |photon| {
+        let direction = -photon.direction;
+        let cosine = hit.normal.dot(&direction).abs();
+        if cosine <= 1e-4 {
+            return Color::zero();
+        }
+        // The material gives the BSDF times the cosine, which the photon's
+        // power already accounts for
+        at_wavelength(
+            material.eval(&r_in.direction, &direction, hit),
+            r_in.wavelength,
+        ) * at_wavelength(photon.power, r_in.wavelength)
+            / cosine
+    })
+}
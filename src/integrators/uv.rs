@@ -0,0 +1,19 @@
+use crate::{
+    integrators::Integrator,
+    scene::Scene,
+    utils::{hittable::Hittable, ray::Ray, splats::Splats, vec::Color},
+};
+
+/// Shows the texture coordinates of the surfaces seen by the camera, u in red and
+/// v in green
+#[derive(Debug, Clone, Copy)]
+pub struct UvIntegrator;
+
+impl Integrator for UvIntegrator {
+    fn color(&self, scene: &Scene, ray: &Ray, _splats: &mut Splats) -> Color {
+        match scene.world.hit(ray, 1e-3, f32::INFINITY) {
+            Some(hit) => Color::new(hit.u, hit.v, 0.0),
+            None => Color::zero(),
+        }
+    }
+}
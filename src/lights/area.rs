@@ -0,0 +1,207 @@
+use std::f32::consts::PI;
+
+use crate::{
+    lights::{EmissionSample, Light, LightSample},
+    utils::{
+        helpers::{random_cosine_direction, random_float},
+        hittable::Hittable,
+        onb::Onb,
+        ray::Ray,
+        vec::Point3,
+    },
+};
+
+/// A shape with an emissive material, sampled through its `random` and `pdf_value`.
+#[derive(Clone)]
+pub struct AreaLight {
+    pub shape: Box<dyn Hittable>,
+}
+
+impl AreaLight {
+    pub fn new(shape: Box<dyn Hittable>) -> Self {
+        Self { shape }
+    }
+}
+
+impl Light for AreaLight {
+    fn sample(&self, origin: &Point3, time: f32) -> Option<LightSample> {
+        let offset = self.shape.random(origin, time)?;
+        let distance = offset.length();
+        let direction = offset / distance;
+        let ray = Ray::new(*origin, direction, time);
+        // Find the sampled point again to read the emission of the material there
+        let hit = self
+            .shape
+            .hit(&ray, distance * (1.0 - 1e-3), distance * (1.0 + 1e-3))?;
+        let pdf = self.shape.pdf_value(&ray);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: hit.material?.emitted(hit.u, hit.v, &hit.point),
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, ray: &Ray, distance: f32) -> f32 {
+        // Only when the surface hit is this light's, rather than another one in front of it
+        let length = ray.direction.length();
+        match self.shape.hit(ray, 1e-4, f32::INFINITY) {
+            Some(hit)
+                if ((hit.t - distance) * length).abs() <= 1e-3 * (1.0 + distance * length) =>
+            {
+                self.shape.pdf_value(ray)
+            }
+            _ => 0.0,
+        }
+    }
+
+    // Light leaves both sides of the surface, with a cosine distribution
+    fn sample_emission(&self, time: f32) -> Option<EmissionSample> {
+        let (point, normal) = self.shape.sample_surface(time)?;
+        let normal = if random_float() < 0.5 {
+            normal
+        } else {
+            -normal
+        };
+        let direction = Onb::from_w(&normal).to_world(&random_cosine_direction());
+        // Find the sampled point again to read the emission of the material there
+        let emission = self.emission(&Ray::new(point + direction, -direction, time), 1.0)?;
+        // Shading normals are only for looks, directions follow the actual surface
+        Some(EmissionSample {
+            ray: Ray::new(point, direction, time),
+            normal: Some(normal),
+            pdf_direction: normal.dot(&direction) / (2.0 * PI),
+            ..emission
+        })
+    }
+
+    fn emission(&self, ray: &Ray, distance: f32) -> Option<EmissionSample> {
+        let hit = self
+            .shape
+            .hit(ray, distance * (1.0 - 1e-3), distance * (1.0 + 1e-3))?;
+        let direction = -ray.direction.unit_vector();
+        let pdf_position = self.shape.surface_pdf(&hit.point, &hit.normal, ray.time);
+        if pdf_position <= 0.0 {
+            return None;
+        }
+        Some(EmissionSample {
+            ray: Ray::new(hit.point, direction, ray.time),
+            normal: Some(hit.normal),
+            radiance: hit.material?.emitted(hit.u, hit.v, &hit.point),
+            pdf_position,
+            pdf_direction: hit.normal.dot(&direction).abs() / (2.0 * PI),
+            delta: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::diffuse_light::DiffuseLight,
+        shapes::{list::HittableList, mesh::TriangleMesh, sphere::Sphere},
+        utils::{helpers::random_vector, vec::Color},
+    };
+
+    const SAMPLES: usize = 20_000;
+
+    fn emission() -> Color {
+        Color::new(4.0, 2.0, 1.0)
+    }
+
+    fn sphere_light() -> AreaLight {
+        let material = Box::new(DiffuseLight::new(emission()));
+        AreaLight::new(Box::new(Sphere::new(
+            Point3::new(0.0, 3.0, 0.0),
+            1.0,
+            Some(material),
+        )))
+    }
+
+    // A unit square one unit above the origin, facing down
+    fn square_light() -> AreaLight {
+        let material = Box::new(DiffuseLight::new(emission()));
+        let positions = vec![
+            Point3::new(-0.5, 1.0, -0.5),
+            Point3::new(0.5, 1.0, -0.5),
+            Point3::new(0.5, 1.0, 0.5),
+            Point3::new(-0.5, 1.0, 0.5),
+        ];
+        AreaLight::new(Box::new(TriangleMesh::new(
+            positions,
+            None,
+            None,
+            None,
+            vec![[0, 1, 2], [0, 2, 3]],
+            Some(material),
+        )))
+    }
+
+    // Averages 1 / pdf over samples of the light, which estimates the solid angle
+    // it covers, checking each sample against `pdf` along the way
+    fn solid_angle(light: &AreaLight) -> f32 {
+        let origin = Point3::zero();
+        let mut total = 0.0;
+        for _ in 0..SAMPLES {
+            let sample = light.sample(&origin, 0.0).unwrap();
+            assert_eq!(sample.radiance, emission());
+            assert!(!sample.delta);
+            let ray = Ray::new(origin, sample.direction, 0.0);
+            let pdf = light.pdf(&ray, sample.distance);
+            assert!((pdf - sample.pdf).abs() <= 1e-3 * sample.pdf);
+            total += 1.0 / sample.pdf;
+        }
+        total / SAMPLES as f32
+    }
+
+    // Integrates `pdf` over the whole sphere of directions, with uniform samples
+    fn pdf_integral(light: &AreaLight) -> f32 {
+        let origin = Point3::zero();
+        let total: f32 = (0..SAMPLES * 10)
+            .map(|_| {
+                let ray = Ray::new(origin, random_vector(), 0.0);
+                match light.shape.hit(&ray, 1e-4, f32::INFINITY) {
+                    Some(hit) => light.pdf(&ray, hit.t),
+                    None => 0.0,
+                }
+            })
+            .sum();
+        4.0 * PI * total / (SAMPLES * 10) as f32
+    }
+
+    #[test]
+    fn samples_spheres_over_their_cone() {
+        let light = sphere_light();
+        let cos_theta_max = (1.0 - 1.0 / 9.0_f32).sqrt();
+        let expected = 2.0 * PI * (1.0 - cos_theta_max);
+        assert!((solid_angle(&light) - expected).abs() < 1e-3 * expected);
+        assert!((pdf_integral(&light) - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn samples_meshes_over_their_area() {
+        let light = square_light();
+        // The solid angle of a rectangle seen from above its center
+        let expected = 4.0 * (0.25_f32 / 1.25).asin();
+        assert!((solid_angle(&light) - expected).abs() < 0.03 * expected);
+        assert!((pdf_integral(&light) - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn shadow_rays_stop_at_occluders() {
+        let light = sphere_light();
+        let origin = Point3::zero();
+        let sample = light.sample(&origin, 0.0).unwrap();
+        let world = HittableList::new(vec![light.shape.clone()]);
+        assert_eq!(sample.transmittance(&world, &origin, 0.0), 1.0);
+
+        let blocker = Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 0.9, None));
+        let world = HittableList::new(vec![light.shape.clone(), blocker]);
+        assert_eq!(sample.transmittance(&world, &origin, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,86 @@
+use std::f32::consts::PI;
+
+use crate::{
+    lights::{Light, LightSample},
+    utils::{
+        helpers::random_cone_direction,
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+/// A light infinitely far away, like the sun, shining from the same direction
+/// on the whole scene. With an angular diameter, it is a disk in the sky which
+/// casts soft shadows; without one, shadows are perfectly sharp.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    /// Where the light goes to, from the light toward the scene
+    pub direction: Vec3,
+    /// The light received by a surface facing it
+    pub irradiance: Color,
+    cos_theta_max: f32,
+}
+
+impl DirectionalLight {
+    /// Create a new DirectionalLight
+    /// # Arguments
+    /// * `direction` - Where the light goes to, does not need to be normalized
+    /// * `irradiance` - The light received by a surface facing it
+    /// * `angle` - The angular diameter of the light in degrees, 0 for sharp shadows (the sun is about 0.53)
+    pub fn new(direction: Vec3, irradiance: Color, angle: f32) -> Self {
+        Self {
+            direction: direction.unit_vector(),
+            irradiance,
+            cos_theta_max: (angle.clamp(0.0, 180.0) / 2.0).to_radians().cos(),
+        }
+    }
+
+    fn is_delta(&self) -> bool {
+        self.cos_theta_max >= 1.0
+    }
+
+    // The solid angle covered by the disk of the light
+    fn solid_angle(&self) -> f32 {
+        2.0 * PI * (1.0 - self.cos_theta_max)
+    }
+
+    fn covers(&self, direction: &Vec3) -> bool {
+        -direction.unit_vector().dot(&self.direction) >= self.cos_theta_max
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _origin: &Point3, _time: f32) -> Option<LightSample> {
+        if self.is_delta() {
+            return Some(LightSample {
+                direction: -self.direction,
+                distance: f32::INFINITY,
+                radiance: self.irradiance,
+                pdf: 1.0,
+                delta: true,
+            });
+        }
+        let solid_angle = self.solid_angle();
+        Some(LightSample {
+            direction: random_cone_direction(&-self.direction, self.cos_theta_max),
+            distance: f32::INFINITY,
+            radiance: self.irradiance / solid_angle,
+            pdf: 1.0 / solid_angle,
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, ray: &Ray, distance: f32) -> f32 {
+        if self.is_delta() || distance.is_finite() || !self.covers(&ray.direction) {
+            return 0.0;
+        }
+        1.0 / self.solid_angle()
+    }
+
+    fn escaped(&self, ray: &Ray) -> Color {
+        if self.is_delta() || !self.covers(&ray.direction) {
+            return Color::zero();
+        }
+        self.irradiance / self.solid_angle()
+    }
+}
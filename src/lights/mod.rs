@@ -0,0 +1,130 @@
+use std::fmt::Debug;
+
+use dyn_clonable::clonable;
+
+use crate::{
+    shapes::list::HittableList,
+    utils::{
+        hittable::Hittable,
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+pub mod area;
+pub mod directional;
+pub mod point;
+pub mod spot;
+
+/// A direction toward a light, picked for next-event estimation
+#[derive(Debug, Copy, Clone)]
+pub struct LightSample {
+    /// Unit vector from the shaded point toward the light
+    pub direction: Vec3,
+    /// How far the light is along `direction`
+    pub distance: f32,
+    /// The light arriving from it, when nothing is in the way
+    pub radiance: Color,
+    /// The probability density of picking `direction`, over solid angle. For
+    /// `delta` lights, the probability of picking the light instead.
+    pub pdf: f32,
+    /// Whether the light is a single point or direction, which rays scattered
+    /// by materials can never find
+    pub delta: bool,
+}
+
+impl LightSample {
+    /// Traces a shadow ray toward the light, giving the share of its light which
+    /// reaches `origin`: 0 when something blocks it, less than 1 through media
+    /// # Arguments
+    /// * `world` - The objects that may cast a shadow, the light itself included
+    /// * `origin` - The shaded point
+    /// * `time` - The time of the shadow ray
+    pub fn transmittance(&self, world: &HittableList, origin: &Point3, time: f32) -> f32 {
+        let shadow_ray = Ray::new(*origin, self.direction, time);
+        // Stop just short of the light, which would otherwise shadow itself
+        world.transmittance(&shadow_ray, 1e-3, self.distance * (1.0 - 1e-3))
+    }
+}
+
+/// A ray leaving a light, picked to start a path from it
+#[derive(Debug, Copy, Clone)]
+pub struct EmissionSample {
+    /// Leaves from a point of the light, along a unit direction
+    pub ray: Ray,
+    /// The unit normal of the light there, on the side of the ray, if the light
+    /// has a surface
+    pub normal: Option<Vec3>,
+    /// The light given off along the ray: radiance for surfaces, and intensity
+    /// for point lights
+    pub radiance: Color,
+    /// The probability density of picking the origin of the ray, over area. 1
+    /// for point lights.
+    pub pdf_position: f32,
+    /// The probability density of picking the direction of the ray, over solid angle
+    pub pdf_direction: f32,
+    /// Whether the light is a single point, which rays can never find
+    pub delta: bool,
+}
+
+/// A light which can be sampled directly, rather than only found by chance by
+/// rays bouncing around the scene.
+#[clonable]
+pub trait Light: Clone + Send + Sync {
+    /// A random direction toward the light from `origin`, `None` when the light
+    /// cannot be reached from there
+    fn sample(&self, origin: &Point3, time: f32) -> Option<LightSample>;
+
+    /// The probability density, over solid angle, of `sample` picking the direction
+    /// of `ray` toward the surface it hits at `distance`. 0 when the light is not there.
+    fn pdf(&self, ray: &Ray, distance: f32) -> f32;
+
+    /// The light seen by a ray leaving the scene, for lights at infinity which
+    /// cover some solid angle, such as the disk of the sun
+    fn escaped(&self, _ray: &Ray) -> Color {
+        Color::zero()
+    }
+
+    /// A random ray leaving the light, for paths traced from it. `None` for
+    /// lights which cannot start paths, such as those at infinity.
+    fn sample_emission(&self, _time: f32) -> Option<EmissionSample> {
+        None
+    }
+
+    /// What `sample_emission` gives for the point `ray` reaches at `distance`,
+    /// and the direction back toward the origin of `ray`. `None` when the light
+    /// is not there, or cannot start paths.
+    fn emission(&self, _ray: &Ray, _distance: f32) -> Option<EmissionSample> {
+        None
+    }
+}
+
+impl Debug for dyn Light {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Light").finish()
+    }
+}
+
+/// The lights of a scene, the background aside.
+#[derive(Default, Clone, Debug)]
+pub struct LightList {
+    pub lights: Vec<Box<dyn Light>>,
+}
+
+impl LightList {
+    pub fn new(lights: Vec<Box<dyn Light>>) -> Self {
+        Self { lights }
+    }
+
+    pub fn add(&mut self, light: Box<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+}
@@ -0,0 +1,66 @@
+use std::f32::consts::PI;
+
+use crate::{
+    lights::{EmissionSample, Light, LightSample},
+    utils::{
+        helpers::random_vector,
+        ray::Ray,
+        vec::{Color, Point3},
+    },
+};
+
+/// A light shining equally in every direction from a single point, fading
+/// with the square of the distance.
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    pub position: Point3,
+    /// The light given off in each direction, the irradiance at a distance of 1
+    pub intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, origin: &Point3, _time: f32) -> Option<LightSample> {
+        let offset = self.position - *origin;
+        let distance_squared = offset.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: offset / distance,
+            distance,
+            radiance: self.intensity / distance_squared,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf(&self, _ray: &Ray, _distance: f32) -> f32 {
+        0.0
+    }
+
+    fn sample_emission(&self, time: f32) -> Option<EmissionSample> {
+        let ray = Ray::new(self.position, random_vector(), time);
+        self.emission(&Ray::new(ray.at(1.0), -ray.direction, time), 1.0)
+    }
+
+    fn emission(&self, ray: &Ray, _distance: f32) -> Option<EmissionSample> {
+        Some(EmissionSample {
+            ray: Ray::new(self.position, -ray.direction.unit_vector(), ray.time),
+            normal: None,
+            radiance: self.intensity,
+            pdf_position: 1.0,
+            pdf_direction: 1.0 / (4.0 * PI),
+            delta: true,
+        })
+    }
+}
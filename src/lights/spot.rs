@@ -0,0 +1,111 @@
+use std::f32::consts::PI;
+
+use crate::{
+    lights::{EmissionSample, Light, LightSample},
+    utils::{
+        helpers::random_cone_direction,
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+/// A point light only shining within a cone. It is at full intensity within
+/// the inner angle, and fades out smoothly up to the outer angle.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub position: Point3,
+    pub direction: Vec3,
+    /// The light given off along the axis of the cone, the irradiance at a distance of 1
+    pub intensity: Color,
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+impl SpotLight {
+    /// Create a new SpotLight
+    /// # Arguments
+    /// * `position` - Where the light is
+    /// * `direction` - Where it points to, does not need to be normalized
+    /// * `intensity` - The light given off along its axis
+    /// * `inner_angle` - The angle between the axis and the edge of the full intensity cone, in degrees
+    /// * `outer_angle` - The angle between the axis and the edge of the lit cone, in degrees
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        let outer_angle = outer_angle.clamp(0.0, 180.0);
+        let inner_angle = inner_angle.clamp(0.0, outer_angle);
+        Self {
+            position,
+            direction: direction.unit_vector(),
+            intensity,
+            cos_inner: inner_angle.to_radians().cos(),
+            cos_outer: outer_angle.to_radians().cos(),
+        }
+    }
+
+    // How much of the intensity goes in a direction, 1 inside the inner cone and
+    // 0 outside of the outer one
+    fn falloff(&self, direction: &Vec3) -> f32 {
+        let cosine = direction.dot(&self.direction);
+        if cosine >= self.cos_inner {
+            1.0
+        } else if cosine <= self.cos_outer {
+            0.0
+        } else {
+            let t = (cosine - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, origin: &Point3, _time: f32) -> Option<LightSample> {
+        let offset = self.position - *origin;
+        let distance_squared = offset.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = offset / distance;
+        let falloff = self.falloff(&-direction);
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * (falloff / distance_squared),
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf(&self, _ray: &Ray, _distance: f32) -> f32 {
+        0.0
+    }
+
+    // Rays leave uniformly within the outer cone
+    fn sample_emission(&self, time: f32) -> Option<EmissionSample> {
+        let direction = random_cone_direction(&self.direction, self.cos_outer);
+        self.emission(&Ray::new(self.position + direction, -direction, time), 1.0)
+    }
+
+    fn emission(&self, ray: &Ray, _distance: f32) -> Option<EmissionSample> {
+        let direction = -ray.direction.unit_vector();
+        if self.cos_outer >= 1.0 || direction.dot(&self.direction) < self.cos_outer {
+            return None;
+        }
+        Some(EmissionSample {
+            ray: Ray::new(self.position, direction, ray.time),
+            normal: None,
+            radiance: self.intensity * self.falloff(&direction),
+            pdf_position: 1.0,
+            pdf_direction: 1.0 / (2.0 * PI * (1.0 - self.cos_outer)),
+            delta: true,
+        })
+    }
+}
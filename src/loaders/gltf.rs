@@ -0,0 +1,348 @@
+//! glTF 2.0 scenes, as `.gltf` (with external or data-URI buffers) or binary `.glb` files.
+
+use std::collections::HashMap;
+
+use ::gltf::{camera::Projection, image::Format, mesh::Mode, Document, Node};
+
+use crate::{
+    loaders::MeshError,
+    materials::{
+        dielectric::Dielectric, diffuse_light::DiffuseLight, principled::Principled, Material,
+    },
+    scene::description::CameraDescription,
+    shapes::{bvh::BvhNode, mesh::TriangleMesh},
+    textures::{image::ImageTexture, scaled::Scaled, solid::SolidColor},
+    utils::{
+        camera::{MAX_TIME, MIN_TIME},
+        hittable::Hittable,
+        matrix::Matrix,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+pub struct GltfScene {
+    pub mesh: BvhNode,
+    /// The first perspective camera found in the node hierarchy, if any.
+    pub camera: Option<CameraDescription>,
+    /// The primitives with an emissive material, to be sampled as area lights
+    pub lights: Vec<Box<dyn Hittable>>,
+}
+
+/// Loads the default scene of a glTF file (or its first one).
+///
+/// Nodes are walked recursively with their transforms applied to their meshes,
+/// each primitive becoming a `TriangleMesh`, and pbrMetallicRoughness materials are mapped onto the
+/// closest existing material.
+pub fn load_gltf(
+    path: &str,
+    material: Option<Box<dyn Material + Send + Sync>>,
+) -> Result<GltfScene, MeshError> {
+    let (document, buffers, images) =
+        ::gltf::import(path).map_err(|e| MeshError::Parse(path.to_string(), e.to_string()))?;
+
+    let mut loader = GltfLoader {
+        buffers,
+        images,
+        override_material: material,
+        materials: HashMap::new(),
+        textures: HashMap::new(),
+        meshes: Vec::new(),
+        lights: Vec::new(),
+        camera: None,
+    };
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            for node in scene.nodes() {
+                loader.visit(&node, &Matrix::identity());
+            }
+        }
+        // Files without scenes can still contain meshes worth rendering
+        None => loader.visit_meshes(&document),
+    }
+
+    if loader.meshes.is_empty() {
+        return Err(MeshError::Empty(path.to_string()));
+    }
+    Ok(GltfScene {
+        mesh: BvhNode::from_objects(loader.meshes, MIN_TIME, MAX_TIME),
+        camera: loader.camera,
+        lights: loader.lights,
+    })
+}
+
+struct GltfLoader {
+    buffers: Vec<::gltf::buffer::Data>,
+    images: Vec<::gltf::image::Data>,
+    override_material: Option<Box<dyn Material + Send + Sync>>,
+    // Converted materials and textures, by glTF index
+    materials: HashMap<Option<usize>, Box<dyn Material + Send + Sync>>,
+    textures: HashMap<usize, ImageTexture>,
+    meshes: Vec<Box<dyn Hittable>>,
+    lights: Vec<Box<dyn Hittable>>,
+    camera: Option<CameraDescription>,
+}
+
+impl GltfLoader {
+    fn visit(&mut self, node: &Node, parent: &Matrix) {
+        let transform = *parent * Matrix::from_columns(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            self.add_mesh(&mesh, &transform);
+        }
+        if let (None, Some(camera)) = (&self.camera, node.camera()) {
+            if let Projection::Perspective(perspective) = camera.projection() {
+                // glTF cameras look down their local -Z axis, with +Y up
+                let look_from = transform.transform_point(Point3::zero());
+                let forward = transform.transform_vector(Vec3::new(0.0, 0.0, -1.0));
+                let up = transform.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+                self.camera = Some(CameraDescription {
+                    look_from,
+                    look_at: look_from + forward.unit_vector(),
+                    up: up.unit_vector(),
+                    fov: Some(perspective.yfov().to_degrees()),
+                });
+            }
+        }
+        for child in node.children() {
+            self.visit(&child, &transform);
+        }
+    }
+
+    fn visit_meshes(&mut self, document: &Document) {
+        for mesh in document.meshes() {
+            self.add_mesh(&mesh, &Matrix::identity());
+        }
+    }
+
+    fn add_mesh(&mut self, mesh: &::gltf::Mesh, transform: &Matrix) {
+        // Normals go through the inverse transpose to stay perpendicular to the
+        // surfaces under non-uniform scaling
+        let normal_matrix = transform
+            .inverse()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or_default();
+        for primitive in mesh.primitives() {
+            let material = self.material(&primitive.material());
+            // An overriding material replaces the emissive ones along with the others
+            let emissive = self.override_material.is_none() && is_emissive(&primitive.material());
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let positions = match reader.read_positions() {
+                Some(positions) => positions
+                    .map(|p| transform.transform_point(Point3::from(p)))
+                    .collect::<Vec<_>>(),
+                None => continue,
+            };
+            let normals = reader.read_normals().map(|normals| {
+                normals
+                    .map(|n| normal_matrix.transform_vector(Vec3::from(n)).unit_vector())
+                    .collect::<Vec<_>>()
+            });
+            // glTF puts the origin of texture coordinates at the top left of the image
+            let uvs = reader.read_tex_coords(0).map(|uvs| {
+                uvs.into_f32()
+                    .map(|[u, v]| (u, 1.0 - v))
+                    .collect::<Vec<_>>()
+            });
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect::<Vec<_>>(),
+            };
+
+            let triangles = triangles(primitive.mode(), &indices);
+            let mesh = TriangleMesh::new(positions, normals, uvs, None, triangles, Some(material));
+            if !mesh.is_empty() {
+                if emissive {
+                    self.lights.push(Box::new(mesh.clone()));
+                }
+                self.meshes.push(Box::new(mesh));
+            }
+        }
+    }
+
+    /// Maps a pbrMetallicRoughness material onto the closest existing material:
+    /// * emissive ones become `DiffuseLight`, scaled by their emissive strength
+    /// * transmissive or blended translucent ones become `Dielectric`, with their
+    ///   IOR, and their roughness when transmissive
+    /// * everything else is `Principled`, with its metallic, roughness and
+    ///   transmission factors, and its base color factor times its texture when present
+    fn material(&mut self, material: &::gltf::Material) -> Box<dyn Material + Send + Sync> {
+        if let Some(material) = &self.override_material {
+            return material.clone();
+        }
+        if let Some(converted) = self.materials.get(&material.index()) {
+            return converted.clone();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, alpha] = pbr.base_color_factor();
+        let base_color = Color::new(r, g, b);
+        let transmission = material
+            .transmission()
+            .map_or(0.0, |t| t.transmission_factor());
+        let translucent =
+            material.alpha_mode() == ::gltf::material::AlphaMode::Blend && alpha < 1.0;
+
+        let scalar = |value: f32| Box::new(SolidColor::new(Color::new(value, value, value)));
+        let converted: Box<dyn Material + Send + Sync> = if is_emissive(material) {
+            Box::new(DiffuseLight::new(emission(material)))
+        } else if transmission > 0.5 || translucent {
+            let mut dielectric = Dielectric::new(material.ior().unwrap_or(1.5));
+            // Blended materials usually leave their roughness to its default of 1
+            if transmission > 0.5 {
+                dielectric = dielectric.with_roughness(scalar(pbr.roughness_factor()));
+            }
+            // The attenuation distance is infinite, absorbing nothing, by default
+            match material.volume() {
+                Some(volume) => {
+                    let [r, g, b] = volume.attenuation_color();
+                    Box::new(
+                        dielectric
+                            .with_absorption(Color::new(r, g, b), volume.attenuation_distance()),
+                    )
+                }
+                None => Box::new(dielectric),
+            }
+        } else {
+            let principled = Principled::new(base_color)
+                .with_metallic(scalar(pbr.metallic_factor()))
+                .with_roughness(scalar(pbr.roughness_factor()))
+                .with_transmission(scalar(transmission))
+                .with_ior(scalar(material.ior().unwrap_or(1.5)));
+            match pbr
+                .base_color_texture()
+                .and_then(|info| self.texture(info.texture().source().index()))
+            {
+                Some(texture) => Box::new(
+                    principled
+                        .with_base_color(Box::new(Scaled::new(Box::new(texture), base_color))),
+                ),
+                None => Box::new(principled),
+            }
+        };
+        self.materials.insert(material.index(), converted.clone());
+        converted
+    }
+
+    fn texture(&mut self, image: usize) -> Option<ImageTexture> {
+        if let Some(texture) = self.textures.get(&image) {
+            return Some(texture.clone());
+        }
+        let data = self.images.get(image)?;
+        let channels = match data.format {
+            Format::R8 => 1,
+            Format::R8G8 => 2,
+            Format::R8G8B8 => 3,
+            Format::R8G8B8A8 => 4,
+            // Higher bit depths are not worth supporting for base colors
+            _ => return None,
+        };
+        let rgb = data
+            .pixels
+            .chunks_exact(channels)
+            .flat_map(|pixel| match channels {
+                1 | 2 => [pixel[0], pixel[0], pixel[0]],
+                _ => [pixel[0], pixel[1], pixel[2]],
+            })
+            .collect();
+        let texture = ImageTexture::from_rgb(rgb, data.width as usize, data.height as usize);
+        self.textures.insert(image, texture.clone());
+        Some(texture)
+    }
+}
+
+// The light given off by a material, scaled by its emissive strength
+fn emission(material: &::gltf::Material) -> Color {
+    let [r, g, b] = material.emissive_factor();
+    Color::new(r, g, b) * material.emissive_strength().unwrap_or(1.0)
+}
+
+fn is_emissive(material: &::gltf::Material) -> bool {
+    let emission = emission(material);
+    emission.x + emission.y + emission.z > 0.0
+}
+
+// Split a primitive's indices into triangles according to its topology
+fn triangles(mode: Mode, indices: &[u32]) -> Vec<[u32; 3]> {
+    match mode {
+        Mode::Triangles => indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect(),
+        Mode::TriangleStrip => indices
+            .windows(3)
+            .enumerate()
+            // Every other triangle of a strip has its winding reversed
+            .map(|(i, t)| {
+                if i % 2 == 0 {
+                    [t[0], t[1], t[2]]
+                } else {
+                    [t[1], t[0], t[2]]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => indices
+            .windows(2)
+            .skip(1)
+            .map(|t| [indices[0], t[0], t[1]])
+            .collect(),
+        // Points and lines have no surface to render
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two copies of the same triangle, the first one with an emissive material
+    const SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "accessors": [{
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": 3,
+            "type": "VEC3",
+            "min": [0, 0, 0],
+            "max": [1, 1, 0]
+        }],
+        "materials": [
+            { "emissiveFactor": [1, 0.5, 0.25] },
+            { "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1] } }
+        ],
+        "meshes": [{
+            "primitives": [
+                { "attributes": { "POSITION": 0 }, "material": 0 },
+                { "attributes": { "POSITION": 0 }, "material": 1 }
+            ]
+        }],
+        "nodes": [{ "mesh": 0 }],
+        "scenes": [{ "nodes": [0] }]
+    }"#;
+
+    fn write_gltf(name: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, SCENE).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn returns_emissive_primitives_as_lights() {
+        let scene = load_gltf(&write_gltf("emissive.gltf"), None).unwrap();
+        assert_eq!(scene.lights.len(), 1);
+    }
+
+    #[test]
+    fn overridden_materials_give_off_no_light() {
+        let material = Box::new(Principled::new(Color::new(0.5, 0.5, 0.5)));
+        let scene = load_gltf(&write_gltf("overridden.gltf"), Some(material)).unwrap();
+        assert!(scene.lights.is_empty());
+    }
+}
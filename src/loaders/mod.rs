@@ -0,0 +1,72 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use crate::{
+    materials::{lambertian::Lambertian, Material},
+    shapes::bvh::BvhNode,
+    utils::vec::Color,
+};
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+#[derive(Debug)]
+pub enum MeshError {
+    UnsupportedFormat(String),
+    Parse(String, String),
+    Image(String, String),
+    Empty(String),
+}
+
+impl Display for MeshError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            MeshError::UnsupportedFormat(file) => {
+                write!(
+                    f,
+                    "unsupported mesh format for {} (expected .obj, .gltf, .glb, .ply or .stl)",
+                    file
+                )
+            }
+            MeshError::Parse(file, message) => write!(f, "unable to parse {}: {}", file, message),
+            MeshError::Image(file, message) => {
+                write!(f, "unable to load texture {}: {}", file, message)
+            }
+            MeshError::Empty(file) => write!(f, "{} does not contain any triangle", file),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+/// Loads a mesh file into a BVH of its triangles, the format is picked from the extension.
+/// # Arguments
+/// * `path` - The mesh file
+/// * `material` - A material for the whole mesh, overriding the one from the file
+pub fn load_mesh(
+    path: &str,
+    material: Option<Box<dyn Material + Send + Sync>>,
+) -> Result<BvhNode, MeshError> {
+    match Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("obj") => obj::load_obj(path, material),
+        Some("gltf" | "glb") => self::gltf::load_gltf(path, material).map(|scene| scene.mesh),
+        Some("ply") => ply::load_ply(path, material),
+        Some("stl") => stl::load_stl(path, material),
+        _ => Err(MeshError::UnsupportedFormat(path.to_string())),
+    }
+}
+
+// Used for meshes whose file does not specify a material
+fn default_material() -> Box<dyn Material + Send + Sync> {
+    Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+}
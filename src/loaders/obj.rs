@@ -0,0 +1,189 @@
+//! Wavefront OBJ meshes and their MTL material libraries.
+
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    loaders::{default_material, MeshError},
+    materials::{
+        dielectric::Dielectric, diffuse_light::DiffuseLight, lambertian::Lambertian, metal::Metal,
+        Material,
+    },
+    shapes::{bvh::BvhNode, mesh::TriangleMesh},
+    textures::image::ImageTexture,
+    utils::{
+        camera::{MAX_TIME, MIN_TIME},
+        hittable::Hittable,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+/// Loads an OBJ file into a BVH of triangle meshes, one per group.
+///
+/// Polygons are fan-triangulated, `o`/`g` groups and `usemtl` switches are kept
+/// through the per-group material, and vertex normals and texture coordinates
+/// are carried over to the meshes when present.
+pub fn load_obj(
+    path: &str,
+    material: Option<Box<dyn Material + Send + Sync>>,
+) -> Result<BvhNode, MeshError> {
+    let options = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ignore_points: true,
+        ignore_lines: true,
+    };
+    let (models, materials) = tobj::load_obj(path, &options)
+        .map_err(|e| MeshError::Parse(path.to_string(), e.to_string()))?;
+    // Files without a `mtllib` have no materials rather than a missing library
+    let materials = materials.map_err(|e| {
+        MeshError::Parse(path.to_string(), format!("invalid material library: {}", e))
+    })?;
+
+    let base_dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let mut textures = HashMap::new();
+    let materials = materials
+        .iter()
+        .map(|m| convert_material(m, base_dir, &mut textures))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut meshes: Vec<Box<dyn Hittable>> = Vec::new();
+    for model in models {
+        let mesh = model.mesh;
+        let material = match &material {
+            Some(material) => material.clone(),
+            None => mesh
+                .material_id
+                .and_then(|id| materials.get(id).cloned())
+                .unwrap_or_else(default_material),
+        };
+        let positions = mesh
+            .positions
+            .chunks_exact(3)
+            .map(|p| Point3::new(p[0], p[1], p[2]))
+            .collect();
+        let normals = (!mesh.normals.is_empty()).then(|| {
+            mesh.normals
+                .chunks_exact(3)
+                .map(|n| Vec3::new(n[0], n[1], n[2]))
+                .collect()
+        });
+        let uvs = (!mesh.texcoords.is_empty()).then(|| {
+            mesh.texcoords
+                .chunks_exact(2)
+                .map(|uv| (uv[0], uv[1]))
+                .collect()
+        });
+        let triangles = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|face| [face[0], face[1], face[2]])
+            .collect();
+
+        let mesh = TriangleMesh::new(positions, normals, uvs, None, triangles, Some(material));
+        // Groups made of points, lines or degenerate faces only have nothing to render
+        if !mesh.is_empty() {
+            meshes.push(Box::new(mesh));
+        }
+    }
+
+    if meshes.is_empty() {
+        return Err(MeshError::Empty(path.to_string()));
+    }
+    Ok(BvhNode::from_objects(meshes, MIN_TIME, MAX_TIME))
+}
+
+/// Maps an MTL material onto the closest existing material:
+/// * emissive ones (`Ke` above black) become `DiffuseLight`
+/// * transparent ones (`d` < 1 or a glass illumination model) become `Dielectric` with `Ni`
+/// * ones with a specular color brighter than their diffuse one become `Metal` with `Ks`,
+///   rougher as `Ns` gets lower
+/// * everything else is `Lambertian` with `Kd`, textured by `map_Kd` when present
+fn convert_material(
+    material: &tobj::Material,
+    base_dir: &Path,
+    textures: &mut HashMap<String, ImageTexture>,
+) -> Result<Box<dyn Material + Send + Sync>, MeshError> {
+    let diffuse = material
+        .diffuse
+        .map(Color::from)
+        .unwrap_or(Color::new(0.8, 0.8, 0.8));
+    let specular = material.specular.map(Color::from).unwrap_or_default();
+    let luminance = |c: Color| 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+
+    // tobj leaves the emissive color among the parameters it does not know
+    let emission = material.unknown_param.get("Ke").and_then(|ke| {
+        let values = ke
+            .split_whitespace()
+            .map(|v| v.parse::<f32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        match values[..] {
+            [r, g, b] => Some(Color::new(r, g, b)),
+            [l] => Some(Color::new(l, l, l)),
+            _ => None,
+        }
+    });
+    if let Some(emission) = emission.filter(|e| luminance(*e) > 0.0) {
+        return Ok(Box::new(DiffuseLight::new(emission)));
+    }
+
+    let transparent = material.dissolve.is_some_and(|d| d < 1.0)
+        || matches!(material.illumination_model, Some(4 | 6 | 7 | 9));
+    if transparent {
+        return Ok(Box::new(Dielectric::new(
+            material.optical_density.unwrap_or(1.5),
+        )));
+    }
+
+    if luminance(specular) > luminance(diffuse) {
+        // Map the Phong exponent to a roughness, as is usual for Blinn-Phong
+        let shininess = material.shininess.unwrap_or(0.0).max(0.0);
+        let fuzz = (2.0 / (shininess + 2.0)).sqrt().clamp(0.0, 1.0);
+        return Ok(Box::new(Metal::new(specular, fuzz)));
+    }
+
+    match &material.diffuse_texture {
+        Some(texture) => {
+            let texture_path = base_dir.join(texture.trim()).display().to_string();
+            let texture = match textures.get(&texture_path) {
+                Some(texture) => texture.clone(),
+                None => {
+                    let texture = ImageTexture::load(&texture_path)
+                        .map_err(|e| MeshError::Image(texture_path.clone(), e.to_string()))?;
+                    textures.insert(texture_path, texture.clone());
+                    texture
+                }
+            };
+            Ok(Box::new(Lambertian::with_texture(
+                diffuse,
+                Box::new(texture),
+            )))
+        }
+        None => Ok(Box::new(Lambertian::new(diffuse))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+    fn write_obj(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn loads_files_without_material_library() {
+        let path = write_obj("no_mtllib.obj", TRIANGLE);
+        assert!(load_obj(&path, None).is_ok());
+    }
+
+    #[test]
+    fn rejects_missing_material_library() {
+        let contents = format!("mtllib missing.mtl\nusemtl red\n{}", TRIANGLE);
+        let path = write_obj("missing_mtllib.obj", &contents);
+        assert!(matches!(load_obj(&path, None), Err(MeshError::Parse(..))));
+    }
+}
@@ -0,0 +1,456 @@
+//! Stanford PLY meshes, in ASCII or binary (either endianness) encoding, as
+//! produced by most 3D scanners.
+
+use std::{fs, str::SplitAsciiWhitespace};
+
+use crate::{
+    loaders::MeshError,
+    materials::{lambertian::Lambertian, Material},
+    shapes::{bvh::BvhNode, mesh::TriangleMesh},
+    textures::vertex_colors::VertexColors,
+    utils::{
+        camera::{MAX_TIME, MIN_TIME},
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+/// Loads a PLY file into a BVH of triangles.
+///
+/// Polygons are fan-triangulated and vertex normals and texture coordinates are
+/// carried over to the triangles when present. Vertex colors are interpolated
+/// across each triangle and read through a `VertexColors` texture, which the
+/// default material uses and `material` can use too.
+pub fn load_ply(
+    path: &str,
+    material: Option<Box<dyn Material + Send + Sync>>,
+) -> Result<BvhNode, MeshError> {
+    let data = fs::read(path).map_err(|e| MeshError::Parse(path.to_string(), e.to_string()))?;
+    let mesh = PlyMesh::parse(&data).map_err(|e| MeshError::Parse(path.to_string(), e))?;
+
+    let mut triangles = Vec::new();
+    for face in &mesh.faces {
+        if let Some(&index) = face.iter().find(|&&i| i >= mesh.positions.len()) {
+            return Err(MeshError::Parse(
+                path.to_string(),
+                format!(
+                    "face references vertex {} but there are only {}",
+                    index,
+                    mesh.positions.len()
+                ),
+            ));
+        }
+        for n in 1..face.len().saturating_sub(1) {
+            triangles.push([face[0], face[n], face[n + 1]].map(|i| i as u32));
+        }
+    }
+
+    // Grey, like the default material, where the file has no vertex colors
+    let material = material.unwrap_or_else(|| {
+        let grey = Color::new(0.5, 0.5, 0.5);
+        Box::new(Lambertian::with_texture(
+            grey,
+            Box::new(VertexColors::new(grey)),
+        ))
+    });
+    let mesh = TriangleMesh::new(
+        mesh.positions,
+        mesh.normals,
+        mesh.uvs,
+        mesh.colors,
+        triangles,
+        Some(material),
+    );
+    if mesh.is_empty() {
+        return Err(MeshError::Empty(path.to_string()));
+    }
+    Ok(BvhNode::from_objects(
+        vec![Box::new(mesh)],
+        MIN_TIME,
+        MAX_TIME,
+    ))
+}
+
+struct PlyMesh {
+    positions: Vec<Point3>,
+    normals: Option<Vec<Vec3>>,
+    colors: Option<Vec<Color>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    faces: Vec<Vec<usize>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    // A count followed by that many items
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl PlyMesh {
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let (encoding, elements, body) = parse_header(data)?;
+        let mut body = match encoding {
+            Encoding::Ascii => Body::Ascii(
+                std::str::from_utf8(body)
+                    .map_err(|e| e.to_string())?
+                    .split_ascii_whitespace(),
+            ),
+            _ => Body::Binary {
+                data: body,
+                big_endian: encoding == Encoding::BinaryBigEndian,
+            },
+        };
+
+        let mut mesh = PlyMesh {
+            positions: Vec::new(),
+            normals: None,
+            colors: None,
+            uvs: None,
+            faces: Vec::new(),
+        };
+        for element in &elements {
+            match element.name.as_str() {
+                "vertex" => mesh.read_vertices(element, &mut body)?,
+                "face" => mesh.read_faces(element, &mut body)?,
+                // Edges, materials and custom elements are skipped
+                _ => {
+                    for _ in 0..element.count {
+                        for property in &element.properties {
+                            body.skip(property)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(mesh)
+    }
+
+    fn read_vertices(&mut self, element: &Element, body: &mut Body) -> Result<(), String> {
+        let find = |names: &[&str]| {
+            element.properties.iter().position(
+                |p| matches!(p, Property::Scalar(name, _) if names.contains(&name.as_str())),
+            )
+        };
+        let find_all =
+            |names: [&[&str]; 3]| Some([find(names[0])?, find(names[1])?, find(names[2])?]);
+        let position = find_all([&["x"], &["y"], &["z"]])
+            .ok_or("vertices are missing their x, y or z property")?;
+        let normal = find_all([&["nx"], &["ny"], &["nz"]]);
+        let color = find_all([
+            &["red", "r", "diffuse_red"],
+            &["green", "g", "diffuse_green"],
+            &["blue", "b", "diffuse_blue"],
+        ]);
+        let uv = find(&["u", "s", "texture_u"])
+            .zip(find(&["v", "t", "texture_v"]))
+            .map(|(u, v)| [u, v]);
+
+        // Integer colors span their whole range, float ones are already in [0, 1]
+        let color_scale = color.map(|color| match &element.properties[color[0]] {
+            Property::Scalar(_, Scalar::U8 | Scalar::I8) => 1.0 / 255.0,
+            Property::Scalar(_, Scalar::U16 | Scalar::I16) => 1.0 / 65535.0,
+            Property::Scalar(_, Scalar::U32 | Scalar::I32) => 1.0 / 4294967295.0,
+            _ => 1.0,
+        });
+
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        let mut uvs = Vec::new();
+        let mut values = vec![0.0; element.properties.len()];
+        for _ in 0..element.count {
+            for (value, property) in values.iter_mut().zip(&element.properties) {
+                *value = match property {
+                    Property::Scalar(_, scalar) => body.read(*scalar)?,
+                    Property::List(..) => {
+                        body.skip(property)?;
+                        0.0
+                    }
+                };
+            }
+            let vector = |[x, y, z]: [usize; 3]| {
+                Vec3::new(values[x] as f32, values[y] as f32, values[z] as f32)
+            };
+            self.positions.push(vector(position));
+            if let Some(normal) = normal {
+                normals.push(vector(normal));
+            }
+            if let (Some(color), Some(scale)) = (color, color_scale) {
+                colors.push(vector(color) * scale as f32);
+            }
+            if let Some([u, v]) = uv {
+                uvs.push((values[u] as f32, values[v] as f32));
+            }
+        }
+        self.normals = normal.map(|_| normals);
+        self.colors = color.map(|_| colors);
+        self.uvs = uv.map(|_| uvs);
+        Ok(())
+    }
+
+    fn read_faces(&mut self, element: &Element, body: &mut Body) -> Result<(), String> {
+        let indices = element
+            .properties
+            .iter()
+            .position(|p| {
+                matches!(p, Property::List(name, ..) if name == "vertex_indices" || name == "vertex_index")
+            })
+            .ok_or("faces are missing their vertex_indices property")?;
+
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::List(_, count, item) if i == indices => {
+                        let count = body.read(*count)? as usize;
+                        let face = (0..count)
+                            .map(|_| body.read(*item).map(|index| index as usize))
+                            .collect::<Result<_, _>>()?;
+                        self.faces.push(face);
+                    }
+                    _ => body.skip(property)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Returns the encoding and elements declared by the header, and the data following it
+fn parse_header(data: &[u8]) -> Result<(Encoding, Vec<Element>, &[u8]), String> {
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    for (number, line) in data.split(|&b| b == b'\n').enumerate() {
+        offset += line.len() + 1;
+        let line = String::from_utf8_lossy(line);
+        let words = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let invalid = || format!("invalid header line {}: {}", number + 1, line.trim());
+        match words.as_slice() {
+            ["ply"] if number == 0 => {}
+            _ if number == 0 => return Err("not a PLY file".to_string()),
+            ["format", format, _] => {
+                encoding = Some(match *format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    _ => return Err(format!("unknown format {}", format)),
+                })
+            }
+            ["comment" | "obj_info", ..] | [] => {}
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid())?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or_else(invalid)?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    parse_scalar(count).ok_or_else(invalid)?,
+                    parse_scalar(item).ok_or_else(invalid)?,
+                )),
+            ["property", scalar, name] => {
+                elements
+                    .last_mut()
+                    .ok_or_else(invalid)?
+                    .properties
+                    .push(Property::Scalar(
+                        name.to_string(),
+                        parse_scalar(scalar).ok_or_else(invalid)?,
+                    ))
+            }
+            ["end_header"] => {
+                let encoding = encoding.ok_or("missing format line")?;
+                return Ok((encoding, elements, data.get(offset..).unwrap_or_default()));
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Err("missing end_header line".to_string())
+}
+
+fn parse_scalar(name: &str) -> Option<Scalar> {
+    Some(match name {
+        "char" | "int8" => Scalar::I8,
+        "uchar" | "uint8" => Scalar::U8,
+        "short" | "int16" => Scalar::I16,
+        "ushort" | "uint16" => Scalar::U16,
+        "int" | "int32" => Scalar::I32,
+        "uint" | "uint32" => Scalar::U32,
+        "float" | "float32" => Scalar::F32,
+        "double" | "float64" => Scalar::F64,
+        _ => return None,
+    })
+}
+
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        match self {
+            Body::Ascii(words) => {
+                let word = words.next().ok_or("unexpected end of file")?;
+                word.parse().map_err(|_| format!("invalid number {}", word))
+            }
+            Body::Binary { data, big_endian } => {
+                let size = match scalar {
+                    Scalar::I8 | Scalar::U8 => 1,
+                    Scalar::I16 | Scalar::U16 => 2,
+                    Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+                    Scalar::F64 => 8,
+                };
+                if data.len() < size {
+                    return Err("unexpected end of file".to_string());
+                }
+                let mut bytes = [0; 8];
+                bytes[..size].copy_from_slice(&data[..size]);
+                if *big_endian {
+                    bytes[..size].reverse();
+                }
+                *data = &data[size..];
+                let [b0, b1, b2, b3, ..] = bytes;
+                Ok(match scalar {
+                    Scalar::I8 => b0 as i8 as f64,
+                    Scalar::U8 => b0 as f64,
+                    Scalar::I16 => i16::from_le_bytes([b0, b1]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b0, b1]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(bytes),
+                })
+            }
+        }
+    }
+
+    fn skip(&mut self, property: &Property) -> Result<(), String> {
+        match property {
+            Property::Scalar(_, scalar) => self.read(*scalar).map(|_| ()),
+            Property::List(_, count, item) => {
+                let count = self.read(*count)? as usize;
+                (0..count).try_for_each(|_| self.read(*item).map(|_| ()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "ply
+format {} 1.0
+comment made by hand
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element edge 1
+property int vertex1
+property int vertex2
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    fn header(format: &str) -> Vec<u8> {
+        HEADER.replace("{}", format).into_bytes()
+    }
+
+    fn check(mesh: &PlyMesh) {
+        assert_eq!(
+            mesh.positions,
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, -2.5, 1.0),
+            ]
+        );
+        assert_eq!(
+            mesh.colors,
+            Some(vec![
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+                Color::new(0.0, 0.0, 1.0),
+            ])
+        );
+        assert!(mesh.normals.is_none() && mesh.uvs.is_none());
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn parses_ascii() {
+        let mut data = header("ascii");
+        data.extend_from_slice(b"0 0 0 255 0 0\n1 0 0 0 255 0\n0 -2.5 1 0 0 255\n0 1\n3 0 1 2\n");
+        check(&PlyMesh::parse(&data).unwrap());
+    }
+
+    #[test]
+    fn parses_binary() {
+        for (format, big_endian) in [("binary_little_endian", false), ("binary_big_endian", true)] {
+            let float = |value: f32| match big_endian {
+                true => value.to_be_bytes(),
+                false => value.to_le_bytes(),
+            };
+            let int = |value: i32| match big_endian {
+                true => value.to_be_bytes(),
+                false => value.to_le_bytes(),
+            };
+            let mut data = header(format);
+            for ([x, y, z], color) in [
+                ([0.0, 0.0, 0.0], [255, 0, 0]),
+                ([1.0, 0.0, 0.0], [0, 255, 0]),
+                ([0.0, -2.5, 1.0], [0, 0, 255]),
+            ] {
+                data.extend(float(x).into_iter().chain(float(y)).chain(float(z)));
+                data.extend(color);
+            }
+            data.extend(int(0).into_iter().chain(int(1)));
+            data.push(3);
+            data.extend(int(0).into_iter().chain(int(1)).chain(int(2)));
+            check(&PlyMesh::parse(&data).unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let mut data = header("binary_little_endian");
+        data.extend_from_slice(&[0; 10]);
+        assert!(PlyMesh::parse(&data).is_err());
+        let mut data = header("ascii");
+        data.extend_from_slice(b"0 0 0 255 0 0\n1 0 0\n");
+        assert!(PlyMesh::parse(&data).is_err());
+        assert!(PlyMesh::parse(b"ply\nformat ascii 1.0\nelement vertex 1\n").is_err());
+        assert!(PlyMesh::parse(b"obj\nend_header\n").is_err());
+    }
+}
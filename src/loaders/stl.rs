@@ -0,0 +1,52 @@
+//! STL meshes, in ASCII or binary encoding, as exported by CAD software.
+
+use std::fs::File;
+
+use crate::{
+    loaders::{default_material, MeshError},
+    materials::Material,
+    shapes::{bvh::BvhNode, mesh::TriangleMesh},
+    utils::{
+        camera::{MAX_TIME, MIN_TIME},
+        vec::Point3,
+    },
+};
+
+/// Loads an STL file into a flat-shaded triangle mesh.
+///
+/// STL files carry no material, so `material` (or a grey `Lambertian`) is used
+/// for the whole mesh. Identical vertices are merged so that facets share them,
+/// and the stored facet normals are ignored in favour of the winding of the
+/// vertices, as exporters often leave them zeroed.
+pub fn load_stl(
+    path: &str,
+    material: Option<Box<dyn Material + Send + Sync>>,
+) -> Result<BvhNode, MeshError> {
+    let parse_error = |e: std::io::Error| MeshError::Parse(path.to_string(), e.to_string());
+    let mut file = File::open(path).map_err(parse_error)?;
+    let stl = stl_io::read_stl(&mut file).map_err(parse_error)?;
+
+    let positions = stl.vertices.iter().map(|v| Point3::from(v.0)).collect();
+    let triangles = stl
+        .faces
+        .iter()
+        .map(|face| face.vertices.map(|i| i as u32))
+        .collect();
+    let mesh = TriangleMesh::new(
+        positions,
+        None,
+        None,
+        None,
+        triangles,
+        Some(material.unwrap_or_else(default_material)),
+    );
+
+    if mesh.is_empty() {
+        return Err(MeshError::Empty(path.to_string()));
+    }
+    Ok(BvhNode::from_objects(
+        vec![Box::new(mesh)],
+        MIN_TIME,
+        MAX_TIME,
+    ))
+}
@@ -1,26 +1,30 @@
 #![allow(dead_code)]
 
+mod backgrounds;
+mod densities;
+mod integrators;
+mod lights;
+mod loaders;
 mod materials;
+mod scene;
 mod shapes;
 mod textures;
 mod utils;
 
-use std::sync::mpsc::Receiver;
+use std::sync::{mpsc::Receiver, Arc};
 
 use crate::{
-    materials::lambertian::Lambertian,
-    shapes::{bvh::BvhNode, list::HittableList, plane::Plane, sphere::Sphere},
-    textures::{image::ImageTexture, solid::SolidColor},
+    scene::Scene,
     utils::{
         args::Args,
-        camera::Camera,
         helpers::{
-            clear, compute_chunk, parse_aspect_ratio, random_spheres, split_evenly, write_to_file,
+            clear, compute_chunk, parse_aspect_ratio, parse_background, parse_integrator,
+            parse_light, split_evenly, write_to_file, ChunkArgs,
         },
-        hittable,
+        photons::{trace_photons, Photon, PhotonArgs, PhotonMap},
         result::Res,
         threads::{job::Job, pool::ThreadPool},
-        vec::{Color, Point3, Vec3},
+        vec::Color,
     },
 };
 use clap::Parser;
@@ -34,10 +38,9 @@ fn main() {
     let cpus = args.threads;
     let image_width = args.width;
     let aspect_ratio = parse_aspect_ratio(&args.aspect_ratio).unwrap_or({
-        if args.width > 0 && args.height.is_some() {
-            args.width as f32 / args.height.unwrap() as f32
-        } else {
-            16.0 / 9.0
+        match args.height {
+            Some(height) if args.width > 0 => args.width as f32 / height as f32,
+            _ => 16.0 / 9.0,
         }
     });
     let image_height = if let Some(height) = args.height {
@@ -46,43 +49,54 @@ fn main() {
         (image_width as f32 / aspect_ratio) as i32
     };
     let samples_per_pixel = args.samples;
+    let mut scene = match &args.scene {
+        Some(path) => Scene::from_file(path, aspect_ratio, args.fov).unwrap_or_else(|e| {
+            eprintln!("❌ Failed to load scene: {}", e);
+            std::process::exit(1);
+        }),
+        None => Scene::random_spheres(aspect_ratio, args.fov),
+    };
+    if let Some(background) = &args.background {
+        scene.background = parse_background(
+            background,
+            args.background_rotation,
+            args.background_intensity,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("❌ Failed to load background: {}", e);
+            std::process::exit(1);
+        });
+    }
+    for light in &args.lights {
+        scene.lights.add(parse_light(light).unwrap_or_else(|e| {
+            eprintln!("❌ Failed to add light: {}", e);
+            std::process::exit(1);
+        }));
+    }
+    // Shared between jobs rather than cloned, meshes can hold millions of triangles
+    let scene = Arc::new(scene);
 
-    let camera = Box::new(Camera::new(
-        Vec3::new(-3.0, 3.0, 3.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        args.fov,
-        aspect_ratio,
-    ));
-    let random: Vec<Box<dyn hittable::Hittable>> = random_spheres(10);
-    let world = Box::new(HittableList::new(vec![
-        Box::new(BvhNode::from_objects(random, 0, 10, 0.0, 1.0)),
-        // Box::new(Plane::new(
-        //     Point3::new(0.0, -3.0, 0.0),
-        //     Vec3::new(0.0, 1.0, 0.0),
-        //     Some(Box::new(Lambertian::with_texture(
-        //         Color::new(0.0, 0.0, 0.0),
-        //         Box::new(SolidColor::new(Color::new(0.5, 0.5, 0.5))),
-        //     ))),
-        // )),
-        // Box::new(Sphere::new(
-        //     Point3::new(0.0, 0.0, 0.0),
-        //     2.0,
-        //     Some(Box::new(Lambertian::with_texture(
-        //         Color::new(0.0, 0.0, 0.0),
-        //         Box::new(ImageTexture::new("earthmap.jpg")),
-        //         // Box::new(SolidColor::new(Color::new(0.5, 0.5, 0.5))),
-        //     ))),
-        // )),
-    ]));
+    let start = std::time::Instant::now();
+
+    let caustics = (args.photons > 0).then_some(|| {
+        let photons = shoot_photons(&scene, &args, cpus);
+        println!(
+            "✨ Traced {} photons, {} of them left caustics",
+            args.photons,
+            photons.len()
+        );
+        Arc::new(PhotonMap::new(photons, args.photon_nearest))
+    });
+    let integrator = parse_integrator(&args.integrator, args.max_depth, args.min_depth, caustics)
+        .unwrap_or_else(|e| {
+            eprintln!("❌ Failed to pick integrator: {}", e);
+            std::process::exit(1);
+        });
 
     let mut image_buffer: Vec<Vec<Color>> =
         vec![vec![Color::new(0.0, 0.0, 0.0); image_width as usize]; image_height as usize];
 
-    let start = std::time::Instant::now();
-
-    let thread_pool =
-        ThreadPool::<(Vec<i32>, Box<Camera>, Box<HittableList>, i32, i32, i32), Res>::new(cpus);
+    let thread_pool = ThreadPool::<ChunkArgs, Res>::new(cpus);
 
     let (rows_chunks, rows_per_chunk) =
         split_evenly((0..image_height).collect::<Vec<i32>>(), args.jobs);
@@ -100,17 +114,18 @@ fn main() {
     ));
 
     for chunk in rows_chunks {
-        let camera = camera.clone();
-        let world = world.clone();
+        let scene = scene.clone();
+        let integrator = integrator.clone();
         let (job, rx) = Job::with_result_sink(
-            move |args| compute_chunk(args),
+            compute_chunk,
             (
                 chunk,
-                camera,
-                world,
+                scene,
+                integrator,
                 image_width,
                 image_height,
                 samples_per_pixel,
+                args.spectral,
             ),
         );
         thread_pool.schedule(job).expect("Failed to schedule job");
@@ -118,6 +133,7 @@ fn main() {
     }
     progress_bar.println("🚚 Dispatched jobs to threads");
 
+    let mut splats = vec![Color::zero(); (image_width * image_height) as usize];
     for rx in rxs {
         let result = rx.recv().unwrap();
         for (i, buffer) in result.buffers.iter().enumerate() {
@@ -125,6 +141,19 @@ fn main() {
             image_buffer[index] = buffer.clone();
         }
         progress_bar.inc(result.buffers.len() as u64);
+        for (total, splat) in splats.iter_mut().zip(result.splats) {
+            *total += splat;
+        }
+    }
+    // Light tracing can land anywhere, so splats are only added once every row
+    // is in place
+    for (index, splat) in splats.into_iter().enumerate() {
+        image_buffer[index / image_width as usize][index % image_width as usize] += splat;
+    }
+    for row in image_buffer.iter_mut() {
+        for pixel in row.iter_mut() {
+            *pixel = pixel.clamp(0.0, 0.999).gamma_correct(2.0);
+        }
     }
 
     progress_bar.finish_and_clear();
@@ -134,7 +163,7 @@ fn main() {
     image_buffer.reverse();
     write_to_file(&args.output, &image_buffer, image_width, image_height);
     let file_size = std::fs::metadata(&args.output)
-        .expect(&format!("Failed to get metadata for {}", args.output))
+        .unwrap_or_else(|_| panic!("Failed to get metadata for {}", args.output))
         .len();
     println!("📝 Wrote to {} ({})", args.output, HumanBytes(file_size));
     println!(
@@ -143,6 +172,27 @@ fn main() {
         duration.as_micros() as f32 / (image_height as f32 * image_width as f32)
     );
     if args.open {
-        open::that(args.output.clone()).expect(&format!("Failed to open {}", args.output));
+        open::that(args.output.clone())
+            .unwrap_or_else(|_| panic!("Failed to open {}", args.output));
     }
 }
+
+// Shoots photons from the lights over the threads of a pool, each job emitting
+// its share of them
+fn shoot_photons(scene: &Arc<Scene>, args: &Args, threads: usize) -> Vec<Photon> {
+    let thread_pool = ThreadPool::<PhotonArgs, Vec<Photon>>::new(threads);
+    let count = args.photons;
+    let jobs = args.jobs.clamp(1, count);
+    let rxs = (0..jobs)
+        .map(|i| {
+            let share = count / jobs + usize::from(i < count % jobs);
+            let (job, rx) = Job::with_result_sink(
+                trace_photons,
+                (scene.clone(), share, count, args.max_depth, args.spectral),
+            );
+            thread_pool.schedule(job).expect("Failed to schedule job");
+            rx
+        })
+        .collect::<Vec<Receiver<Vec<Photon>>>>();
+    rxs.into_iter().flat_map(|rx| rx.recv().unwrap()).collect()
+}
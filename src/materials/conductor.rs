@@ -0,0 +1,210 @@
+use crate::{
+    materials::{
+        microfacet::{fresnel_conductor, Ggx},
+        Material, ScatterRecord,
+    },
+    utils::{
+        helpers::random_float,
+        hittable::HitRecord,
+        onb::Onb,
+        ray::Ray,
+        vec::{Color, Vec3},
+    },
+};
+
+/// A rough metal, made of tiny mirror facets following the GGX distribution,
+/// whose color comes from its complex refractive index `eta + i k` through the
+/// Fresnel equations: the same at every angle as measured on the real metal.
+///
+/// Metals rougher along one direction than the other look brushed, with the
+/// highlights stretched across the grooves. The grooves follow `direction`,
+/// laid flat onto the surface.
+#[derive(Debug, Clone)]
+pub struct Conductor {
+    /// The real part of the refractive index, for each channel
+    pub eta: Color,
+    /// The imaginary part of the refractive index, for each channel
+    pub k: Color,
+    pub ggx: Ggx,
+    /// The direction of the grooves of brushed metals
+    pub direction: Vec3,
+}
+
+/// Metals measured in the lab, to make conductors of
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Preset {
+    Gold,
+    Silver,
+    Copper,
+    Aluminum,
+    Chrome,
+}
+
+impl Preset {
+    /// The real and imaginary parts of the refractive index of the metal, at
+    /// the wavelengths of red, green and blue
+    pub fn index(&self) -> (Color, Color) {
+        match self {
+            Self::Gold => (
+                Color::new(0.143, 0.375, 1.442),
+                Color::new(3.983, 2.386, 1.603),
+            ),
+            Self::Silver => (
+                Color::new(0.155, 0.117, 0.138),
+                Color::new(4.828, 3.122, 2.147),
+            ),
+            Self::Copper => (
+                Color::new(0.200, 0.924, 1.102),
+                Color::new(3.913, 2.453, 2.142),
+            ),
+            Self::Aluminum => (
+                Color::new(1.657, 0.880, 0.521),
+                Color::new(9.224, 6.270, 4.837),
+            ),
+            Self::Chrome => (
+                Color::new(4.370, 2.917, 1.655),
+                Color::new(5.206, 4.231, 3.755),
+            ),
+        }
+    }
+}
+
+impl Conductor {
+    /// Create a new metal, as rough as brushed steel until changed with `with_roughness`
+    /// # Arguments
+    /// * `eta` - The real part of the refractive index, for each channel
+    /// * `k` - The imaginary part of the refractive index, for each channel
+    pub fn new(eta: Color, k: Color) -> Self {
+        Self {
+            eta,
+            k,
+            ggx: Ggx::from_roughness(0.3),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+        }
+    }
+    pub fn from_preset(preset: Preset) -> Self {
+        let (eta, k) = preset.index();
+        Self::new(eta, k)
+    }
+    /// Sets how rough the metal is, from a mirror at 0 to matte at 1
+    /// # Arguments
+    /// * `along` - The roughness along the grooves
+    /// * `across` - The roughness across the grooves, the same as `along` for
+    ///   metals which are not brushed
+    pub fn with_roughness(mut self, along: f32, across: f32) -> Self {
+        let alpha = |roughness: f32| roughness.clamp(0.0, 1.0).powi(2);
+        self.ggx = Ggx::new(alpha(along), alpha(across));
+        self
+    }
+    pub fn with_direction(mut self, direction: Vec3) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    // The frame of the surface, with the grooves along its X axis
+    fn frame(&self, rec: &HitRecord) -> Onb {
+        Onb::from_wu(&rec.normal, &self.direction)
+    }
+}
+
+impl Material for Conductor {
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let frame = self.frame(rec);
+        let v = frame.to_local(&-r_in.direction.unit_vector());
+        if v.z <= 0.0 {
+            return None;
+        }
+        let m = self.ggx.sample_visible(&v, random_float(), random_float());
+        let vm = v.dot(&m);
+        let l = 2.0 * vm * m - v;
+        if l.z <= 0.0 || vm <= 0.0 {
+            return None;
+        }
+
+        // Facets are picked as often as they are seen, so only the shadowing
+        // of the reflected light and the Fresnel term are left of D G F / pdf
+        Some(ScatterRecord {
+            direction: frame.to_world(&l).unit_vector(),
+            throughput: fresnel_conductor(vm, self.eta, self.k)
+                * (self.ggx.g(&v, &l) / self.ggx.g1(&v)),
+            pdf: self.ggx.pdf_visible(&v, &m) / (4.0 * vm),
+            specular: false,
+        })
+    }
+
+    fn eval(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> Color {
+        let frame = self.frame(rec);
+        let v = frame.to_local(&-wi.unit_vector());
+        let l = frame.to_local(&wo.unit_vector());
+        if v.z <= 0.0 || l.z <= 0.0 {
+            return Color::zero();
+        }
+        let h = (v + l).unit_vector();
+        // D G F / (4 cos v cos l), times the cosine of l
+        fresnel_conductor(v.dot(&h), self.eta, self.k)
+            * (self.ggx.d(&h) * self.ggx.g(&v, &l) / (4.0 * v.z))
+    }
+
+    fn pdf(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> f32 {
+        let frame = self.frame(rec);
+        let v = frame.to_local(&-wi.unit_vector());
+        let l = frame.to_local(&wo.unit_vector());
+        if v.z <= 0.0 || l.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (v + l).unit_vector();
+        self.ggx.pdf_visible(&v, &h) / (4.0 * v.dot(&h))
+    }
+
+    // The color of the metal seen head-on
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        fresnel_conductor(1.0, self.eta, self.k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::tests::{accepted_share, check_sampling, hit_from_above};
+
+    #[test]
+    fn samples_with_the_density_of_pdf() {
+        let rec = hit_from_above();
+        let wi = Vec3::new(1.0, 0.5, -1.0);
+        for conductor in [
+            Conductor::from_preset(Preset::Copper).with_roughness(0.5, 0.5),
+            Conductor::from_preset(Preset::Aluminum)
+                .with_roughness(0.2, 0.7)
+                .with_direction(Vec3::new(1.0, 1.0, 0.0)),
+        ] {
+            // Reflections below the surface are absorbed, and have no pdf
+            let integral = check_sampling(&conductor, &rec, wi);
+            let share = accepted_share(&conductor, &rec, wi);
+            assert!((integral - share).abs() < 0.01, "{} != {}", integral, share);
+        }
+    }
+
+    #[test]
+    fn reflects_no_more_light_than_it_receives() {
+        let conductor = Conductor::from_preset(Preset::Silver).with_roughness(0.8, 0.8);
+        let rec = hit_from_above();
+        let r_in = Ray::new(Vec3::new(-1.0, 0.0, 0.2), Vec3::new(1.0, 0.0, -0.2), 0.0);
+        for _ in 0..10_000 {
+            if let Some(sample) = conductor.sample(&r_in, &rec) {
+                let t = sample.throughput;
+                assert!(t.x <= 1.0 && t.y <= 1.0 && t.z <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn presets_have_the_color_of_their_metal() {
+        let rec = hit_from_above();
+        let gold = Conductor::from_preset(Preset::Gold).albedo(&rec);
+        assert!(gold.x > gold.y && gold.y > gold.z);
+        let copper = Conductor::from_preset(Preset::Copper).albedo(&rec);
+        assert!(copper.x > copper.y && copper.x > copper.z);
+        let silver = Conductor::from_preset(Preset::Silver).albedo(&rec);
+        assert!(silver.x > 0.9 && silver.y > 0.9 && silver.z > 0.9);
+    }
+}
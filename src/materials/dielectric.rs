@@ -1,43 +1,449 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::{
-    material,
-    materials::Material,
+    materials::{
+        microfacet::{Ggx, RoughBoundary},
+        Interior, Material, ScatterRecord,
+    },
+    textures::Texture,
     utils::{
         helpers::{random_float, reflectance},
         hittable::HitRecord,
+        onb::Onb,
         ray::Ray,
-        vec::Color,
+        vec::{Color, Vec3},
     },
 };
 
-material!(Dielectric { n: f32 });
+/// Glass, water or any other clear material, reflecting or refracting light at
+/// its surface and tinted by the light it absorbs inside (Beer–Lambert law).
+///
+/// Where dielectrics overlap, such as ice floating in water, the inside of the
+/// one with the highest `priority` wins, and the surfaces of the others within
+/// it are ignored. This needs an integrator tracking which dielectrics rays are
+/// inside of, the path tracer does.
+///
+/// In spectral mode, a `dispersion` formula gives the refractive index of each
+/// wavelength, splitting white light into a rainbow like a prism.
+///
+/// Rough dielectrics, such as frosted glass or ice, scatter light around the
+/// mirror and refracted directions. Lights are only sampled through them
+/// against air, and at the refractive index `n`: between nested dielectrics or
+/// at the other wavelengths of a dispersive one, their paths go on like through
+/// smooth ones.
+#[derive(Clone)]
+pub struct Dielectric {
+    /// The refractive index
+    pub n: f32,
+    pub dispersion: Option<Dispersion>,
+    /// The share of light absorbed per unit of distance inside, for each channel
+    pub absorption: Color,
+    pub priority: i32,
+    /// How rough the surface is, from 0 for smooth to 1, in the red channel
+    pub roughness: Option<Box<dyn Texture + Send + Sync>>,
+    // Shared by the clones of the material, but not by other dielectrics
+    id: usize,
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl Dielectric {
+    pub fn new(n: f32) -> Self {
+        Self {
+            n,
+            dispersion: None,
+            absorption: Color::zero(),
+            priority: 0,
+            roughness: None,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+    /// Tints the material, absorbing light as it travels inside
+    /// # Arguments
+    /// * `color` - The color of white light after traveling `distance` inside
+    /// * `distance` - The distance at which light has the given color
+    pub fn with_absorption(mut self, color: Color, distance: f32) -> Self {
+        let coefficient = |c: f32| -c.clamp(1e-6, 1.0).ln() / distance.max(1e-6);
+        self.absorption = Color::new(
+            coefficient(color.x),
+            coefficient(color.y),
+            coefficient(color.z),
+        );
+        self
+    }
+    /// Makes the refractive index depend on the wavelength in spectral mode
+    pub fn with_dispersion(mut self, dispersion: Dispersion) -> Self {
+        self.dispersion = Some(dispersion);
+        self
+    }
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+    /// Frosts the surface, from smooth at 0 to matte at 1
+    pub fn with_roughness(mut self, roughness: Box<dyn Texture + Send + Sync>) -> Self {
+        self.roughness = Some(roughness);
+        self
+    }
+    fn inside(&self) -> Interior {
+        Interior {
+            n: self.n,
+            dispersion: self.dispersion,
+            absorption: self.absorption,
+            priority: self.priority,
+            id: self.id,
+        }
+    }
+
+    // The rough surface at the hit point, between the refractive index below it
+    // and the one above, where the ray came from. `None` where it is smooth.
+    fn boundary(&self, rec: &HitRecord, eta: f32) -> Option<RoughBoundary> {
+        let roughness = self.roughness.as_ref()?.value_at(rec).x;
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        // Smoother facets than GGX can handle are left to specular refraction
+        (alpha >= 1e-3).then(|| RoughBoundary {
+            ggx: Ggx::new(alpha, alpha),
+            eta,
+        })
+    }
+
+    // The refractive index of the other side of the surface, divided by the one
+    // of the side it is hit from, as if the dielectric were surrounded by air
+    fn eta(&self, rec: &HitRecord) -> f32 {
+        if rec.front_face {
+            self.n
+        } else {
+            1.0 / self.n
+        }
+    }
+}
 
 impl Material for Dielectric {
-    fn scatter(
+    // Without knowing what the ray travels through, the material is assumed to be
+    // surrounded by air, and rays leaving it to have traveled inside since the
+    // last hit
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let n = self.inside().index(r_in.wavelength);
+        let refraction_ratio = if rec.front_face { 1.0 / n } else { n };
+        let sample = self.sample_boundary(r_in, rec, refraction_ratio)?;
+        if rec.front_face {
+            return Some(sample);
+        }
+        Some(ScatterRecord {
+            throughput: sample.throughput
+                * self.inside().transmittance(rec.t * r_in.direction.length()),
+            ..sample
+        })
+    }
+
+    fn sample_boundary(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
+        refraction_ratio: f32,
+    ) -> Option<ScatterRecord> {
+        let boundary = match self.boundary(rec, 1.0 / refraction_ratio) {
+            Some(boundary) => boundary,
+            None => {
+                let (direction, _) = reflect_or_refract(r_in, rec, refraction_ratio);
+                return Some(ScatterRecord {
+                    direction,
+                    throughput: Color::new(1.0, 1.0, 1.0),
+                    pdf: 0.0,
+                    specular: true,
+                });
+            }
+        };
+        let frame = Onb::from_w(&rec.normal);
+        let v = frame.to_local(&-r_in.direction.unit_vector());
+        let l = boundary.sample(&v, random_float(), random_float(), random_float())?;
+        let pdf = boundary.pdf(&v, &l);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let (reflected, transmitted) = boundary.eval(&v, &l);
+        // `eval` and `pdf` only know of the surface against air, other ratios
+        // are left out of light sampling like specular bounces
+        let exact = (boundary.eta / self.eta(rec) - 1.0).abs() < 1e-4;
+        let throughput = (reflected + transmitted) / pdf;
+        Some(ScatterRecord {
+            direction: frame.to_world(&l).unit_vector(),
+            throughput: Color::new(throughput, throughput, throughput),
+            pdf: if exact { pdf } else { 0.0 },
+            specular: !exact,
+        })
+    }
+
+    fn eval(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> Color {
+        let boundary = match self.boundary(rec, self.eta(rec)) {
+            Some(boundary) => boundary,
+            None => return Color::zero(),
+        };
+        let frame = Onb::from_w(&rec.normal);
+        let (reflected, transmitted) = boundary.eval(
+            &frame.to_local(&-wi.unit_vector()),
+            &frame.to_local(&wo.unit_vector()),
+        );
+        let f = reflected + transmitted;
+        Color::new(f, f, f)
+    }
+
+    fn pdf(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> f32 {
+        match self.boundary(rec, self.eta(rec)) {
+            Some(boundary) => {
+                let frame = Onb::from_w(&rec.normal);
+                boundary.pdf(
+                    &frame.to_local(&-wi.unit_vector()),
+                    &frame.to_local(&wo.unit_vector()),
+                )
+            }
+            None => 0.0,
+        }
+    }
 
-        let refraction_ratio = if rec.front_face { 1.0 / self.n } else { self.n };
-        let unit_direction = r_in.direction.unit_vector();
+    fn interior(&self) -> Option<Interior> {
+        Some(self.inside())
+    }
+}
+
+/// How the refractive index of a dielectric changes with the wavelength
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Dispersion {
+    /// `n = a + b / λ²`, with λ in micrometers
+    Cauchy { a: f32, b: f32 },
+    /// `n² = 1 + Σ bᵢλ² / (λ² - cᵢ)`, with λ in micrometers, as given for
+    /// optical glasses
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    /// The refractive index at a wavelength in nanometers
+    pub fn index(&self, wavelength: f32) -> f32 {
+        let l2 = (wavelength / 1000.0).powi(2);
+        match self {
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => {
+                let sum: f32 = b.iter().zip(c).map(|(b, c)| b * l2 / (l2 - c)).sum();
+                (1.0 + sum).max(1.0).sqrt()
+            }
+        }
+    }
+}
 
-        let cos_theta = (-unit_direction).dot(&rec.normal).min(1.0);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+/// Picks whether a ray reflects off or refracts through a dielectric surface, by
+/// the chance of it being reflected. Returns the unit direction it leaves in,
+/// and whether it went through the surface.
+/// # Arguments
+/// * `r_in` - The ray hitting the surface
+/// * `rec` - Where it hits
+/// * `refraction_ratio` - The refractive index of the side the ray comes from,
+///   divided by the one of the other side
+pub fn reflect_or_refract(r_in: &Ray, rec: &HitRecord, refraction_ratio: f32) -> (Vec3, bool) {
+    let unit_direction = r_in.direction.unit_vector();
 
-        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+    let cos_theta = (-unit_direction).dot(&rec.normal).min(1.0);
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        let direction =
-            if cannot_refract || reflectance(cos_theta, refraction_ratio) > random_float() {
-                unit_direction.reflect(&rec.normal)
+    let cannot_refract = refraction_ratio * sin_theta > 1.0;
+
+    if cannot_refract || reflectance(cos_theta, refraction_ratio) > random_float() {
+        (unit_direction.reflect(&rec.normal).unit_vector(), false)
+    } else {
+        (
+            unit_direction
+                .refract(&rec.normal, refraction_ratio)
+                .unit_vector(),
+            true,
+        )
+    }
+}
+
+/// The dielectrics a path is inside of, innermost last, to know which one a ray
+/// travels through and which surfaces actually separate two of them.
+#[derive(Debug, Clone, Default)]
+pub struct InteriorStack {
+    interiors: Vec<Interior>,
+}
+
+impl InteriorStack {
+    /// The dielectric rays travel through, the one with the highest priority
+    /// among those they are inside of, or the latest entered on ties
+    pub fn current(&self) -> Option<&Interior> {
+        highest(&self.interiors)
+    }
+
+    /// Scatters a ray hitting the surface of a dielectric, like
+    /// `Material::sample`, and keeps track of it entering or leaving the
+    /// dielectric. Surfaces within a dielectric of higher priority are passed
+    /// straight through.
+    /// # Arguments
+    /// * `r_in` - The ray hitting the surface
+    /// * `rec` - Where it hits
+    /// * `interior` - The inside of the material hit
+    /// * `material` - The material hit, which scatters the ray
+    pub fn scatter(
+        &mut self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        interior: Interior,
+        material: &(dyn Material + Send + Sync),
+    ) -> Option<ScatterRecord> {
+        let entering = rec.front_face;
+        let inside = self.interiors.iter().rposition(|i| i.id == interior.id);
+        // The dielectrics on both sides of the surface, besides this one
+        let mut others = self.interiors.clone();
+        if let (false, Some(index)) = (entering, inside) {
+            others.remove(index);
+        }
+        let outside = highest(&others);
+
+        let sample = match outside {
+            Some(outside) if outside.priority > interior.priority => ScatterRecord {
+                direction: r_in.direction.unit_vector(),
+                throughput: Color::new(1.0, 1.0, 1.0),
+                pdf: 0.0,
+                specular: true,
+            },
+            _ => {
+                let outside_n = outside.map_or(1.0, |outside| outside.index(r_in.wavelength));
+                let inside_n = interior.index(r_in.wavelength);
+                let refraction_ratio = if entering {
+                    outside_n / inside_n
+                } else {
+                    inside_n / outside_n
+                };
+                material.sample_boundary(r_in, rec, refraction_ratio)?
+            }
+        };
+
+        // The normal faces the ray, which crosses the surface when it leaves
+        // on the other side
+        if sample.direction.dot(&rec.normal) < 0.0 {
+            self.cross(rec, interior);
+        }
+        Some(sample)
+    }
+
+    /// The dielectric rays travel through once they went through the surface
+    /// of `interior` hit at `rec`, without scattering them
+    pub fn beyond(&self, rec: &HitRecord, interior: Interior) -> Option<Interior> {
+        let mut crossed = self.clone();
+        crossed.cross(rec, interior);
+        crossed.current().copied()
+    }
+
+    // Enters or leaves `interior`, depending on the side its surface is hit from
+    fn cross(&mut self, rec: &HitRecord, interior: Interior) {
+        if rec.front_face {
+            self.interiors.push(interior);
+        } else if let Some(index) = self.interiors.iter().rposition(|i| i.id == interior.id) {
+            self.interiors.remove(index);
+        }
+    }
+}
+
+fn highest(interiors: &[Interior]) -> Option<&Interior> {
+    interiors.iter().rev().reduce(|highest, i| {
+        if i.priority > highest.priority {
+            i
+        } else {
+            highest
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::tests::{accepted_share, check_sampling},
+        textures::solid::SolidColor,
+        utils::vec::Point3,
+    };
+
+    fn hit(front_face: bool) -> HitRecord {
+        let mut rec = HitRecord::new(
+            Point3::zero(),
+            Vec3::new(0.0, 0.0, 1.0),
+            None,
+            1.0,
+            0.0,
+            0.0,
+        );
+        rec.front_face = front_face;
+        rec
+    }
+
+    #[test]
+    fn tells_apart_objects_of_the_same_glass() {
+        let glass = Dielectric::new(1.5).with_absorption(Color::new(0.5, 0.8, 0.9), 1.0);
+        let first = glass.inside();
+        let second = Dielectric::new(1.5)
+            .with_absorption(Color::new(0.5, 0.8, 0.9), 1.0)
+            .inside();
+        assert_eq!(glass.clone().inside().id, first.id);
+        assert_ne!(first.id, second.id);
+
+        let mut stack = InteriorStack::default();
+        stack.cross(&hit(true), first);
+        stack.cross(&hit(true), second);
+        // Leaving the first object, where both overlap, keeps the path inside the second
+        assert_eq!(
+            stack.beyond(&hit(false), first).map(|i| i.id),
+            Some(second.id)
+        );
+        stack.cross(&hit(false), first);
+        assert_eq!(stack.current().map(|i| i.id), Some(second.id));
+        assert!(stack.beyond(&hit(false), second).is_none());
+    }
+
+    #[test]
+    fn refracts_smooth_surfaces_by_snells_law() {
+        let glass = Dielectric::new(1.5);
+        let rec = hit(true);
+        let r_in = Ray::new(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0), 0.0);
+        let sin_in = 0.5_f32.sqrt();
+        let (mut reflected, mut refracted) = (0, 0);
+        for _ in 0..1_000 {
+            let sample = glass.sample(&r_in, &rec).unwrap();
+            assert!(sample.specular && sample.pdf == 0.0);
+            if sample.direction.z > 0.0 {
+                assert!((sample.direction - Vec3::new(sin_in, 0.0, sin_in)).length() < 1e-5);
+                reflected += 1;
             } else {
-                unit_direction.refract(&rec.normal, refraction_ratio)
-            };
+                assert!((sample.direction.x - sin_in / 1.5).abs() < 1e-5);
+                refracted += 1;
+            }
+        }
+        // About 5% of the light is reflected at 45 degrees
+        assert!(reflected > 10 && refracted > 900);
+    }
 
-        *scattered = Ray::new(rec.point, direction, r_in.time);
+    #[test]
+    fn reflects_all_light_beyond_the_critical_angle() {
+        let glass = Dielectric::new(1.5);
+        let rec = hit(false);
+        // Leaving the glass at 60 degrees, beyond its critical angle of about 42
+        let direction = Vec3::new(3.0_f32.sqrt(), 0.0, 1.0);
+        let r_in = Ray::new(-direction, direction, 0.0);
+        for _ in 0..100 {
+            let sample = glass.sample(&r_in, &hit(false)).unwrap();
+            assert!(sample.direction.z < 0.0);
+        }
+        assert_eq!(glass.eval(&direction, &-direction, &rec), Color::zero());
+    }
 
-        true
+    #[test]
+    fn samples_frosted_glass_with_the_density_of_pdf() {
+        let glass = Dielectric::new(1.5)
+            .with_roughness(Box::new(SolidColor::new(Color::new(0.5, 0.5, 0.5))));
+        let wi = Vec3::new(0.5, 0.2, -1.0);
+        // From the air, and from inside the glass
+        for front_face in [true, false] {
+            let rec = hit(front_face);
+            let integral = check_sampling(&glass, &rec, wi);
+            let share = accepted_share(&glass, &rec, wi);
+            assert!((integral - share).abs() < 0.01, "{} != {}", integral, share);
+        }
     }
 }
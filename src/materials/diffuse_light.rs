@@ -0,0 +1,31 @@
+use crate::{
+    material,
+    materials::{Material, ScatterRecord},
+    utils::{
+        hittable::HitRecord,
+        ray::Ray,
+        vec::{Color, Point3},
+    },
+};
+
+// A light source, emitting `emit` (or its texture) in every direction without
+// reflecting anything. Values above 1 make brighter lights.
+material!(DiffuseLight { emit: Color });
+
+impl Material for DiffuseLight {
+    fn sample(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, u: f32, v: f32, point: &Point3) -> Color {
+        if let Some(texture) = &self.texture {
+            texture.value(u, v, point)
+        } else {
+            self.emit
+        }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.emitted(rec.u, rec.v, &rec.point)
+    }
+}
@@ -0,0 +1,75 @@
+use std::f32::consts::PI;
+
+use crate::{
+    material,
+    materials::{Material, ScatterRecord},
+    utils::{
+        helpers::random_float,
+        hittable::HitRecord,
+        onb::Onb,
+        ray::Ray,
+        vec::{Color, Vec3},
+    },
+};
+
+// The Henyey-Greenstein phase function of a participating medium, scattering
+// light mostly forward when `g` is positive, backward when it is negative, and
+// equally in every direction at 0, between -1 and 1. `albedo` (or its texture)
+// is the share of light scattered rather than absorbed.
+material!(HenyeyGreenstein {
+    albedo: Color,
+    g: f32
+});
+
+impl HenyeyGreenstein {
+    // The phase function for light turning by an angle whose cosine is `cos_theta`
+    fn phase(&self, cos_theta: f32) -> f32 {
+        let denominator = 1.0 + self.g * self.g - 2.0 * self.g * cos_theta;
+        (1.0 - self.g * self.g) / (4.0 * PI * denominator * denominator.max(1e-8).sqrt())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        // Inverting the cumulative distribution of the phase function, so that it
+        // cancels out with the pdf
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * random_float()
+        } else {
+            let ratio = (1.0 - g * g) / (1.0 - g + 2.0 * g * random_float());
+            ((1.0 + g * g - ratio * ratio) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_float();
+        let direction = Onb::from_w(&r_in.direction).to_world(&Vec3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos_theta,
+        ));
+
+        Some(ScatterRecord {
+            direction: direction.unit_vector(),
+            throughput: self.albedo(rec),
+            pdf: self.phase(cos_theta),
+            specular: false,
+        })
+    }
+
+    // Media have no surface to take the cosine of
+    fn eval(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> Color {
+        self.albedo(rec) * self.pdf(wi, wo, rec)
+    }
+
+    fn pdf(&self, wi: &Vec3, wo: &Vec3, _rec: &HitRecord) -> f32 {
+        self.phase(wi.unit_vector().dot(&wo.unit_vector()))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        if let Some(texture) = &self.texture {
+            texture.value_at(rec)
+        } else {
+            self.albedo
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::f32::consts::PI;
+
+use crate::{
+    material,
+    materials::{Material, ScatterRecord},
+    utils::{
+        helpers::random_vector,
+        hittable::HitRecord,
+        ray::Ray,
+        vec::{Color, Vec3},
+    },
+};
+
+// The phase function of a participating medium, scattering light equally in
+// every direction. `albedo` (or its texture) is the share of light scattered
+// rather than absorbed.
+material!(Isotropic { albedo: Color });
+
+impl Material for Isotropic {
+    fn sample(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        // Uniform over the sphere, like the phase function, which cancels out with the pdf
+        Some(ScatterRecord {
+            direction: random_vector(),
+            throughput: self.albedo(rec),
+            pdf: 1.0 / (4.0 * PI),
+            specular: false,
+        })
+    }
+
+    // Media have no surface to take the cosine of
+    fn eval(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> Color {
+        self.albedo(rec) * self.pdf(wi, wo, rec)
+    }
+
+    fn pdf(&self, _wi: &Vec3, _wo: &Vec3, _rec: &HitRecord) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        if let Some(texture) = &self.texture {
+            texture.value_at(rec)
+        } else {
+            self.albedo
+        }
+    }
+}
@@ -1,28 +1,76 @@
+use std::f32::consts::PI;
+
 use crate::{
     material,
-    materials::Material,
-    utils::{helpers::random_vector, hittable::HitRecord, ray::Ray, vec::Color},
+    materials::{Material, ScatterRecord},
+    utils::{
+        helpers::random_vector,
+        hittable::HitRecord,
+        ray::Ray,
+        vec::{Color, Vec3},
+    },
 };
 
 material!(Lambertian { albedo: Color });
 
 impl Material for Lambertian {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let scatter_direction = rec.normal + random_vector();
-
-        *scattered = Ray::new(rec.point, scatter_direction, r_in.time);
-        *attenuation = if let Some(texture) = &self.texture {
-            texture.value(rec.u, rec.v, &rec.point)
+    fn sample(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        // Cosine-weighted around the normal, so the cosine and the pdf cancel out
+        let mut scatter_direction = rec.normal + random_vector();
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+        let direction = scatter_direction.unit_vector();
+
+        Some(ScatterRecord {
+            direction,
+            throughput: self.albedo(rec),
+            pdf: direction.dot(&rec.normal).max(0.0) / PI,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> Color {
+        self.albedo(rec) * self.pdf(wi, wo, rec)
+    }
+
+    fn pdf(&self, _wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> f32 {
+        let cosine = wo.unit_vector().dot(&rec.normal);
+        cosine.max(0.0) / PI
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        if let Some(texture) = &self.texture {
+            texture.value_at(rec)
         } else {
             self.albedo
-        };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::tests::{check_sampling, hit_from_above};
+
+    #[test]
+    fn samples_with_the_density_of_pdf() {
+        let lambertian = Lambertian::new(Color::new(0.8, 0.4, 0.2));
+        let rec = hit_from_above();
+        let integral = check_sampling(&lambertian, &rec, Vec3::new(1.0, 0.0, -1.0));
+        assert!((integral - 1.0).abs() < 0.01, "{}", integral);
+    }
 
-        true
+    #[test]
+    fn evaluates_to_the_albedo_over_pi_times_the_cosine() {
+        let albedo = Color::new(0.8, 0.4, 0.2);
+        let lambertian = Lambertian::new(albedo);
+        let rec = hit_from_above();
+        let wi = Vec3::new(0.0, 0.0, -1.0);
+        let wo = Vec3::new(1.0, 0.0, 1.0).unit_vector();
+        let expected = albedo * wo.z / PI;
+        assert!((lambertian.eval(&wi, &wo, &rec) - expected).length() < 1e-6);
+        // Nothing goes through the surface
+        assert_eq!(lambertian.eval(&wi, &-wo, &rec), Color::zero());
     }
 }
@@ -1,9 +1,16 @@
+use std::f32::consts::PI;
+
 use crate::{
     material,
-    utils::{helpers::random_in_unit_sphere, hittable::HitRecord, ray::Ray, vec::Color},
+    utils::{
+        helpers::random_in_unit_sphere,
+        hittable::HitRecord,
+        ray::Ray,
+        vec::{Color, Vec3},
+    },
 };
 
-use super::Material;
+use super::{Material, ScatterRecord};
 
 material!(Metal {
     albedo: Color,
@@ -11,21 +18,95 @@ material!(Metal {
 });
 
 impl Material for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
+    // Perfect mirrors, without any fuzz, have no pdf and are treated as specular
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         let reflected = r_in.direction.unit_vector().reflect(&rec.normal);
-        *scattered = Ray::new(
-            rec.point,
-            reflected + self.fuzz * random_in_unit_sphere().unit_vector(),
-            r_in.time,
-        );
-        *attenuation = self.albedo;
-
-        scattered.direction.dot(&rec.normal) > 0.0
+        let direction = reflected + self.fuzz * random_in_unit_sphere().unit_vector();
+        if direction.dot(&rec.normal) <= 0.0 {
+            return None;
+        }
+        let direction = direction.unit_vector();
+        let specular = self.fuzz <= 0.0;
+        let pdf = self.pdf(&r_in.direction, &direction, rec);
+        // Directions grazing the sphere of fuzz can round to just outside of it
+        if !specular && pdf <= 0.0 {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            direction,
+            throughput: self.albedo(rec),
+            pdf,
+            specular,
+        })
+    }
+
+    // Directions below the surface are absorbed, so the BSDF times the cosine is
+    // the albedo spread by the pdf of the fuzzy reflection
+    fn eval(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> Color {
+        if wo.dot(&rec.normal) <= 0.0 {
+            return Color::zero();
+        }
+        self.albedo(rec) * self.pdf(wi, wo, rec)
+    }
+
+    // The fuzzy direction is the mirror direction plus a random point of a sphere of
+    // radius `fuzz`, so the density of a direction adds up over the points of that
+    // sphere it goes through: their density over its area, moved to solid angle
+    fn pdf(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> f32 {
+        if self.fuzz <= 0.0 {
+            return 0.0;
+        }
+        let reflected = wi.unit_vector().reflect(&rec.normal);
+        let direction = wo.unit_vector();
+        let b = direction.dot(&reflected);
+        let discriminant = b * b - (1.0 - self.fuzz * self.fuzz);
+        if discriminant < 0.0 {
+            return 0.0;
+        }
+        let root = discriminant.sqrt();
+        [b - root, b + root]
+            .iter()
+            .filter(|&&t| t > 0.0)
+            .map(|&t| {
+                let normal = (t * direction - reflected) / self.fuzz;
+                let cosine = direction.dot(&normal).abs().max(1e-4);
+                t * t / (cosine * 4.0 * PI * self.fuzz * self.fuzz)
+            })
+            .sum()
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        if let Some(texture) = &self.texture {
+            texture.value_at(rec)
+        } else {
+            self.albedo
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::tests::{check_sampling, hit_from_above};
+
+    #[test]
+    fn samples_fuzzy_reflections_with_the_density_of_pdf() {
+        let metal = Metal::new(Color::new(0.9, 0.8, 0.5), 0.5);
+        let rec = hit_from_above();
+        // Fuzzy directions below the surface are absorbed, but still counted by pdf
+        let integral = check_sampling(&metal, &rec, Vec3::new(1.0, 0.0, -2.0));
+        assert!((integral - 1.0).abs() < 0.01, "{}", integral);
+    }
+
+    #[test]
+    fn mirrors_are_specular() {
+        let metal = Metal::new(Color::new(0.9, 0.8, 0.5), 0.0);
+        let rec = hit_from_above();
+        let r_in = Ray::new(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0), 0.0);
+        let sample = metal.sample(&r_in, &rec).unwrap();
+        assert!(sample.specular);
+        assert!((sample.direction - Vec3::new(1.0, 0.0, 1.0).unit_vector()).length() < 1e-6);
+        assert_eq!(metal.pdf(&r_in.direction, &sample.direction, &rec), 0.0);
     }
 }
@@ -0,0 +1,388 @@
+use std::f32::consts::PI;
+
+use crate::utils::vec::{Color, Vec3};
+
+/// The distribution of the normals of the tiny facets making up a rough surface,
+/// in the GGX (Trowbridge-Reitz) model. Directions are given in a local frame
+/// whose Z axis is the normal of the surface, and point away from it.
+#[derive(Debug, Copy, Clone)]
+pub struct Ggx {
+    /// The roughness along the X axis of the local frame
+    pub alpha_x: f32,
+    /// The roughness along the Y axis of the local frame
+    pub alpha_y: f32,
+}
+
+impl Ggx {
+    pub fn new(alpha_x: f32, alpha_y: f32) -> Self {
+        // Perfectly smooth surfaces would need a specular material instead
+        Self {
+            alpha_x: alpha_x.max(1e-3),
+            alpha_y: alpha_y.max(1e-3),
+        }
+    }
+
+    /// The distribution of a surface of perceived `roughness`, between 0 and 1,
+    /// the same in every direction
+    pub fn from_roughness(roughness: f32) -> Self {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        Self::new(alpha, alpha)
+    }
+
+    /// The density of facets facing `m`, over solid angle, projected onto the surface
+    pub fn d(&self, m: &Vec3) -> f32 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let x = m.x / self.alpha_x;
+        let y = m.y / self.alpha_y;
+        let t = x * x + y * y + m.z * m.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * t * t)
+    }
+
+    // Smith's auxiliary function, from which the shadowing terms follow
+    fn lambda(&self, w: &Vec3) -> f32 {
+        let z2 = w.z * w.z;
+        if z2 <= 0.0 {
+            return f32::INFINITY;
+        }
+        let tan2 = (self.alpha_x * self.alpha_x * w.x * w.x
+            + self.alpha_y * self.alpha_y * w.y * w.y)
+            / z2;
+        ((1.0 + tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// The share of the facets seen from `w` which are not hidden by others
+    pub fn g1(&self, w: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// The share of the facets seen from both `v` and `l`, taking into account
+    /// that facets hidden from one are often hidden from the other
+    pub fn g(&self, v: &Vec3, l: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(v) + self.lambda(l))
+    }
+
+    /// Picks the normal of a facet seen from `v`, in proportion to how much of it
+    /// is visible (Heitz 2018), which wastes no samples on hidden facets
+    /// # Arguments
+    /// * `v` - The direction the surface is seen from, above it
+    /// * `u1` - A random number between 0 and 1
+    /// * `u2` - Another random number between 0 and 1
+    pub fn sample_visible(&self, v: &Vec3, u1: f32, u2: f32) -> Vec3 {
+        // Stretch the view so the facets make up a hemisphere
+        let vh = Vec3::new(self.alpha_x * v.x, self.alpha_y * v.y, v.z).unit_vector();
+        let length_squared = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length_squared > 0.0 {
+            Vec3::new(-vh.y, vh.x, 0.0) / length_squared.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+        // A point of the disk the hemisphere projects to, squeezed onto its
+        // visible part
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unit_vector()
+    }
+
+    /// The probability density of `sample_visible` picking `m`, over solid angle
+    pub fn pdf_visible(&self, v: &Vec3, m: &Vec3) -> f32 {
+        if v.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(v) * v.dot(m).max(0.0) * self.d(m) / v.z
+    }
+}
+
+/// The rough surface between two dielectrics, whose facets reflect and refract
+/// light (Walter et al. 2007). Directions are given in the local frame of the
+/// surface, on either of its sides.
+///
+/// Light refracted through the surface is not scaled by the square of the
+/// ratio of refractive indices, as with smooth dielectrics, so paths starting and
+/// ending in air carry the right amount of it.
+#[derive(Debug, Copy, Clone)]
+pub struct RoughBoundary {
+    pub ggx: Ggx,
+    /// The refractive index below the surface, divided by the one above it
+    pub eta: f32,
+}
+
+impl RoughBoundary {
+    /// The light reflected and the light refracted toward `v` from `l`: the
+    /// BSDF value of each times the cosine of `l`
+    pub fn eval(&self, v: &Vec3, l: &Vec3) -> (f32, f32) {
+        let (v, l, eta) = self.oriented(v, l);
+        if v.z <= 0.0 || l.z == 0.0 {
+            return (0.0, 0.0);
+        }
+        if l.z > 0.0 {
+            let h = (v + l).unit_vector();
+            let fresnel = fresnel_dielectric(v.dot(&h), eta);
+            return (
+                fresnel * self.ggx.d(&h) * self.ggx.g(&v, &l) / (4.0 * v.z),
+                0.0,
+            );
+        }
+        let h = match refraction_half_vector(&v, &l, eta) {
+            Some(h) => h,
+            None => return (0.0, 0.0),
+        };
+        let (vh, lh) = (v.dot(&h), l.dot(&h));
+        let denominator = vh + eta * lh;
+        // Walter's BTDF, times the square of `eta` which it divides radiance by
+        let transmitted = (1.0 - fresnel_dielectric(vh, eta))
+            * self.ggx.d(&h)
+            * self.ggx.g(&v, &l)
+            * vh
+            * lh.abs()
+            * eta
+            * eta
+            / (v.z * denominator * denominator);
+        (0.0, transmitted)
+    }
+
+    /// The probability density of `sample` picking `l`, seen from `v`
+    pub fn pdf(&self, v: &Vec3, l: &Vec3) -> f32 {
+        let (v, l, eta) = self.oriented(v, l);
+        if v.z <= 0.0 || l.z == 0.0 {
+            return 0.0;
+        }
+        if l.z > 0.0 {
+            let h = (v + l).unit_vector();
+            let vh = v.dot(&h);
+            if vh <= 0.0 {
+                return 0.0;
+            }
+            return fresnel_dielectric(vh, eta) * self.ggx.pdf_visible(&v, &h) / (4.0 * vh);
+        }
+        let h = match refraction_half_vector(&v, &l, eta) {
+            Some(h) => h,
+            None => return 0.0,
+        };
+        let (vh, lh) = (v.dot(&h), l.dot(&h));
+        let denominator = vh + eta * lh;
+        // From the density of the facets to the one of the refracted directions
+        let jacobian = eta * eta * lh.abs() / (denominator * denominator);
+        (1.0 - fresnel_dielectric(vh, eta)) * self.ggx.pdf_visible(&v, &h) * jacobian
+    }
+
+    /// Picks the direction light comes from, seen from `v`: reflected or
+    /// refracted by a visible facet, by the chance of it being reflected.
+    /// `None` when it would end up on the wrong side of the surface.
+    /// # Arguments
+    /// * `v` - The direction the surface is seen from
+    /// * `u1`, `u2`, `u3` - Random numbers between 0 and 1
+    pub fn sample(&self, v: &Vec3, u1: f32, u2: f32, u3: f32) -> Option<Vec3> {
+        let below = v.z < 0.0;
+        let (v, _, eta) = self.oriented(v, v);
+        if v.z == 0.0 {
+            return None;
+        }
+        let m = self.ggx.sample_visible(&v, u1, u2);
+        let vm = v.dot(&m);
+        let reflected = u3 < fresnel_dielectric(vm, eta);
+        let l = if reflected {
+            2.0 * vm * m - v
+        } else {
+            (-v).refract(&m, 1.0 / eta).unit_vector()
+        };
+        // Steep facets can send light to the wrong side
+        if (l.z > 0.0) != reflected || l.z == 0.0 {
+            return None;
+        }
+        Some(if below { Vec3::new(l.x, l.y, -l.z) } else { l })
+    }
+
+    // The directions and ratio of refractive indices seen from above the
+    // surface, where `v` is
+    fn oriented(&self, v: &Vec3, l: &Vec3) -> (Vec3, Vec3, f32) {
+        if v.z >= 0.0 {
+            (*v, *l, self.eta)
+        } else {
+            let flip = |w: &Vec3| Vec3::new(w.x, w.y, -w.z);
+            (flip(v), flip(l), 1.0 / self.eta)
+        }
+    }
+}
+
+// The normal of the facet refracting light between `v` above the surface and
+// `l` below it, facing `v`
+fn refraction_half_vector(v: &Vec3, l: &Vec3, eta: f32) -> Option<Vec3> {
+    let h = -(*v + eta * *l);
+    if h.near_zero() {
+        return None;
+    }
+    let h = h.unit_vector();
+    let h = if h.z < 0.0 { -h } else { h };
+    // Light cannot refract off the back of a facet
+    (v.dot(&h) > 0.0 && l.dot(&h) < 0.0).then_some(h)
+}
+
+/// The share of light reflected off a dielectric, by the exact Fresnel equations
+/// for unpolarized light
+/// # Arguments
+/// * `cos_i` - The cosine of the angle between the light and the normal, positive
+/// * `eta` - The refractive index of the other side, divided by the one of the
+///   side the light comes from
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        // Total internal reflection
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    (rs * rs + rp * rp) / 2.0
+}
+
+/// Schlick's approximation of the share of light reflected, from the one `f0`
+/// reflected head-on
+pub fn fresnel_schlick(f0: Color, cosine: f32) -> Color {
+    let weight = (1.0 - cosine.clamp(0.0, 1.0)).powi(5);
+    f0 + (Color::new(1.0, 1.0, 1.0) - f0) * weight
+}
+
+/// The share of light reflected off a metal, by the Fresnel equations for
+/// unpolarized light, in each channel
+/// # Arguments
+/// * `cos_i` - The cosine of the angle between the light and the normal, positive
+/// * `eta` - The real part of the refractive index of the metal, relative to
+///   the side the light comes from
+/// * `k` - The imaginary part of the refractive index, how strongly the metal absorbs light
+pub fn fresnel_conductor(cos_i: f32, eta: Color, k: Color) -> Color {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let channel = |eta: f32, k: f32| {
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos2.sqrt() * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        (rs + rp) / 2.0
+    };
+    Color::new(
+        channel(eta.x, k.x),
+        channel(eta.y, k.y),
+        channel(eta.z, k.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{materials::tests::integrate, utils::helpers::random_float};
+
+    fn distributions() -> [Ggx; 3] {
+        [Ggx::new(0.3, 0.3), Ggx::new(0.6, 0.6), Ggx::new(0.1, 0.5)]
+    }
+
+    #[test]
+    fn projected_facets_cover_the_surface() {
+        for ggx in distributions() {
+            let area = integrate(|m| ggx.d(m) * m.z.max(0.0));
+            assert!((area - 1.0).abs() < 0.01, "{:?}: {}", ggx, area);
+        }
+    }
+
+    #[test]
+    fn visible_normals_pdf_integrates_to_one() {
+        let v = Vec3::new(0.5, -0.3, 0.6).unit_vector();
+        for ggx in distributions() {
+            let integral = integrate(|m| ggx.pdf_visible(&v, m));
+            assert!((integral - 1.0).abs() < 0.01, "{:?}: {}", ggx, integral);
+        }
+    }
+
+    #[test]
+    fn samples_visible_normals_with_the_density_of_pdf() {
+        let v = Vec3::new(0.5, -0.3, 0.6).unit_vector();
+        let ggx = Ggx::new(0.1, 0.5);
+        // The share of samples within a cone around the normal, against the
+        // integral of the pdf over that cone
+        let inside = |m: &Vec3| m.z > 0.9;
+        let expected = integrate(|m| {
+            if inside(m) {
+                ggx.pdf_visible(&v, m)
+            } else {
+                0.0
+            }
+        });
+        let samples = 100_000;
+        let count = (0..samples)
+            .map(|_| ggx.sample_visible(&v, random_float(), random_float()))
+            .inspect(|m| assert!(m.z > 0.0 && v.dot(m) >= 0.0))
+            .filter(inside)
+            .count();
+        let share = count as f32 / samples as f32;
+        assert!((share - expected).abs() < 0.01, "{} != {}", share, expected);
+    }
+
+    #[test]
+    fn fresnel_reflects_everything_at_grazing_angles() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert!((fresnel_dielectric(0.0, 1.5) - 1.0).abs() < 1e-6);
+        // Beyond the critical angle, from inside the glass
+        assert_eq!(fresnel_dielectric(0.5, 1.0 / 1.5), 1.0);
+
+        let (eta, k) = (Color::new(0.2, 0.9, 1.1), Color::new(3.9, 2.5, 2.1));
+        let head_on = fresnel_conductor(1.0, eta, k);
+        let expected =
+            |eta: f32, k: f32| ((eta - 1.0).powi(2) + k * k) / ((eta + 1.0).powi(2) + k * k);
+        assert!((head_on.x - expected(eta.x, k.x)).abs() < 1e-5);
+        assert!((head_on.z - expected(eta.z, k.z)).abs() < 1e-5);
+        assert!((fresnel_conductor(0.0, eta, k) - Color::new(1.0, 1.0, 1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn rough_boundaries_sample_with_the_density_of_pdf() {
+        let boundary = RoughBoundary {
+            ggx: Ggx::new(0.3, 0.3),
+            eta: 1.5,
+        };
+        // Seen from the air above, and from the glass below
+        for v in [
+            Vec3::new(0.4, 0.1, 0.8).unit_vector(),
+            Vec3::new(-0.2, 0.5, -0.7).unit_vector(),
+        ] {
+            let samples = 100_000;
+            let mut accepted = 0;
+            let (mut refracted, mut refracted_weight) = (0, 0.0);
+            for _ in 0..samples {
+                let l = match boundary.sample(&v, random_float(), random_float(), random_float()) {
+                    Some(l) => l,
+                    None => continue,
+                };
+                accepted += 1;
+                let pdf = boundary.pdf(&v, &l);
+                assert!(pdf > 0.0);
+                // Facets are picked as often as they reflect or refract light,
+                // so no more than what arrives leaves
+                let (reflected, transmitted) = boundary.eval(&v, &l);
+                let weight = (reflected + transmitted) / pdf;
+                assert!(weight <= 1.0 + 1e-3);
+                if (l.z > 0.0) != (v.z > 0.0) {
+                    refracted += 1;
+                    refracted_weight += weight;
+                }
+            }
+            // Like through smooth glass, refracted light is not scaled by the
+            // square of `eta` either way, only shadowed by the facets
+            assert!(refracted_weight / refracted as f32 > 0.9);
+            let share = accepted as f32 / samples as f32;
+            let integral = integrate(|l| boundary.pdf(&v, l));
+            assert!((integral - share).abs() < 0.01, "{} != {}", integral, share);
+        }
+    }
+}
@@ -2,21 +2,131 @@ use std::fmt::Debug;
 
 use dyn_clonable::clonable;
 
-use crate::utils::{hittable::HitRecord, ray::Ray, vec::Color};
+use crate::{
+    materials::dielectric::Dispersion,
+    utils::{
+        hittable::HitRecord,
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+};
 
+pub mod conductor;
 pub mod dielectric;
+pub mod diffuse_light;
+pub mod henyey_greenstein;
+pub mod isotropic;
 pub mod lambertian;
 pub mod metal;
+pub mod microfacet;
+pub mod principled;
 
+/// A direction picked by a material for light to carry on in
+#[derive(Debug, Copy, Clone)]
+pub struct ScatterRecord {
+    /// The unit direction leaving the surface
+    pub direction: Vec3,
+    /// The BSDF value times the cosine of `direction`, divided by its pdf: the
+    /// share of the light carried along it
+    pub throughput: Color,
+    /// The probability density of `direction` over solid angle, 0 for specular samples
+    pub pdf: f32,
+    /// Whether `direction` was the only one possible, such as off a mirror or
+    /// through glass, which `eval` and `pdf` cannot account for
+    pub specular: bool,
+}
+
+/// The inside of a dielectric, which rays travel through between its surfaces
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interior {
+    /// The refractive index
+    pub n: f32,
+    /// How the refractive index changes with the wavelength, in spectral mode
+    pub dispersion: Option<Dispersion>,
+    /// The share of light absorbed per unit of distance, for each channel
+    pub absorption: Color,
+    /// Which one rays travel through where dielectrics overlap, the highest
+    pub priority: i32,
+    /// Tells apart objects made of the same dielectric, which rays can be
+    /// inside of at once
+    pub id: usize,
+}
+
+impl Interior {
+    /// The refractive index for rays of the given wavelength
+    pub fn index(&self, wavelength: Option<f32>) -> f32 {
+        match (self.dispersion, wavelength) {
+            (Some(dispersion), Some(wavelength)) => dispersion.index(wavelength),
+            _ => self.n,
+        }
+    }
+
+    /// The share of light left after traveling `distance` inside
+    pub fn transmittance(&self, distance: f32) -> Color {
+        // Beer–Lambert law: light fades exponentially with the distance traveled
+        Color::new(
+            (-self.absorption.x * distance).exp(),
+            (-self.absorption.y * distance).exp(),
+            (-self.absorption.z * distance).exp(),
+        )
+    }
+}
+
+/// How a surface or medium scatters light. Directions `wi` are the ones rays
+/// arrive along, toward the hit point, and directions `wo` the ones they leave
+/// along, so that light flows from `wo` back to `wi` on paths traced from the
+/// camera.
 #[clonable]
 pub trait Material: Clone + Send + Sync {
-    fn scatter(
+    /// Picks the direction of the ray leaving the surface, `None` when it is absorbed
+    /// # Arguments
+    /// * `r_in` - The ray hitting the surface
+    /// * `rec` - Where it hits
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord>;
+
+    /// The BSDF value times the cosine of `wo`, for directions the material did
+    /// not pick itself, e.g. toward a light. Zero for specular materials.
+    fn eval(&self, _wi: &Vec3, _wo: &Vec3, _rec: &HitRecord) -> Color {
+        Color::zero()
+    }
+
+    /// The probability density of `sample` picking `wo`, over solid angle
+    fn pdf(&self, _wi: &Vec3, _wo: &Vec3, _rec: &HitRecord) -> f32 {
+        0.0
+    }
+
+    /// The color of the surface at the hit point, as shown by the albedo integrator
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    /// Picks the direction of a ray hitting the surface of a dielectric, like
+    /// `sample` does against air, between two media of the given ratio of
+    /// refractive indices. Only materials with an `interior` need it.
+    /// # Arguments
+    /// * `r_in` - The ray hitting the surface
+    /// * `rec` - Where it hits
+    /// * `refraction_ratio` - The refractive index of the side the ray comes from,
+    ///   divided by the one of the other side
+    fn sample_boundary(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool;
+        _refraction_ratio: f32,
+    ) -> Option<ScatterRecord> {
+        self.sample(r_in, rec)
+    }
+
+    /// The inside of the material, for integrators keeping track of the
+    /// dielectrics rays go through. `None` for opaque materials.
+    fn interior(&self) -> Option<Interior> {
+        None
+    }
+
+    /// Light given off at a point of the surface, none unless the material is a light
+    fn emitted(&self, _u: f32, _v: f32, _point: &Point3) -> Color {
+        Color::zero()
+    }
 }
 
 impl Debug for dyn Material + Send + Sync {
@@ -33,7 +143,7 @@ macro_rules! material {
         #[derive(Clone)]
         pub struct $name {
             $(pub $field: $type),*,
-            pub texture: Option<Box<dyn crate::textures::Texture + Send + Sync>>
+            pub texture: Option<Box<dyn $crate::textures::Texture + Send + Sync>>
         }
 
         impl $name {
@@ -43,7 +153,7 @@ macro_rules! material {
                     texture: None
                 }
             }
-            pub fn with_texture($($field: $type),*, texture: Box<dyn crate::textures::Texture + Send + Sync>) -> Self {
+            pub fn with_texture($($field: $type),*, texture: Box<dyn $crate::textures::Texture + Send + Sync>) -> Self {
                 Self {
                     $($field),*,
                     texture: Some(texture)
@@ -52,3 +162,73 @@ macro_rules! material {
         }
     };
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A hit at the origin of a surface facing +Z, from above
+    pub fn hit_from_above() -> HitRecord {
+        let mut rec = HitRecord::new(
+            Point3::zero(),
+            Vec3::new(0.0, 0.0, 1.0),
+            None,
+            1.0,
+            0.5,
+            0.5,
+        );
+        rec.front_face = true;
+        rec
+    }
+
+    /// Checks the directions `sample` picks for a ray arriving along `wi` against
+    /// `eval` and `pdf`, and returns the integral of `pdf` over the sphere of
+    /// directions, which is 1 unless the material picks no direction at times
+    pub fn check_sampling(material: &dyn Material, rec: &HitRecord, wi: Vec3) -> f32 {
+        let r_in = Ray::new(rec.point - wi, wi, 0.0);
+        for _ in 0..10_000 {
+            let sample = match material.sample(&r_in, rec) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            assert!(!sample.specular && sample.pdf > 0.0);
+            let pdf = material.pdf(&wi, &sample.direction, rec);
+            assert!(
+                (pdf - sample.pdf).abs() <= 1e-3 * pdf,
+                "{} != {}",
+                pdf,
+                sample.pdf
+            );
+            let expected = material.eval(&wi, &sample.direction, rec) / pdf;
+            assert!((sample.throughput - expected).length() <= 1e-3 * expected.length());
+        }
+
+        integrate(|wo| material.pdf(&wi, wo, rec))
+    }
+
+    /// The share of rays arriving along `wi` which `sample` does not absorb
+    pub fn accepted_share(material: &dyn Material, rec: &HitRecord, wi: Vec3) -> f32 {
+        let r_in = Ray::new(rec.point - wi, wi, 0.0);
+        let samples = 100_000;
+        let accepted = (0..samples)
+            .filter(|_| material.sample(&r_in, rec).is_some())
+            .count();
+        accepted as f32 / samples as f32
+    }
+
+    /// Integrates a function over the sphere of directions, on a grid of cells
+    /// of the same solid angle
+    pub fn integrate(f: impl Fn(&Vec3) -> f32) -> f32 {
+        let steps = 1000;
+        let mut total = 0.0;
+        for i in 0..steps {
+            let z = 1.0 - 2.0 * (i as f32 + 0.5) / steps as f32;
+            let r = (1.0 - z * z).sqrt();
+            for j in 0..steps {
+                let phi = 2.0 * std::f32::consts::PI * (j as f32 + 0.5) / steps as f32;
+                total += f(&Vec3::new(r * phi.cos(), r * phi.sin(), z));
+            }
+        }
+        4.0 * std::f32::consts::PI * total / (steps * steps) as f32
+    }
+}
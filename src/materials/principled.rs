@@ -0,0 +1,322 @@
+use std::f32::consts::PI;
+
+use crate::{
+    materials::{
+        microfacet::{fresnel_schlick, Ggx, RoughBoundary},
+        Material, ScatterRecord,
+    },
+    textures::{solid::SolidColor, Texture},
+    utils::{
+        helpers::{random_cosine_direction, random_float},
+        hittable::HitRecord,
+        onb::Onb,
+        ray::Ray,
+        vec::{Color, Vec3},
+    },
+};
+
+/// A material for every kind of surface, in the spirit of Disney's principled
+/// BSDF: a diffuse base with sheen, a glossy specular layer turning into a metal
+/// with `metallic`, a clear coat on top, and rough glass with `transmission`.
+///
+/// Parameters go from 0 to 1, apart from the refractive index `ior`, and can
+/// each be read from a texture: the scalar ones from its red channel. Glass made
+/// this way is hollow to the integrators, which only follow nested dielectrics
+/// through `Dielectric`.
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Box<dyn Texture + Send + Sync>,
+    pub metallic: Box<dyn Texture + Send + Sync>,
+    pub roughness: Box<dyn Texture + Send + Sync>,
+    /// The strength of the specular reflection of non-metals, 0.5 for the 4%
+    /// reflected head-on by most of them
+    pub specular: Box<dyn Texture + Send + Sync>,
+    /// How much the specular reflection of non-metals takes the base color
+    pub specular_tint: Box<dyn Texture + Send + Sync>,
+    /// The strength of the soft glow at grazing angles of cloth
+    pub sheen: Box<dyn Texture + Send + Sync>,
+    /// The strength of a glossy transparent coat, such as car paint's
+    pub clearcoat: Box<dyn Texture + Send + Sync>,
+    pub transmission: Box<dyn Texture + Send + Sync>,
+    pub ior: Box<dyn Texture + Send + Sync>,
+}
+
+impl Principled {
+    /// Create a new rough plastic of the given color, to be changed with the
+    /// `with_*` methods
+    pub fn new(base_color: Color) -> Self {
+        Self {
+            base_color: constant(base_color),
+            metallic: scalar(0.0),
+            roughness: scalar(0.5),
+            specular: scalar(0.5),
+            specular_tint: scalar(0.0),
+            sheen: scalar(0.0),
+            clearcoat: scalar(0.0),
+            transmission: scalar(0.0),
+            ior: scalar(1.5),
+        }
+    }
+    pub fn with_base_color(mut self, base_color: Box<dyn Texture + Send + Sync>) -> Self {
+        self.base_color = base_color;
+        self
+    }
+    pub fn with_metallic(mut self, metallic: Box<dyn Texture + Send + Sync>) -> Self {
+        self.metallic = metallic;
+        self
+    }
+    pub fn with_roughness(mut self, roughness: Box<dyn Texture + Send + Sync>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+    pub fn with_specular(mut self, specular: Box<dyn Texture + Send + Sync>) -> Self {
+        self.specular = specular;
+        self
+    }
+    pub fn with_specular_tint(mut self, specular_tint: Box<dyn Texture + Send + Sync>) -> Self {
+        self.specular_tint = specular_tint;
+        self
+    }
+    pub fn with_sheen(mut self, sheen: Box<dyn Texture + Send + Sync>) -> Self {
+        self.sheen = sheen;
+        self
+    }
+    pub fn with_clearcoat(mut self, clearcoat: Box<dyn Texture + Send + Sync>) -> Self {
+        self.clearcoat = clearcoat;
+        self
+    }
+    pub fn with_transmission(mut self, transmission: Box<dyn Texture + Send + Sync>) -> Self {
+        self.transmission = transmission;
+        self
+    }
+    pub fn with_ior(mut self, ior: Box<dyn Texture + Send + Sync>) -> Self {
+        self.ior = ior;
+        self
+    }
+
+    // The parameters at the hit point, and how likely each lobe is to be
+    // sampled for light leaving toward `v`
+    fn lobes(&self, rec: &HitRecord, v: &Vec3) -> Lobes {
+        let value = |texture: &dyn Texture| texture.value_at(rec);
+        let read = |texture: &dyn Texture| value(texture).x.clamp(0.0, 1.0);
+        let base_color = value(self.base_color.as_ref());
+        let metallic = read(self.metallic.as_ref());
+        let roughness = read(self.roughness.as_ref());
+        let transmission = read(self.transmission.as_ref());
+        let ior = value(self.ior.as_ref()).x.max(1.0);
+
+        let tint = tint(base_color);
+        // Non-metals reflect 8% of the light head-on at most, metals their color
+        let dielectric_f0 = lerp(
+            Color::new(1.0, 1.0, 1.0),
+            tint,
+            read(self.specular_tint.as_ref()),
+        ) * (0.08 * read(self.specular.as_ref()));
+        let f0 = lerp(dielectric_f0, base_color, metallic);
+
+        let diffuse = (1.0 - metallic) * (1.0 - transmission);
+        let glass = (1.0 - metallic) * transmission;
+        let specular = 1.0 - glass;
+        let clearcoat = 0.25 * read(self.clearcoat.as_ref());
+        let ggx = Ggx::from_roughness(roughness);
+
+        // Each lobe is sampled about as often as it reflects light toward `v`,
+        // which is below the surface when a path refracted through it is
+        // evaluated backwards
+        let cosine = v.z.abs();
+        let weights = [
+            diffuse * luminance(base_color),
+            specular * luminance(fresnel_schlick(f0, cosine)),
+            glass,
+            clearcoat * fresnel_schlick(Color::new(0.04, 0.04, 0.04), cosine).x,
+        ];
+        let total: f32 = weights.iter().sum();
+        let probabilities = if total > 0.0 {
+            weights.map(|weight| weight / total)
+        } else {
+            [1.0, 0.0, 0.0, 0.0]
+        };
+
+        Lobes {
+            base_color,
+            roughness,
+            f0,
+            sheen: lerp(Color::new(1.0, 1.0, 1.0), tint, 0.5) * read(self.sheen.as_ref()),
+            diffuse,
+            specular,
+            glass,
+            clearcoat,
+            ggx,
+            boundary: RoughBoundary {
+                ggx,
+                eta: if rec.front_face { ior } else { 1.0 / ior },
+            },
+            clearcoat_ggx: Ggx::new(0.05, 0.05),
+            probabilities,
+        }
+    }
+
+    // The BSDF value times the cosine of `l`, seen from `v`, in the local frame
+    fn eval_local(&self, lobes: &Lobes, v: &Vec3, l: &Vec3) -> Color {
+        let mut f = Color::zero();
+        if lobes.glass > 0.0 {
+            let (reflected, transmitted) = lobes.boundary.eval(v, l);
+            f += (Color::new(reflected, reflected, reflected) + lobes.base_color * transmitted)
+                * lobes.glass;
+        }
+        // Only glass lets light through, the other lobes are opaque
+        if v.z <= 0.0 || l.z <= 0.0 {
+            return f;
+        }
+
+        let h = (*v + *l).unit_vector();
+        let lh = l.dot(&h);
+        if lobes.diffuse > 0.0 {
+            // Burley's diffuse, darker at grazing angles on smooth surfaces and
+            // brighter on rough ones
+            let fd90 = 0.5 + 2.0 * lobes.roughness * lh * lh;
+            let fl = schlick_weight(l.z);
+            let fv = schlick_weight(v.z);
+            let retro = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+            f += (lobes.base_color * (retro / PI) + lobes.sheen * schlick_weight(lh))
+                * (lobes.diffuse * l.z);
+        }
+        // Specular lobes: D G F / (4 cos v cos l), times the cosine of l
+        let microfacet = lobes.ggx.d(&h) * lobes.ggx.g(v, l) / (4.0 * v.z);
+        f += fresnel_schlick(lobes.f0, lh) * (lobes.specular * microfacet);
+        if lobes.clearcoat > 0.0 {
+            let coat = lobes.clearcoat_ggx.d(&h) * lobes.clearcoat_ggx.g(v, l) / (4.0 * v.z);
+            f += fresnel_schlick(Color::new(0.04, 0.04, 0.04), lh) * (lobes.clearcoat * coat);
+        }
+        f
+    }
+
+    // The probability density of `sample` picking `l`, seen from `v`, in the local frame
+    fn pdf_local(&self, lobes: &Lobes, v: &Vec3, l: &Vec3) -> f32 {
+        let [diffuse, specular, glass, clearcoat] = lobes.probabilities;
+        let mut pdf = 0.0;
+        if glass > 0.0 {
+            pdf += glass * lobes.boundary.pdf(v, l);
+        }
+        if v.z <= 0.0 || l.z <= 0.0 {
+            return pdf;
+        }
+        let h = (*v + *l).unit_vector();
+        let vh = v.dot(&h);
+        pdf += diffuse * l.z / PI;
+        if vh > 0.0 {
+            let reflected = |ggx: &Ggx| ggx.pdf_visible(v, &h) / (4.0 * vh);
+            pdf += specular * reflected(&lobes.ggx) + clearcoat * reflected(&lobes.clearcoat_ggx);
+        }
+        pdf
+    }
+}
+
+impl Material for Principled {
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let frame = Onb::from_w(&rec.normal);
+        let v = frame.to_local(&-r_in.direction.unit_vector());
+        if v.z <= 0.0 {
+            return None;
+        }
+        let lobes = self.lobes(rec, &v);
+        let [diffuse, specular, glass, _] = lobes.probabilities;
+        let reflect = |m: Vec3| 2.0 * v.dot(&m) * m - v;
+
+        let choice = random_float();
+        let l = if choice < diffuse {
+            random_cosine_direction()
+        } else if choice < diffuse + specular {
+            reflect(lobes.ggx.sample_visible(&v, random_float(), random_float()))
+        } else if choice < diffuse + specular + glass {
+            lobes
+                .boundary
+                .sample(&v, random_float(), random_float(), random_float())?
+        } else {
+            reflect(
+                lobes
+                    .clearcoat_ggx
+                    .sample_visible(&v, random_float(), random_float()),
+            )
+        };
+
+        // Every lobe could have picked the direction, so all of them count
+        let pdf = self.pdf_local(&lobes, &v, &l);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            direction: frame.to_world(&l).unit_vector(),
+            throughput: self.eval_local(&lobes, &v, &l) / pdf,
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> Color {
+        let frame = Onb::from_w(&rec.normal);
+        let v = frame.to_local(&-wi.unit_vector());
+        let l = frame.to_local(&wo.unit_vector());
+        self.eval_local(&self.lobes(rec, &v), &v, &l)
+    }
+
+    fn pdf(&self, wi: &Vec3, wo: &Vec3, rec: &HitRecord) -> f32 {
+        let frame = Onb::from_w(&rec.normal);
+        let v = frame.to_local(&-wi.unit_vector());
+        let l = frame.to_local(&wo.unit_vector());
+        self.pdf_local(&self.lobes(rec, &v), &v, &l)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base_color.value_at(rec)
+    }
+}
+
+// The parameters of a principled material at a point, weighting its lobes
+struct Lobes {
+    base_color: Color,
+    roughness: f32,
+    /// The share of light reflected head-on by the specular layer
+    f0: Color,
+    sheen: Color,
+    diffuse: f32,
+    specular: f32,
+    glass: f32,
+    clearcoat: f32,
+    ggx: Ggx,
+    /// The glass lobe
+    boundary: RoughBoundary,
+    clearcoat_ggx: Ggx,
+    /// The chance of sampling the diffuse, specular, glass and clear coat lobes
+    probabilities: [f32; 4],
+}
+
+fn constant(color: Color) -> Box<dyn Texture + Send + Sync> {
+    Box::new(SolidColor::new(color))
+}
+
+fn scalar(value: f32) -> Box<dyn Texture + Send + Sync> {
+    constant(Color::new(value, value, value))
+}
+
+fn lerp(a: Color, b: Color, t: f32) -> Color {
+    a * (1.0 - t) + b * t
+}
+
+fn luminance(color: Color) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// The hue of a color without its brightness
+fn tint(color: Color) -> Color {
+    let luminance = luminance(color);
+    if luminance > 0.0 {
+        color / luminance
+    } else {
+        Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+fn schlick_weight(cosine: f32) -> f32 {
+    (1.0 - cosine.clamp(0.0, 1.0)).powi(5)
+}
@@ -0,0 +1,795 @@
+//! Serializable description of a scene, as read from TOML, JSON or RON files.
+//!
+//! Materials and textures are declared once in their own tables and referenced
+//! by id from shapes (and from other textures, e.g. the two halves of a checker).
+//! Shapes, materials and textures are tables whose first field is `type`, naming
+//! the Rust type to build, followed by that type's fields.
+
+use std::{collections::HashMap, fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, value::MapAccessDeserializer, DeserializeSeed, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::utils::{
+    camera::{MAX_TIME, MIN_TIME},
+    vec::{Color, Point3, Vec3},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub textures: HashMap<String, TextureDescription>,
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub shapes: Vec<ShapeDescription>,
+    /// Lights without a shape, emissive shapes are lights already.
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    /// What rays leaving the scene see, the sky of the built-in scene when omitted.
+    pub background: Option<BackgroundDescription>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    #[serde(default = "default_up")]
+    pub up: Vec3,
+    /// Vertical field of view in degrees, falls back to `--fov` when omitted.
+    pub fov: Option<f32>,
+}
+
+#[derive(Debug)]
+pub enum TextureDescription {
+    SolidColor(SolidColorDescription),
+    CheckerTexture(CheckerTextureDescription),
+    NoiseTexture(NoiseTextureDescription),
+    ImageTexture(ImageTextureDescription),
+    VertexColors(VertexColorsDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolidColorDescription {
+    pub color: Color,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckerTextureDescription {
+    pub odd: String,
+    pub even: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseTextureDescription {
+    #[serde(default = "default_noise_scale")]
+    pub scale: f32,
+    #[serde(default = "default_noise_octaves")]
+    pub octaves: usize,
+    #[serde(default = "default_noise_frequency")]
+    pub frequency: f32,
+    #[serde(default = "default_noise_persistence")]
+    pub persistence: f32,
+    #[serde(default = "default_noise_lacunarity")]
+    pub lacunarity: f32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageTextureDescription {
+    pub path: String,
+}
+
+/// The vertex colors of the mesh hit, or `fallback` on shapes without any.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VertexColorsDescription {
+    #[serde(default = "default_albedo")]
+    pub fallback: Color,
+}
+
+#[derive(Debug)]
+pub enum MaterialDescription {
+    Lambertian(LambertianDescription),
+    Metal(MetalDescription),
+    Dielectric(DielectricDescription),
+    DiffuseLight(DiffuseLightDescription),
+    Isotropic(IsotropicDescription),
+    HenyeyGreenstein(HenyeyGreensteinDescription),
+    Principled(PrincipledDescription),
+    Conductor(ConductorDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LambertianDescription {
+    #[serde(default = "default_albedo")]
+    pub albedo: Color,
+    pub texture: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetalDescription {
+    #[serde(default = "default_albedo")]
+    pub albedo: Color,
+    #[serde(default)]
+    pub fuzz: f32,
+    pub texture: Option<String>,
+}
+
+/// Glass, water and the like, of refractive index `n`, turning white light
+/// `color` after it travels `distance` inside. Where dielectrics overlap, the
+/// inside of the one of highest `priority` wins. In spectral mode, the index
+/// follows `dispersion` instead, which also gives `n` when it is left out.
+/// Frosted ones have a `roughness`, a number or the id of a texture.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DielectricDescription {
+    pub n: Option<f32>,
+    pub dispersion: Option<DispersionDescription>,
+    #[serde(default = "default_tint")]
+    pub color: Color,
+    #[serde(default = "default_distance")]
+    pub distance: f32,
+    #[serde(default)]
+    pub priority: i32,
+    pub roughness: Option<ParameterDescription>,
+}
+
+#[derive(Debug)]
+pub enum DispersionDescription {
+    Cauchy(CauchyDescription),
+    Sellmeier(SellmeierDescription),
+}
+
+/// `n = a + b / λ²`, with the wavelength λ in micrometers.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CauchyDescription {
+    pub a: f32,
+    pub b: f32,
+}
+
+/// `n² = 1 + Σ bᵢλ² / (λ² - cᵢ)`, with the wavelength λ in micrometers, as
+/// listed in optical glass catalogs.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SellmeierDescription {
+    pub b: [f32; 3],
+    pub c: [f32; 3],
+}
+
+/// A light source, brighter as `emit` goes above 1.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiffuseLightDescription {
+    #[serde(default = "default_emit")]
+    pub emit: Color,
+    pub texture: Option<String>,
+}
+
+/// The particles of a `ConstantMedium`, scattering light in every direction.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IsotropicDescription {
+    #[serde(default = "default_albedo")]
+    pub albedo: Color,
+    pub texture: Option<String>,
+}
+
+/// The particles of a medium, scattering light forward when `g` is positive and
+/// backward when it is negative, between -1 and 1.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HenyeyGreensteinDescription {
+    #[serde(default = "default_albedo")]
+    pub albedo: Color,
+    #[serde(default)]
+    pub g: f32,
+    pub texture: Option<String>,
+}
+
+/// A material for most surfaces, from rough plastic to metal and glass, whose
+/// color comes from `base_color` or `texture`. Every other parameter is either a
+/// number or the id of a texture, read from its red channel.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrincipledDescription {
+    #[serde(default = "default_albedo")]
+    pub base_color: Color,
+    pub texture: Option<String>,
+    #[serde(default = "default_metallic")]
+    pub metallic: ParameterDescription,
+    #[serde(default = "default_roughness")]
+    pub roughness: ParameterDescription,
+    #[serde(default = "default_specular")]
+    pub specular: ParameterDescription,
+    #[serde(default = "default_specular_tint")]
+    pub specular_tint: ParameterDescription,
+    #[serde(default = "default_sheen")]
+    pub sheen: ParameterDescription,
+    #[serde(default = "default_clearcoat")]
+    pub clearcoat: ParameterDescription,
+    #[serde(default = "default_transmission")]
+    pub transmission: ParameterDescription,
+    #[serde(default = "default_ior")]
+    pub ior: ParameterDescription,
+}
+
+/// A rough metal, whose refractive index is given by `eta` and `k` or taken
+/// from a `metal` preset. Metals with a different `roughness_x` along
+/// `direction` and `roughness_y` across it look brushed.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConductorDescription {
+    pub metal: Option<PresetDescription>,
+    pub eta: Option<Color>,
+    pub k: Option<Color>,
+    #[serde(default = "default_conductor_roughness")]
+    pub roughness: f32,
+    pub roughness_x: Option<f32>,
+    pub roughness_y: Option<f32>,
+    #[serde(default = "default_direction")]
+    pub direction: Vec3,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresetDescription {
+    Gold,
+    Silver,
+    Copper,
+    Aluminum,
+    Chrome,
+}
+
+/// A number, or the id of a texture giving it at each point.
+#[derive(Debug, Clone)]
+pub enum ParameterDescription {
+    Value(f32),
+    Texture(String),
+}
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum LightDescription {
+    PointLight(PointLightDescription),
+    SpotLight(SpotLightDescription),
+    DirectionalLight(DirectionalLightDescription),
+}
+
+/// A light at a single point, whose `intensity` is the irradiance it gives at a
+/// distance of 1.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PointLightDescription {
+    pub position: Point3,
+    #[serde(default = "default_emit")]
+    pub intensity: Color,
+}
+
+/// A point light shining toward `direction`, fully within `inner_angle` of it
+/// and fading out up to `outer_angle` (in degrees).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpotLightDescription {
+    pub position: Point3,
+    pub direction: Vec3,
+    #[serde(default = "default_emit")]
+    pub intensity: Color,
+    #[serde(default = "default_inner_angle")]
+    pub inner_angle: f32,
+    #[serde(default = "default_outer_angle")]
+    pub outer_angle: f32,
+}
+
+/// A light infinitely far away going toward `direction`, like the sun, with an
+/// optional angular diameter (in degrees) for soft shadows.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectionalLightDescription {
+    pub direction: Vec3,
+    #[serde(default = "default_emit")]
+    pub irradiance: Color,
+    #[serde(default)]
+    pub angle: f32,
+}
+
+#[derive(Debug)]
+pub enum BackgroundDescription {
+    SolidBackground(SolidBackgroundDescription),
+    GradientBackground(GradientBackgroundDescription),
+    EnvironmentMap(EnvironmentMapDescription),
+    SkyBackground(SkyBackgroundDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolidBackgroundDescription {
+    #[serde(default)]
+    pub color: Color,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GradientBackgroundDescription {
+    #[serde(default = "default_gradient_bottom")]
+    pub bottom: Color,
+    #[serde(default = "default_gradient_top")]
+    pub top: Color,
+    #[serde(default = "default_up")]
+    pub up: Vec3,
+}
+
+/// An equirectangular .hdr or .exr image, turned by `rotation` degrees around
+/// the Y axis and scaled by `intensity`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentMapDescription {
+    pub path: String,
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+}
+
+/// A clear daylight sky with the sun `elevation` degrees above the horizon and
+/// `azimuth` degrees clockwise from the north (-Z), or where it is seen from
+/// `latitude` and `longitude` at an ISO 8601 `time`. Hazier as `turbidity`
+/// goes from 2 to 10.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyBackgroundDescription {
+    pub elevation: Option<f32>,
+    #[serde(default)]
+    pub azimuth: f32,
+    pub latitude: Option<f32>,
+    pub longitude: Option<f32>,
+    pub time: Option<String>,
+    #[serde(default = "default_turbidity")]
+    pub turbidity: f32,
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+    #[serde(default = "default_sun")]
+    pub sun: bool,
+    #[serde(default = "default_ground")]
+    pub ground: Color,
+}
+
+#[derive(Debug)]
+pub enum ShapeDescription {
+    Sphere(SphereDescription),
+    MovingSphere(MovingSphereDescription),
+    Plane(PlaneDescription),
+    Triangle(TriangleDescription),
+    Mesh(MeshDescription),
+    Transform(TransformDescription),
+    AnimatedTransform(AnimatedTransformDescription),
+    ConstantMedium(ConstantMediumDescription),
+    HeterogeneousMedium(HeterogeneousMediumDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SphereDescription {
+    pub center: Point3,
+    pub radius: f32,
+    pub material: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MovingSphereDescription {
+    pub center0: Point3,
+    pub center1: Point3,
+    #[serde(default = "default_time0")]
+    pub time0: f32,
+    #[serde(default = "default_time1")]
+    pub time1: f32,
+    pub radius: f32,
+    pub material: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlaneDescription {
+    pub center: Point3,
+    pub normal: Vec3,
+    pub material: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriangleDescription {
+    pub a: Point3,
+    pub b: Point3,
+    pub c: Point3,
+    pub material: Option<String>,
+}
+
+/// A mesh file, with its own materials unless `material` overrides them.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MeshDescription {
+    pub path: String,
+    pub material: Option<String>,
+}
+
+/// Another shape scaled, then rotated around the X, Y and Z axes (in degrees),
+/// then translated. Meshes are only loaded once, however many times they are
+/// transformed.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransformDescription {
+    pub shape: Box<ShapeDescription>,
+    #[serde(default)]
+    pub translate: Vec3,
+    #[serde(default)]
+    pub rotate: Vec3,
+    #[serde(default = "default_scale")]
+    pub scale: Vec3,
+}
+
+/// Another shape moving through keyframes while the shutter is open, which
+/// blurs it. Each keyframe places the shape like a `Transform`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnimatedTransformDescription {
+    pub shape: Box<ShapeDescription>,
+    pub keys: Vec<KeyframeDescription>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyframeDescription {
+    pub time: f32,
+    #[serde(default)]
+    pub translate: Vec3,
+    #[serde(default)]
+    pub rotate: Vec3,
+    #[serde(default = "default_scale")]
+    pub scale: Vec3,
+}
+
+/// Fog or smoke filling a closed `boundary` shape, scattering rays `density`
+/// times per unit of distance on average. Its `material` is usually `Isotropic`,
+/// a white one when omitted.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConstantMediumDescription {
+    pub boundary: Box<ShapeDescription>,
+    pub density: f32,
+    pub material: Option<String>,
+}
+
+/// Clouds or smoke filling a closed `boundary` shape, with a `density` varying
+/// from point to point. Its `material` is like a `ConstantMedium`'s.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeterogeneousMediumDescription {
+    pub boundary: Box<ShapeDescription>,
+    pub density: DensityDescription,
+    pub material: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum DensityDescription {
+    NoiseDensity(NoiseDensityDescription),
+    GridDensity(GridDensityDescription),
+}
+
+/// Perlin noise, up to `scale` where it is highest and empty where it is below
+/// `coverage` (from 0 to 1), like a `NoiseTexture`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseDensityDescription {
+    #[serde(default = "default_noise_scale")]
+    pub scale: f32,
+    #[serde(default = "default_coverage")]
+    pub coverage: f32,
+    #[serde(default = "default_noise_octaves")]
+    pub octaves: usize,
+    #[serde(default = "default_noise_frequency")]
+    pub frequency: f32,
+    #[serde(default = "default_noise_persistence")]
+    pub persistence: f32,
+    #[serde(default = "default_noise_lacunarity")]
+    pub lacunarity: f32,
+}
+
+/// Voxels from an NRRD or raw file, stretched over the bounding box of the
+/// medium's boundary and multiplied by `scale`. Raw files need a `resolution`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GridDensityDescription {
+    pub path: String,
+    #[serde(default = "default_noise_scale")]
+    pub scale: f32,
+    pub resolution: Option<[usize; 3]>,
+}
+
+/// Declares a `type`-tagged enum whose variants each wrap a description struct.
+///
+/// Unlike `#[serde(tag = "type")]`, the fields are read straight from the
+/// underlying format instead of being buffered first, so errors keep the exact
+/// line and field they come from. This requires `type` to be the first field.
+macro_rules! tagged {
+    ($name:ident { $($variant:ident),* }) => {
+        impl Tagged for $name {
+            const VARIANTS: &'static [&'static str] = &[$(stringify!($variant)),*];
+
+            fn from_fields<'de, A: MapAccess<'de>>(tag: &str, fields: A) -> Result<Self, A::Error> {
+                let fields = MapAccessDeserializer::new(fields);
+                match tag {
+                    $(stringify!($variant) => Deserialize::deserialize(fields).map($name::$variant),)*
+                    _ => Err(de::Error::unknown_variant(tag, Self::VARIANTS)),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(TaggedVisitor(PhantomData))
+            }
+        }
+    };
+}
+
+tagged!(TextureDescription {
+    SolidColor,
+    CheckerTexture,
+    NoiseTexture,
+    ImageTexture,
+    VertexColors
+});
+tagged!(MaterialDescription {
+    Lambertian,
+    Metal,
+    Dielectric,
+    DiffuseLight,
+    Isotropic,
+    HenyeyGreenstein,
+    Principled,
+    Conductor
+});
+tagged!(DispersionDescription { Cauchy, Sellmeier });
+tagged!(LightDescription {
+    PointLight,
+    SpotLight,
+    DirectionalLight
+});
+tagged!(BackgroundDescription {
+    SolidBackground,
+    GradientBackground,
+    EnvironmentMap,
+    SkyBackground
+});
+tagged!(ShapeDescription {
+    Sphere,
+    MovingSphere,
+    Plane,
+    Triangle,
+    Mesh,
+    Transform,
+    AnimatedTransform,
+    ConstantMedium,
+    HeterogeneousMedium
+});
+tagged!(DensityDescription {
+    NoiseDensity,
+    GridDensity
+});
+
+impl<'de> Deserialize<'de> for ParameterDescription {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ParameterVisitor)
+    }
+}
+
+struct ParameterVisitor;
+
+impl<'de> Visitor<'de> for ParameterVisitor {
+    type Value = ParameterDescription;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number or the id of a texture")
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(ParameterDescription::Value(value as f32))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(ParameterDescription::Value(value as f32))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(ParameterDescription::Value(value as f32))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(ParameterDescription::Texture(value.to_string()))
+    }
+}
+
+trait Tagged: Sized {
+    const VARIANTS: &'static [&'static str];
+    fn from_fields<'de, A: MapAccess<'de>>(tag: &str, fields: A) -> Result<Self, A::Error>;
+}
+
+struct TaggedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Tagged> Visitor<'de> for TaggedVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a table with a `type` field")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<T, A::Error> {
+        match map.next_key::<String>()? {
+            Some(key) if key == "type" => {
+                let tag = map.next_value_seed(TagSeed(T::VARIANTS))?;
+                T::from_fields(&tag, map)
+            }
+            Some(key) => Err(de::Error::custom(format!(
+                "expected `type` as the first field, found `{}`",
+                key
+            ))),
+            None => Err(de::Error::missing_field("type")),
+        }
+    }
+}
+
+// Checks the tag while it is being read, so unknown types point at the `type` field
+struct TagSeed(&'static [&'static str]);
+
+impl<'de> DeserializeSeed<'de> for TagSeed {
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        let tag = String::deserialize(deserializer)?;
+        if self.0.contains(&tag.as_str()) {
+            Ok(tag)
+        } else {
+            Err(de::Error::unknown_variant(&tag, self.0))
+        }
+    }
+}
+
+fn default_up() -> Vec3 {
+    Vec3::new(0.0, 1.0, 0.0)
+}
+
+fn default_scale() -> Vec3 {
+    Vec3::new(1.0, 1.0, 1.0)
+}
+
+fn default_albedo() -> Color {
+    Color::new(0.5, 0.5, 0.5)
+}
+
+fn default_emit() -> Color {
+    Color::new(1.0, 1.0, 1.0)
+}
+
+fn default_gradient_bottom() -> Color {
+    Color::new(1.0, 1.0, 1.0)
+}
+
+fn default_gradient_top() -> Color {
+    Color::new(0.5, 0.7, 1.0)
+}
+
+fn default_inner_angle() -> f32 {
+    20.0
+}
+
+fn default_outer_angle() -> f32 {
+    30.0
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn default_sun() -> bool {
+    true
+}
+
+fn default_ground() -> Color {
+    Color::new(0.3, 0.3, 0.3)
+}
+
+fn default_time0() -> f32 {
+    MIN_TIME
+}
+
+fn default_time1() -> f32 {
+    MAX_TIME
+}
+
+fn default_noise_scale() -> f32 {
+    1.0
+}
+
+fn default_noise_octaves() -> usize {
+    4
+}
+
+fn default_noise_frequency() -> f32 {
+    1.0
+}
+
+fn default_noise_persistence() -> f32 {
+    0.5
+}
+
+fn default_noise_lacunarity() -> f32 {
+    2.0
+}
+
+fn default_tint() -> Color {
+    Color::new(1.0, 1.0, 1.0)
+}
+
+fn default_distance() -> f32 {
+    1.0
+}
+
+fn default_coverage() -> f32 {
+    0.5
+}
+
+fn default_conductor_roughness() -> f32 {
+    0.3
+}
+
+fn default_direction() -> Vec3 {
+    Vec3::new(1.0, 0.0, 0.0)
+}
+
+fn default_metallic() -> ParameterDescription {
+    ParameterDescription::Value(0.0)
+}
+
+fn default_roughness() -> ParameterDescription {
+    ParameterDescription::Value(0.5)
+}
+
+fn default_specular() -> ParameterDescription {
+    ParameterDescription::Value(0.5)
+}
+
+fn default_specular_tint() -> ParameterDescription {
+    ParameterDescription::Value(0.0)
+}
+
+fn default_sheen() -> ParameterDescription {
+    ParameterDescription::Value(0.0)
+}
+
+fn default_clearcoat() -> ParameterDescription {
+    ParameterDescription::Value(0.0)
+}
+
+fn default_transmission() -> ParameterDescription {
+    ParameterDescription::Value(0.0)
+}
+
+fn default_ior() -> ParameterDescription {
+    ParameterDescription::Value(1.5)
+}
@@ -0,0 +1,1124 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    backgrounds::{
+        environment::EnvironmentMap,
+        gradient::GradientBackground,
+        sky::{sun_position, SkyBackground},
+        solid::SolidBackground,
+        Background,
+    },
+    densities::{grid::GridDensity, noise::NoiseDensity, Density},
+    integrators::power_heuristic,
+    lights::{
+        area::AreaLight, directional::DirectionalLight, point::PointLight, spot::SpotLight, Light,
+        LightList, LightSample,
+    },
+    loaders::{gltf::load_gltf, load_mesh, MeshError},
+    materials::{
+        conductor::{Conductor, Preset},
+        dielectric::{Dielectric, Dispersion},
+        diffuse_light::DiffuseLight,
+        henyey_greenstein::HenyeyGreenstein,
+        isotropic::Isotropic,
+        lambertian::Lambertian,
+        metal::Metal,
+        principled::Principled,
+        Material,
+    },
+    scene::description::{
+        AnimatedTransformDescription, BackgroundDescription, CameraDescription, CauchyDescription,
+        CheckerTextureDescription, ConductorDescription, ConstantMediumDescription,
+        DensityDescription, DielectricDescription, DiffuseLightDescription,
+        DirectionalLightDescription, DispersionDescription, EnvironmentMapDescription,
+        GradientBackgroundDescription, GridDensityDescription, HenyeyGreensteinDescription,
+        HeterogeneousMediumDescription, ImageTextureDescription, IsotropicDescription,
+        LambertianDescription, LightDescription, MaterialDescription, MeshDescription,
+        MetalDescription, MovingSphereDescription, NoiseDensityDescription,
+        NoiseTextureDescription, ParameterDescription, PlaneDescription, PointLightDescription,
+        PresetDescription, PrincipledDescription, SceneDescription, SellmeierDescription,
+        ShapeDescription, SkyBackgroundDescription, SolidBackgroundDescription,
+        SolidColorDescription, SphereDescription, SpotLightDescription, TextureDescription,
+        TransformDescription, TriangleDescription, VertexColorsDescription,
+    },
+    shapes::{
+        animated::{
+            sphere::MovingSphere,
+            transform::{AnimatedTransform, Keyframe},
+        },
+        bvh::BvhNode,
+        constant_medium::ConstantMedium,
+        heterogeneous_medium::HeterogeneousMedium,
+        list::HittableList,
+        plane::Plane,
+        sphere::Sphere,
+        transform::Transform,
+        triangle::Triangle,
+    },
+    textures::{
+        checker::CheckerTexture, image::ImageTexture, noise::NoiseTexture, solid::SolidColor,
+        vertex_colors::VertexColors, Texture,
+    },
+    utils::{
+        camera::{Camera, MAX_TIME, MIN_TIME},
+        helpers::{random_float, random_spheres},
+        hittable::Hittable,
+        matrix::Matrix,
+        quaternion::Quaternion,
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+pub mod description;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(String, std::io::Error),
+    UnsupportedFormat(String),
+    Parse {
+        file: String,
+        line: usize,
+        column: usize,
+        field: String,
+        message: String,
+    },
+    UnknownTexture {
+        field: String,
+        id: String,
+    },
+    UnknownMaterial {
+        field: String,
+        id: String,
+    },
+    TextureCycle {
+        field: String,
+        id: String,
+    },
+    Image {
+        field: String,
+        path: String,
+        message: String,
+    },
+    Mesh {
+        field: String,
+        error: MeshError,
+    },
+    Volume {
+        field: String,
+        path: String,
+        message: String,
+    },
+    Import(MeshError),
+    SingularTransform(String),
+    NoKeyframes(String),
+    NoIndex(String),
+    Sky {
+        field: String,
+        message: String,
+    },
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            SceneError::Io(file, error) => write!(f, "unable to read {}: {}", file, error),
+            SceneError::UnsupportedFormat(file) => write!(
+                f,
+                "unsupported scene format for {} (expected .toml, .json, .ron, .gltf or .glb)",
+                file
+            ),
+            SceneError::Parse {
+                file,
+                line,
+                column,
+                field,
+                message,
+            } => write!(
+                f,
+                "{}:{}:{}: invalid `{}`: {}",
+                file, line, column, field, message
+            ),
+            SceneError::UnknownTexture { field, id } => {
+                write!(f, "`{}` references unknown texture `{}`", field, id)
+            }
+            SceneError::UnknownMaterial { field, id } => {
+                write!(f, "`{}` references unknown material `{}`", field, id)
+            }
+            SceneError::TextureCycle { field, id } => {
+                write!(f, "`{}` makes texture `{}` reference itself", field, id)
+            }
+            SceneError::Image {
+                field,
+                path,
+                message,
+            } => write!(f, "`{}`: unable to load image {}: {}", field, path, message),
+            SceneError::Mesh { field, error } => write!(f, "`{}`: {}", field, error),
+            SceneError::Volume {
+                field,
+                path,
+                message,
+            } => write!(
+                f,
+                "`{}`: unable to load volume {}: {}",
+                field, path, message
+            ),
+            SceneError::Import(error) => write!(f, "{}", error),
+            SceneError::SingularTransform(field) => {
+                write!(f, "`{}` flattens the shape, its scale cannot be 0", field)
+            }
+            SceneError::NoKeyframes(field) => write!(f, "`{}` needs at least one keyframe", field),
+            SceneError::NoIndex(field) => {
+                write!(
+                    f,
+                    "`{}` needs a `metal` preset, or both `eta` and `k`",
+                    field
+                )
+            }
+            SceneError::Sky { field, message } => write!(f, "`{}`: {}", field, message),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+/// A renderable scene: the camera looking at it, everything it contains and
+/// what lies beyond.
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub camera: Camera,
+    pub world: HittableList,
+    /// The emissive shapes of `world`, also sampled directly
+    pub lights: LightList,
+    /// Sampled along with the lights, unless it gives off no light
+    pub background: Box<dyn Background>,
+}
+
+impl Scene {
+    /// Loads a scene from a TOML, JSON or RON file, picked by its extension.
+    /// glTF files (.gltf or .glb) are also accepted and rendered through their own camera.
+    /// # Arguments
+    /// * `path` - The scene file, relative paths inside it are resolved against its directory
+    /// * `aspect_ratio` - The aspect ratio of the rendered image
+    /// * `fov` - The vertical field of view used when the scene does not set one
+    pub fn from_file(path: &str, aspect_ratio: f32, fov: f32) -> Result<Self, SceneError> {
+        let is_gltf = Path::new(path)
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("gltf") || e.eq_ignore_ascii_case("glb"));
+        if is_gltf {
+            return Self::from_gltf(path, aspect_ratio, fov);
+        }
+        let source = fs::read_to_string(path).map_err(|e| SceneError::Io(path.to_string(), e))?;
+        let description = parse_description(Path::new(path), &source)?;
+        let base_dir = Path::new(path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        SceneBuilder::new(&description, base_dir).build(aspect_ratio, fov)
+    }
+
+    fn from_gltf(path: &str, aspect_ratio: f32, fov: f32) -> Result<Self, SceneError> {
+        let scene = load_gltf(path, None).map_err(SceneError::Import)?;
+        let mut lights = LightList::default();
+        for light in scene.lights {
+            lights.add(Box::new(AreaLight::new(light)));
+        }
+        let camera = scene.camera.unwrap_or_else(|| {
+            // Without a camera in the file, frame the whole mesh from the front
+            let bounds = scene.mesh.bounding_box.clone();
+            let center = (bounds.min + bounds.max) / 2.0;
+            let radius = (bounds.max - bounds.min).length() / 2.0;
+            let distance = radius / (fov.to_radians() / 2.0).sin();
+            CameraDescription {
+                look_from: center + Vec3::new(0.0, 0.0, distance),
+                look_at: center,
+                up: Vec3::new(0.0, 1.0, 0.0),
+                fov: None,
+            }
+        });
+        Ok(Self {
+            camera: Camera::new(
+                camera.look_from,
+                camera.look_at,
+                camera.up,
+                camera.fov.unwrap_or(fov),
+                aspect_ratio,
+            ),
+            world: HittableList::new(vec![Box::new(scene.mesh)]),
+            lights,
+            background: Box::new(GradientBackground::sky()),
+        })
+    }
+
+    /// A random direction toward one of the lights or the background, picked
+    /// uniformly, with the probability of picking it folded into the pdf
+    /// # Arguments
+    /// * `origin` - The point to light
+    /// * `time` - The time of the shadow ray
+    pub fn sample_light(&self, origin: &Point3, time: f32) -> Option<LightSample> {
+        let count = self.light_count();
+        if count == 0 {
+            return None;
+        }
+        let index = ((random_float() * count as f32) as usize).min(count - 1);
+        let mut sample = self.sample_light_at(index, origin, time)?;
+        sample.pdf /= count as f32;
+        (sample.pdf > 0.0).then_some(sample)
+    }
+
+    /// A direction toward the light of the given index, the background coming
+    /// after the other lights, with the pdf of that light alone
+    pub fn sample_light_at(&self, index: usize, origin: &Point3, time: f32) -> Option<LightSample> {
+        match self.lights.lights.get(index) {
+            Some(light) => light.sample(origin, time),
+            None => {
+                let (direction, pdf) = self.background.sample();
+                Some(LightSample {
+                    direction,
+                    distance: f32::INFINITY,
+                    radiance: self.background.color(&direction),
+                    pdf,
+                    delta: false,
+                })
+            }
+        }
+    }
+
+    /// The probability density of `sample_light` picking the direction of `ray`,
+    /// toward the surface it hits at `distance`
+    pub fn light_pdf(&self, ray: &Ray, distance: f32) -> f32 {
+        let count = self.light_count();
+        if count == 0 {
+            return 0.0;
+        }
+        self.lights
+            .lights
+            .iter()
+            .map(|light| light.pdf(ray, distance))
+            .sum::<f32>()
+            / count as f32
+    }
+
+    /// The light reaching a ray which leaves the scene: the background, and lights
+    /// at infinity such as the disk of the sun
+    /// # Arguments
+    /// * `ray` - The ray leaving the scene
+    /// * `scatter_pdf` - The pdf of the material which scattered the ray, whose
+    ///   light is then weighted by MIS against sampling each source directly
+    pub fn escaped(&self, ray: &Ray, scatter_pdf: Option<f32>) -> Color {
+        let count = self.light_count() as f32;
+        let weight = |light_pdf: f32| match scatter_pdf {
+            Some(scatter_pdf) => power_heuristic(scatter_pdf, light_pdf / count),
+            None => 1.0,
+        };
+        let mut color = self.background.color(&ray.direction);
+        if self.background.is_light() {
+            color *= weight(self.background.pdf(&ray.direction));
+        }
+        for light in &self.lights.lights {
+            let escaped = light.escaped(ray);
+            if !escaped.near_zero() {
+                color += escaped * weight(light.pdf(ray, f32::INFINITY));
+            }
+        }
+        color
+    }
+
+    /// The probability density of `sample_light` picking the direction of `ray`
+    /// toward a light at infinity, the background included
+    pub fn infinite_light_pdf(&self, ray: &Ray) -> f32 {
+        let count = self.light_count();
+        if count == 0 {
+            return 0.0;
+        }
+        let background = if self.background.is_light() {
+            self.background.pdf(&ray.direction)
+        } else {
+            0.0
+        };
+        self.light_pdf(ray, f32::INFINITY) + background / count as f32
+    }
+
+    /// The number of lights `sample_light` picks from, the background included
+    /// when it gives off light
+    pub fn light_count(&self) -> usize {
+        self.lights.len() + usize::from(self.background.is_light())
+    }
+
+    /// The built-in scene rendered when no scene file is given.
+    pub fn random_spheres(aspect_ratio: f32, fov: f32) -> Self {
+        let camera = Camera::new(
+            Vec3::new(-3.0, 3.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            fov,
+            aspect_ratio,
+        );
+        let world = HittableList::new(vec![Box::new(BvhNode::from_objects(
+            random_spheres(10),
+            MIN_TIME,
+            MAX_TIME,
+        ))]);
+        Self {
+            camera,
+            world,
+            lights: LightList::default(),
+            background: Box::new(GradientBackground::sky()),
+        }
+    }
+}
+
+fn build_light(light: &LightDescription) -> Box<dyn Light> {
+    match light {
+        LightDescription::PointLight(PointLightDescription {
+            position,
+            intensity,
+        }) => Box::new(PointLight::new(*position, *intensity)),
+        LightDescription::SpotLight(SpotLightDescription {
+            position,
+            direction,
+            intensity,
+            inner_angle,
+            outer_angle,
+        }) => Box::new(SpotLight::new(
+            *position,
+            *direction,
+            *intensity,
+            *inner_angle,
+            *outer_angle,
+        )),
+        LightDescription::DirectionalLight(DirectionalLightDescription {
+            direction,
+            irradiance,
+            angle,
+        }) => Box::new(DirectionalLight::new(*direction, *irradiance, *angle)),
+    }
+}
+
+fn parse_description(path: &Path, source: &str) -> Result<SceneDescription, SceneError> {
+    let file = path.display().to_string();
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("toml") => {
+            let toml_error = |field: String, error: toml::de::Error| {
+                let (line, column) = error
+                    .span()
+                    .map(|span| line_column(source, span.start))
+                    .unwrap_or((1, 1));
+                SceneError::Parse {
+                    file: file.clone(),
+                    line,
+                    column,
+                    field,
+                    message: error.message().trim().to_string(),
+                }
+            };
+            let deserializer =
+                toml::Deserializer::parse(source).map_err(|e| toml_error(".".to_string(), e))?;
+            serde_path_to_error::deserialize(deserializer)
+                .map_err(|e| toml_error(e.path().to_string(), e.into_inner()))
+        }
+        Some("json") => {
+            let mut deserializer = serde_json::Deserializer::from_str(source);
+            serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+                let field = e.path().to_string();
+                let error = e.into_inner();
+                // serde_json appends the position to its messages, we report it separately
+                let message = error.to_string();
+                let message = message.split(" at line ").next().unwrap_or_default();
+                SceneError::Parse {
+                    file: file.clone(),
+                    line: error.line(),
+                    column: error.column(),
+                    field,
+                    message: message.to_string(),
+                }
+            })
+        }
+        Some("ron") => {
+            let ron_error = |field: String, error: ron::error::SpannedError| SceneError::Parse {
+                file: file.clone(),
+                line: error.span.start.line,
+                column: error.span.start.col,
+                field,
+                message: error.code.to_string(),
+            };
+            let mut deserializer =
+                ron::Deserializer::from_str(source).map_err(|e| ron_error(".".to_string(), e))?;
+            serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+                let field = e.path().to_string();
+                ron_error(field, deserializer.span_error(e.into_inner()))
+            })
+        }
+        _ => Err(SceneError::UnsupportedFormat(file)),
+    }
+}
+
+// Convert a byte offset into a 1-based (line, column) pair
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// Turns a `SceneDescription` into hittables, resolving texture and material ids.
+/// Each texture and material is built once and cloned into the shapes using it.
+struct SceneBuilder<'a> {
+    description: &'a SceneDescription,
+    base_dir: PathBuf,
+    textures: HashMap<String, Box<dyn Texture + Send + Sync>>,
+    materials: HashMap<String, Box<dyn Material + Send + Sync>>,
+    // Loaded meshes, by path and material id
+    meshes: HashMap<(String, Option<String>), Arc<dyn Hittable>>,
+    // Texture ids currently being built, used to detect reference cycles
+    pending: Vec<String>,
+}
+
+impl<'a> SceneBuilder<'a> {
+    fn new(description: &'a SceneDescription, base_dir: PathBuf) -> Self {
+        Self {
+            description,
+            base_dir,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            meshes: HashMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn build(mut self, aspect_ratio: f32, fov: f32) -> Result<Scene, SceneError> {
+        let camera = &self.description.camera;
+        let camera = Camera::new(
+            camera.look_from,
+            camera.look_at,
+            camera.up,
+            camera.fov.unwrap_or(fov),
+            aspect_ratio,
+        );
+
+        let mut bounded: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut unbounded: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut lights = LightList::default();
+        for (i, shape) in self.description.shapes.iter().enumerate() {
+            let object = self.shape(&format!("shapes[{}]", i), shape)?;
+            if self.is_light(shape) {
+                lights.add(Box::new(AreaLight::new(object.clone())));
+            }
+            if object.bounding_box(MIN_TIME, MAX_TIME).is_some() {
+                bounded.push(object);
+            } else {
+                unbounded.push(object);
+            }
+        }
+
+        for light in &self.description.lights {
+            lights.add(build_light(light));
+        }
+
+        let mut world = HittableList::new(unbounded);
+        if !bounded.is_empty() {
+            world.add(Box::new(BvhNode::from_objects(bounded, MIN_TIME, MAX_TIME)));
+        }
+        let background = self.background()?;
+        Ok(Scene {
+            camera,
+            world,
+            lights,
+            background,
+        })
+    }
+
+    // Whether a shape has an emissive material and can be sampled as a light.
+    // Planes are infinite, and meshes only when their material is overridden.
+    fn is_light(&self, shape: &ShapeDescription) -> bool {
+        let material = match shape {
+            ShapeDescription::Sphere(SphereDescription { material, .. })
+            | ShapeDescription::MovingSphere(MovingSphereDescription { material, .. })
+            | ShapeDescription::Triangle(TriangleDescription { material, .. })
+            | ShapeDescription::Mesh(MeshDescription { material, .. }) => material,
+            ShapeDescription::Transform(TransformDescription { shape, .. })
+            | ShapeDescription::AnimatedTransform(AnimatedTransformDescription { shape, .. }) => {
+                return self.is_light(shape)
+            }
+            ShapeDescription::Plane(_)
+            | ShapeDescription::ConstantMedium(_)
+            | ShapeDescription::HeterogeneousMedium(_) => return false,
+        };
+        matches!(
+            material
+                .as_ref()
+                .and_then(|id| self.description.materials.get(id)),
+            Some(MaterialDescription::DiffuseLight(_))
+        )
+    }
+
+    fn background(&self) -> Result<Box<dyn Background>, SceneError> {
+        Ok(match &self.description.background {
+            None => Box::new(GradientBackground::sky()),
+            Some(BackgroundDescription::SolidBackground(SolidBackgroundDescription { color })) => {
+                Box::new(SolidBackground::new(*color))
+            }
+            Some(BackgroundDescription::GradientBackground(GradientBackgroundDescription {
+                bottom,
+                top,
+                up,
+            })) => Box::new(GradientBackground::new(*bottom, *top, *up)),
+            Some(BackgroundDescription::EnvironmentMap(EnvironmentMapDescription {
+                path,
+                rotation,
+                intensity,
+            })) => {
+                let path = self.base_dir.join(path).display().to_string();
+                let background =
+                    EnvironmentMap::load(&path, *rotation, *intensity).map_err(|e| {
+                        SceneError::Image {
+                            field: "background.path".to_string(),
+                            path: path.clone(),
+                            message: e.to_string(),
+                        }
+                    })?;
+                Box::new(background)
+            }
+            Some(BackgroundDescription::SkyBackground(SkyBackgroundDescription {
+                elevation,
+                azimuth,
+                latitude,
+                longitude,
+                time,
+                turbidity,
+                intensity,
+                sun,
+                ground,
+            })) => {
+                let (elevation, azimuth) =
+                    match (elevation, latitude, longitude, time) {
+                        (Some(elevation), None, None, None) => (*elevation, *azimuth),
+                        (None, Some(latitude), Some(longitude), Some(time)) => {
+                            sun_position(*latitude, *longitude, time).map_err(|message| {
+                                SceneError::Sky {
+                                    field: "background.time".to_string(),
+                                    message,
+                                }
+                            })?
+                        }
+                        _ => return Err(SceneError::Sky {
+                            field: "background".to_string(),
+                            message:
+                                "expected either `elevation` or `latitude`, `longitude` and `time`"
+                                    .to_string(),
+                        }),
+                    };
+                Box::new(SkyBackground::new(
+                    elevation, azimuth, *turbidity, *intensity, *sun, *ground,
+                ))
+            }
+        })
+    }
+
+    fn shape(
+        &mut self,
+        field: &str,
+        shape: &ShapeDescription,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let material_field = format!("{}.material", field);
+        Ok(match shape {
+            ShapeDescription::Sphere(SphereDescription {
+                center,
+                radius,
+                material,
+            }) => Box::new(Sphere::new(
+                *center,
+                *radius,
+                self.material(&material_field, material)?,
+            )),
+            ShapeDescription::MovingSphere(MovingSphereDescription {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            }) => Box::new(MovingSphere::new(
+                *center0,
+                *center1,
+                *time0,
+                *time1,
+                *radius,
+                self.material(&material_field, material)?,
+            )),
+            ShapeDescription::Plane(PlaneDescription {
+                center,
+                normal,
+                material,
+            }) => Box::new(Plane::new(
+                *center,
+                normal.unit_vector(),
+                self.material(&material_field, material)?,
+            )),
+            ShapeDescription::Triangle(TriangleDescription { a, b, c, material }) => {
+                Box::new(Triangle::new(
+                    *a,
+                    *b,
+                    *c,
+                    (*b - *a).cross(&(*c - *a)).unit_vector(),
+                    None,
+                    None,
+                    self.material(&material_field, material)?,
+                ))
+            }
+            ShapeDescription::Mesh(mesh) => Box::new(self.mesh(field, mesh)?),
+            ShapeDescription::Transform(TransformDescription {
+                shape,
+                translate,
+                rotate,
+                scale,
+            }) => {
+                let object = self.shared_shape(&format!("{}.shape", field), shape)?;
+                let matrix = Matrix::translation(*translate)
+                    * Matrix::rotation_z(rotate.z)
+                    * Matrix::rotation_y(rotate.y)
+                    * Matrix::rotation_x(rotate.x)
+                    * Matrix::scaling(*scale);
+                Box::new(
+                    Transform::new(object, matrix)
+                        .ok_or_else(|| SceneError::SingularTransform(format!("{}.scale", field)))?,
+                )
+            }
+            ShapeDescription::AnimatedTransform(AnimatedTransformDescription { shape, keys }) => {
+                let object = self.shared_shape(&format!("{}.shape", field), shape)?;
+                let mut keyframes = Vec::new();
+                for (i, key) in keys.iter().enumerate() {
+                    if key.scale.x == 0.0 || key.scale.y == 0.0 || key.scale.z == 0.0 {
+                        return Err(SceneError::SingularTransform(format!(
+                            "{}.keys[{}].scale",
+                            field, i
+                        )));
+                    }
+                    keyframes.push(Keyframe::new(
+                        key.time,
+                        key.translate,
+                        Quaternion::from_euler(key.rotate),
+                        key.scale,
+                    ));
+                }
+                Box::new(
+                    AnimatedTransform::new(object, keyframes)
+                        .ok_or_else(|| SceneError::NoKeyframes(format!("{}.keys", field)))?,
+                )
+            }
+            ShapeDescription::ConstantMedium(ConstantMediumDescription {
+                boundary,
+                density,
+                material,
+            }) => {
+                let boundary = self.shared_shape(&format!("{}.boundary", field), boundary)?;
+                let phase_function = self
+                    .material(&material_field, material)?
+                    .unwrap_or_else(|| Box::new(Isotropic::new(Color::new(1.0, 1.0, 1.0))));
+                Box::new(ConstantMedium::new(boundary, *density, phase_function))
+            }
+            ShapeDescription::HeterogeneousMedium(HeterogeneousMediumDescription {
+                boundary,
+                density,
+                material,
+            }) => {
+                let boundary = self.shared_shape(&format!("{}.boundary", field), boundary)?;
+                let density = self.density(&format!("{}.density", field), density, &boundary)?;
+                let phase_function = self
+                    .material(&material_field, material)?
+                    .unwrap_or_else(|| Box::new(Isotropic::new(Color::new(1.0, 1.0, 1.0))));
+                Box::new(HeterogeneousMedium::new(boundary, density, phase_function))
+            }
+        })
+    }
+
+    fn density(
+        &self,
+        field: &str,
+        density: &DensityDescription,
+        boundary: &Arc<dyn Hittable>,
+    ) -> Result<Box<dyn Density>, SceneError> {
+        Ok(match density {
+            DensityDescription::NoiseDensity(NoiseDensityDescription {
+                scale,
+                coverage,
+                octaves,
+                frequency,
+                persistence,
+                lacunarity,
+            }) => Box::new(NoiseDensity::new(
+                NoiseTexture::new(1.0, *octaves, *frequency, *persistence, *lacunarity),
+                *scale,
+                *coverage,
+            )),
+            DensityDescription::GridDensity(GridDensityDescription {
+                path,
+                scale,
+                resolution,
+            }) => {
+                let path = self.base_dir.join(path).display().to_string();
+                let error = |message: String| SceneError::Volume {
+                    field: format!("{}.path", field),
+                    path: path.clone(),
+                    message,
+                };
+                let bounds = boundary
+                    .bounding_box(MIN_TIME, MAX_TIME)
+                    .ok_or_else(|| error("the boundary has no bounding box".to_string()))?;
+                Box::new(GridDensity::load(&path, *resolution, bounds, *scale).map_err(error)?)
+            }
+        })
+    }
+
+    // Meshes are shared between their instances rather than copied
+    fn shared_shape(
+        &mut self,
+        field: &str,
+        shape: &ShapeDescription,
+    ) -> Result<Arc<dyn Hittable>, SceneError> {
+        match shape {
+            ShapeDescription::Mesh(mesh) => self.mesh(field, mesh),
+            shape => Ok(Arc::from(self.shape(field, shape)?)),
+        }
+    }
+
+    fn mesh(
+        &mut self,
+        field: &str,
+        mesh: &MeshDescription,
+    ) -> Result<Arc<dyn Hittable>, SceneError> {
+        let path = self.base_dir.join(&mesh.path).display().to_string();
+        let key = (path, mesh.material.clone());
+        if let Some(mesh) = self.meshes.get(&key) {
+            return Ok(mesh.clone());
+        }
+        let material = self.material(&format!("{}.material", field), &mesh.material)?;
+        let object: Arc<dyn Hittable> =
+            Arc::new(
+                load_mesh(&key.0, material).map_err(|error| SceneError::Mesh {
+                    field: format!("{}.path", field),
+                    error,
+                })?,
+            );
+        self.meshes.insert(key, object.clone());
+        Ok(object)
+    }
+
+    fn material(
+        &mut self,
+        field: &str,
+        id: &Option<String>,
+    ) -> Result<Option<Box<dyn Material + Send + Sync>>, SceneError> {
+        let id = match id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        // Dielectrics are built again for every shape, which rays can be inside
+        // of at the same time as other shapes of the same glass
+        if let Some(material) = self.materials.get(id).filter(|m| m.interior().is_none()) {
+            return Ok(Some(material.clone()));
+        }
+        let description =
+            self.description
+                .materials
+                .get(id)
+                .ok_or_else(|| SceneError::UnknownMaterial {
+                    field: field.to_string(),
+                    id: id.clone(),
+                })?;
+        let texture_field = format!("materials.{}.texture", id);
+        let material: Box<dyn Material + Send + Sync> = match description {
+            MaterialDescription::Lambertian(LambertianDescription { albedo, texture }) => {
+                match texture {
+                    Some(texture) => Box::new(Lambertian::with_texture(
+                        *albedo,
+                        self.texture(&texture_field, texture)?,
+                    )),
+                    None => Box::new(Lambertian::new(*albedo)),
+                }
+            }
+            MaterialDescription::Metal(MetalDescription {
+                albedo,
+                fuzz,
+                texture,
+            }) => match texture {
+                Some(texture) => Box::new(Metal::with_texture(
+                    *albedo,
+                    *fuzz,
+                    self.texture(&texture_field, texture)?,
+                )),
+                None => Box::new(Metal::new(*albedo, *fuzz)),
+            },
+            MaterialDescription::Dielectric(DielectricDescription {
+                n,
+                dispersion,
+                color,
+                distance,
+                priority,
+                roughness,
+            }) => {
+                let dispersion = dispersion.as_ref().map(|dispersion| match dispersion {
+                    DispersionDescription::Cauchy(CauchyDescription { a, b }) => {
+                        Dispersion::Cauchy { a: *a, b: *b }
+                    }
+                    DispersionDescription::Sellmeier(SellmeierDescription { b, c }) => {
+                        Dispersion::Sellmeier { b: *b, c: *c }
+                    }
+                });
+                // Without spectral mode, at the yellow sodium line refractive
+                // indices are usually measured at
+                let n = n.unwrap_or_else(|| dispersion.map_or(1.5, |d| d.index(589.3)));
+                let mut dielectric = Dielectric::new(n)
+                    .with_absorption(*color, *distance)
+                    .with_priority(*priority);
+                if let Some(roughness) = roughness {
+                    let field = format!("materials.{}.roughness", id);
+                    dielectric = dielectric.with_roughness(self.parameter(&field, roughness)?);
+                }
+                Box::new(match dispersion {
+                    Some(dispersion) => dielectric.with_dispersion(dispersion),
+                    None => dielectric,
+                })
+            }
+            MaterialDescription::DiffuseLight(DiffuseLightDescription { emit, texture }) => {
+                match texture {
+                    Some(texture) => Box::new(DiffuseLight::with_texture(
+                        *emit,
+                        self.texture(&texture_field, texture)?,
+                    )),
+                    None => Box::new(DiffuseLight::new(*emit)),
+                }
+            }
+            MaterialDescription::HenyeyGreenstein(HenyeyGreensteinDescription {
+                albedo,
+                g,
+                texture,
+            }) => match texture {
+                Some(texture) => Box::new(HenyeyGreenstein::with_texture(
+                    *albedo,
+                    *g,
+                    self.texture(&texture_field, texture)?,
+                )),
+                None => Box::new(HenyeyGreenstein::new(*albedo, *g)),
+            },
+            MaterialDescription::Isotropic(IsotropicDescription { albedo, texture }) => {
+                match texture {
+                    Some(texture) => Box::new(Isotropic::with_texture(
+                        *albedo,
+                        self.texture(&texture_field, texture)?,
+                    )),
+                    None => Box::new(Isotropic::new(*albedo)),
+                }
+            }
+            MaterialDescription::Principled(PrincipledDescription {
+                base_color,
+                texture,
+                metallic,
+                roughness,
+                specular,
+                specular_tint,
+                sheen,
+                clearcoat,
+                transmission,
+                ior,
+            }) => {
+                let mut parameter = |name: &str, parameter: &ParameterDescription| {
+                    self.parameter(&format!("materials.{}.{}", id, name), parameter)
+                };
+                let principled = Principled::new(*base_color)
+                    .with_metallic(parameter("metallic", metallic)?)
+                    .with_roughness(parameter("roughness", roughness)?)
+                    .with_specular(parameter("specular", specular)?)
+                    .with_specular_tint(parameter("specular_tint", specular_tint)?)
+                    .with_sheen(parameter("sheen", sheen)?)
+                    .with_clearcoat(parameter("clearcoat", clearcoat)?)
+                    .with_transmission(parameter("transmission", transmission)?)
+                    .with_ior(parameter("ior", ior)?);
+                Box::new(match texture {
+                    Some(texture) => {
+                        principled.with_base_color(self.texture(&texture_field, texture)?)
+                    }
+                    None => principled,
+                })
+            }
+            MaterialDescription::Conductor(ConductorDescription {
+                metal,
+                eta,
+                k,
+                roughness,
+                roughness_x,
+                roughness_y,
+                direction,
+            }) => {
+                let preset = metal.map(|metal| {
+                    match metal {
+                        PresetDescription::Gold => Preset::Gold,
+                        PresetDescription::Silver => Preset::Silver,
+                        PresetDescription::Copper => Preset::Copper,
+                        PresetDescription::Aluminum => Preset::Aluminum,
+                        PresetDescription::Chrome => Preset::Chrome,
+                    }
+                    .index()
+                });
+                // The index of the preset can be changed one part at a time
+                let (eta, k) = match (eta, k, preset) {
+                    (Some(eta), Some(k), _) => (*eta, *k),
+                    (eta, k, Some((preset_eta, preset_k))) => {
+                        (eta.unwrap_or(preset_eta), k.unwrap_or(preset_k))
+                    }
+                    _ => return Err(SceneError::NoIndex(format!("materials.{}", id))),
+                };
+                Box::new(
+                    Conductor::new(eta, k)
+                        .with_roughness(
+                            roughness_x.unwrap_or(*roughness),
+                            roughness_y.unwrap_or(*roughness),
+                        )
+                        .with_direction(*direction),
+                )
+            }
+        };
+        self.materials.insert(id.clone(), material.clone());
+        Ok(Some(material))
+    }
+
+    // A constant, or the texture it names
+    fn parameter(
+        &mut self,
+        field: &str,
+        parameter: &ParameterDescription,
+    ) -> Result<Box<dyn Texture + Send + Sync>, SceneError> {
+        match parameter {
+            ParameterDescription::Value(value) => Ok(Box::new(SolidColor::new(Color::new(
+                *value, *value, *value,
+            )))),
+            ParameterDescription::Texture(id) => self.texture(field, id),
+        }
+    }
+
+    fn texture(
+        &mut self,
+        field: &str,
+        id: &str,
+    ) -> Result<Box<dyn Texture + Send + Sync>, SceneError> {
+        if let Some(texture) = self.textures.get(id) {
+            return Ok(texture.clone());
+        }
+        if self.pending.iter().any(|pending| pending == id) {
+            return Err(SceneError::TextureCycle {
+                field: field.to_string(),
+                id: id.to_string(),
+            });
+        }
+        let description =
+            self.description
+                .textures
+                .get(id)
+                .ok_or_else(|| SceneError::UnknownTexture {
+                    field: field.to_string(),
+                    id: id.to_string(),
+                })?;
+
+        self.pending.push(id.to_string());
+        let texture: Box<dyn Texture + Send + Sync> = match description {
+            TextureDescription::SolidColor(SolidColorDescription { color }) => {
+                Box::new(SolidColor::new(*color))
+            }
+            TextureDescription::CheckerTexture(CheckerTextureDescription { odd, even }) => {
+                let odd = self.texture(&format!("textures.{}.odd", id), odd)?;
+                let even = self.texture(&format!("textures.{}.even", id), even)?;
+                Box::new(CheckerTexture::new(odd, even))
+            }
+            TextureDescription::NoiseTexture(NoiseTextureDescription {
+                scale,
+                octaves,
+                frequency,
+                persistence,
+                lacunarity,
+            }) => Box::new(NoiseTexture::new(
+                *scale,
+                *octaves,
+                *frequency,
+                *persistence,
+                *lacunarity,
+            )),
+            TextureDescription::ImageTexture(ImageTextureDescription { path }) => {
+                let path = self.base_dir.join(path).display().to_string();
+                let texture = ImageTexture::load(&path).map_err(|e| SceneError::Image {
+                    field: format!("textures.{}.path", id),
+                    path: path.clone(),
+                    message: e.to_string(),
+                })?;
+                Box::new(texture)
+            }
+            TextureDescription::VertexColors(VertexColorsDescription { fallback }) => {
+                Box::new(VertexColors::new(*fallback))
+            }
+        };
+        self.pending.pop();
+
+        self.textures.insert(id.to_string(), texture.clone());
+        Ok(texture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two spherical lights on either side of the origin, of different sizes
+    fn two_lights() -> Scene {
+        let light = |center: Point3, radius: f32| -> Box<dyn Hittable> {
+            let material = Box::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0)));
+            Box::new(Sphere::new(center, radius, Some(material)))
+        };
+        let shapes = vec![
+            light(Point3::new(-3.0, 0.0, 0.0), 1.0),
+            light(Point3::new(0.0, 5.0, 0.0), 2.0),
+        ];
+        let mut lights = LightList::default();
+        for shape in &shapes {
+            lights.add(Box::new(AreaLight::new(shape.clone())));
+        }
+        Scene {
+            camera: Camera::new(
+                Point3::new(0.0, 0.0, 5.0),
+                Point3::zero(),
+                Vec3::new(0.0, 1.0, 0.0),
+                40.0,
+                1.0,
+            ),
+            world: HittableList::new(shapes),
+            lights,
+            background: Box::new(SolidBackground::new(Color::zero())),
+        }
+    }
+
+    #[test]
+    fn light_pdf_matches_sample_light() {
+        let scene = two_lights();
+        assert_eq!(scene.light_count(), 2);
+        let origin = Point3::zero();
+        for _ in 0..1_000 {
+            let sample = scene.sample_light(&origin, 0.0).unwrap();
+            let ray = Ray::new(origin, sample.direction, 0.0);
+            let pdf = scene.light_pdf(&ray, sample.distance);
+            assert!((pdf - sample.pdf).abs() <= 1e-3 * sample.pdf);
+        }
+        // Away from both lights
+        let ray = Ray::new(origin, Vec3::new(0.0, 0.0, 1.0), 0.0);
+        assert_eq!(scene.light_pdf(&ray, 10.0), 0.0);
+    }
+}
@@ -47,6 +47,25 @@ impl Hittable for AxisAlignedBoundingBox {
 }
 
 impl AxisAlignedBoundingBox {
+    /// Slab test without building a `HitRecord`, used when traversing a BVH
+    pub fn intersects(&self, ray: &ray::Ray, t_min: f32, t_max: f32) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for a in 0..3 {
+            let inv_d = 1.0 / ray.direction[a];
+            let mut t0 = (self.min[a] - ray.origin[a]) * inv_d;
+            let mut t1 = (self.max[a] - ray.origin[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
     pub fn surrounding_box(box0: AxisAlignedBoundingBox, box1: AxisAlignedBoundingBox) -> Self {
         let small = Point3::new(
             box0.min.x.min(box1.min.x),
@@ -70,12 +89,8 @@ impl AxisAlignedBoundingBox {
 impl Default for AxisAlignedBoundingBox {
     fn default() -> Self {
         Self::new(
-            Point3::new(std::f32::INFINITY, std::f32::INFINITY, std::f32::INFINITY),
-            Point3::new(
-                std::f32::NEG_INFINITY,
-                std::f32::NEG_INFINITY,
-                std::f32::NEG_INFINITY,
-            ),
+            Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
             None,
         )
     }
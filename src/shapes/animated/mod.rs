@@ -1 +1,2 @@
 pub mod sphere;
+pub mod transform;
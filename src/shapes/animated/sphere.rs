@@ -1,10 +1,16 @@
+use std::f32::consts::PI;
+
 use crate::{
     shape,
-    shapes::{aabb::AxisAlignedBoundingBox, sphere::Sphere},
+    shapes::{
+        aabb::AxisAlignedBoundingBox,
+        sphere::{cone_pdf, random_in_cone, Sphere},
+    },
     utils::{
+        helpers::random_vector,
         hittable::{HitRecord, Hittable},
         ray::Ray,
-        vec::Point3,
+        vec::{Point3, Vec3},
     },
 };
 
@@ -28,16 +34,16 @@ impl Hittable for MovingSphere {
             return None;
         }
         let discriminant = discriminant.sqrt();
-        let t = (-half_b - discriminant) / a;
-        if t < t_max && t > t_min {
-            let point = ray.at(t);
-            let normal = (point - self.center(ray.time)) / self.radius;
-            let (u, v) = Sphere::get_sphere_uv(normal);
-            let mut hit_record = HitRecord::new(point, normal, self.material.clone(), t, u, v);
-            hit_record.set_face_normal(ray, normal);
-            return Some(hit_record);
-        }
-        None
+        // The far root is where rays starting inside the sphere leave it
+        let t = [(-half_b - discriminant) / a, (-half_b + discriminant) / a]
+            .into_iter()
+            .find(|&t| t < t_max && t > t_min)?;
+        let point = ray.at(t);
+        let normal = (point - self.center(ray.time)) / self.radius;
+        let (u, v) = Sphere::get_sphere_uv(normal);
+        let mut hit_record = HitRecord::new(point, normal, self.material.clone(), t, u, v);
+        hit_record.set_face_normal(ray, normal);
+        Some(hit_record)
     }
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox> {
         let box0 = AxisAlignedBoundingBox::new(
@@ -52,6 +58,24 @@ impl Hittable for MovingSphere {
         );
         Some(AxisAlignedBoundingBox::surrounding_box(box0, box1))
     }
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        if self.hit(ray, 1e-4, f32::INFINITY).is_none() {
+            return 0.0;
+        }
+        cone_pdf(self.center(ray.time), self.radius, &ray.origin)
+    }
+    fn random(&self, origin: &Point3, time: f32) -> Option<Vec3> {
+        let direction = random_in_cone(self.center(time), self.radius, origin)?;
+        let hit = self.hit(&Ray::new(*origin, direction, time), 1e-4, f32::INFINITY)?;
+        Some(hit.t * direction)
+    }
+    fn sample_surface(&self, time: f32) -> Option<(Point3, Vec3)> {
+        let normal = random_vector();
+        Some((self.center(time) + self.radius * normal, normal))
+    }
+    fn surface_pdf(&self, _point: &Point3, _normal: &Vec3, _time: f32) -> f32 {
+        1.0 / (4.0 * PI * self.radius * self.radius)
+    }
 }
 
 impl MovingSphere {
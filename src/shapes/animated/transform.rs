@@ -0,0 +1,310 @@
+use std::sync::Arc;
+
+use crate::{
+    shapes::{
+        aabb::AxisAlignedBoundingBox,
+        transform::{
+            hit_transformed, pdf_transformed, random_transformed, sample_surface_transformed,
+            surface_pdf_transformed, transform_box, transmittance_transformed,
+        },
+    },
+    utils::{
+        hittable::{HitRecord, Hittable},
+        matrix::Matrix,
+        quaternion::Quaternion,
+        ray::Ray,
+        vec::{Point3, Vec3},
+    },
+};
+
+// Samples taken between two keyframes when bounding the motion
+const BOUND_STEPS: usize = 16;
+
+/// The placement of an object at a given time: scaled, then rotated, then translated.
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix {
+        Matrix::translation(self.translation)
+            * self.rotation.to_matrix()
+            * Matrix::scaling(self.scale)
+    }
+
+    /// The inverse of `matrix`, built from its parts, or `None` when a scale is 0
+    pub fn inverse(&self) -> Option<Matrix> {
+        if self.scale.x == 0.0 || self.scale.y == 0.0 || self.scale.z == 0.0 {
+            return None;
+        }
+        Some(
+            Matrix::scaling(Vec3::new(
+                self.scale.x.recip(),
+                self.scale.y.recip(),
+                self.scale.z.recip(),
+            )) * self.rotation.to_matrix().transpose()
+                * Matrix::translation(-self.translation),
+        )
+    }
+
+    // The placement between this keyframe and the next one
+    fn interpolate(&self, next: &Keyframe, time: f32) -> Keyframe {
+        let duration = next.time - self.time;
+        let t = if duration > 0.0 {
+            ((time - self.time) / duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Keyframe::new(
+            time,
+            self.translation + t * (next.translation - self.translation),
+            self.rotation.slerp(&next.rotation, t),
+            self.scale + t * (next.scale - self.scale),
+        )
+    }
+}
+
+/// Another object moving along a track of keyframes, for motion blur.
+///
+/// Translation and scale are interpolated linearly between keyframes, and
+/// rotation spherically. Before the first keyframe and after the last one, the
+/// object stays still. Like `Transform`, the object is only shared.
+#[derive(Clone)]
+pub struct AnimatedTransform {
+    pub object: Arc<dyn Hittable>,
+    keys: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    /// Create a new AnimatedTransform, or `None` without any keyframe
+    /// # Arguments
+    /// * `object` - The object to move
+    /// * `keys` - Its placements over time, in any order
+    pub fn new(object: Arc<dyn Hittable>, keys: Vec<Keyframe>) -> Option<Self> {
+        if keys.is_empty() {
+            return None;
+        }
+        let mut keys = keys;
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Some(Self { object, keys })
+    }
+
+    pub fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    /// The placement of the object at a given time
+    pub fn at(&self, time: f32) -> Keyframe {
+        let next = self.keys.partition_point(|key| key.time <= time);
+        if next == 0 {
+            self.keys[0]
+        } else if next == self.keys.len() {
+            self.keys[next - 1]
+        } else {
+            self.keys[next - 1].interpolate(&self.keys[next], time)
+        }
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let key = self.at(ray.time);
+        hit_transformed(
+            self.object.as_ref(),
+            &key.matrix(),
+            &key.inverse()?,
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox> {
+        let local = self.object.bounding_box(time0, time1)?;
+        // How far a point of the object can be from its origin, which bounds how
+        // far a rotation sweeps it
+        let radius = [local.min, local.max]
+            .iter()
+            .map(|p| p.x.abs().max(p.y.abs()).max(p.z.abs()))
+            .fold(0.0, f32::max)
+            * 3f32.sqrt();
+        let max_scale = |key: &Keyframe| {
+            key.scale
+                .x
+                .abs()
+                .max(key.scale.y.abs())
+                .max(key.scale.z.abs())
+        };
+
+        // Sample the motion between each pair of keyframes within the interval
+        let mut times = vec![time0];
+        times.extend(
+            self.keys
+                .iter()
+                .map(|key| key.time)
+                .filter(|&time| time > time0 && time < time1),
+        );
+        times.push(time1);
+
+        let mut previous = self.at(time0);
+        let mut bounds = transform_box(&local, &previous.matrix());
+        let mut padding: f32 = 0.0;
+        for window in times.windows(2) {
+            for step in 1..=BOUND_STEPS {
+                let time = window[0] + (window[1] - window[0]) * step as f32 / BOUND_STEPS as f32;
+                let key = self.at(time);
+                bounds = AxisAlignedBoundingBox::surrounding_box(
+                    bounds,
+                    transform_box(&local, &key.matrix()),
+                );
+                // Between two samples, a rotation moves points along an arc which
+                // bulges out of their boxes by at most its sagitta
+                let angle = previous.rotation.angle_to(&key.rotation);
+                let sagitta = radius
+                    * max_scale(&previous).max(max_scale(&key))
+                    * (1.0 - (angle / 2.0).cos());
+                padding = padding.max(sagitta);
+                previous = key;
+            }
+        }
+        let padding = Point3::new(padding, padding, padding);
+        Some(AxisAlignedBoundingBox::new(
+            bounds.min - padding,
+            bounds.max + padding,
+            None,
+        ))
+    }
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        match self.at(ray.time).inverse() {
+            Some(inverse) => pdf_transformed(self.object.as_ref(), &inverse, ray),
+            None => 0.0,
+        }
+    }
+    fn random(&self, origin: &Point3, time: f32) -> Option<Vec3> {
+        let key = self.at(time);
+        random_transformed(
+            self.object.as_ref(),
+            &key.matrix(),
+            &key.inverse()?,
+            origin,
+            time,
+        )
+    }
+    fn sample_surface(&self, time: f32) -> Option<(Point3, Vec3)> {
+        let key = self.at(time);
+        sample_surface_transformed(self.object.as_ref(), &key.matrix(), &key.inverse()?, time)
+    }
+    fn surface_pdf(&self, point: &Point3, normal: &Vec3, time: f32) -> f32 {
+        let key = self.at(time);
+        match key.inverse() {
+            Some(inverse) => surface_pdf_transformed(
+                self.object.as_ref(),
+                &key.matrix(),
+                &inverse,
+                point,
+                normal,
+                time,
+            ),
+            None => 0.0,
+        }
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        match self.at(ray.time).inverse() {
+            Some(inverse) => {
+                transmittance_transformed(self.object.as_ref(), &inverse, ray, t_min, t_max)
+            }
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::sphere::Sphere;
+
+    // A unit sphere two units away from the origin, turning a quarter turn around
+    // the Z axis and moving up by 4 over the second half of the interval, given
+    // out of order
+    fn spinning() -> AnimatedTransform {
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let sphere = Arc::new(Sphere::new(Point3::new(2.0, 0.0, 0.0), 1.0, None));
+        let one = Vec3::new(1.0, 1.0, 1.0);
+        AnimatedTransform::new(
+            sphere,
+            vec![
+                Keyframe::new(
+                    1.0,
+                    Vec3::new(0.0, 4.0, 0.0),
+                    Quaternion::from_axis_angle(z, 90.0),
+                    one,
+                ),
+                Keyframe::new(0.5, Vec3::zero(), Quaternion::identity(), one),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn stays_still_outside_the_keyframes() {
+        let animated = spinning();
+        assert_eq!(animated.keys()[0].time, 0.5);
+        let at = |time: f32| {
+            animated
+                .at(time)
+                .matrix()
+                .transform_point(Point3::new(2.0, 0.0, 0.0))
+        };
+        assert!((at(0.0) - Point3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+        assert!((at(0.5) - Point3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+        assert!((at(1.0) - Point3::new(0.0, 6.0, 0.0)).length() < 1e-5);
+        assert!((at(2.0) - Point3::new(0.0, 6.0, 0.0)).length() < 1e-5);
+        // Halfway, an eighth of a turn and halfway up
+        let diagonal = 2.0_f32.sqrt();
+        assert!((at(0.75) - Point3::new(diagonal, 2.0 + diagonal, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn hits_the_object_where_it_is_at_the_ray_time() {
+        let animated = spinning();
+        let down = |x: f32, y: f32, time: f32| {
+            Ray::new(Point3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0), time)
+        };
+        assert!(animated
+            .hit(&down(2.0, 0.0, 0.2), 0.001, f32::INFINITY)
+            .is_some());
+        assert!(animated
+            .hit(&down(2.0, 0.0, 1.0), 0.001, f32::INFINITY)
+            .is_none());
+        let rec = animated
+            .hit(&down(0.0, 6.0, 1.0), 0.001, f32::INFINITY)
+            .unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bounds_the_whole_motion() {
+        let animated = spinning();
+        let bounds = animated.bounding_box(0.0, 1.0).unwrap();
+        for step in 0..=100 {
+            let key = animated.at(step as f32 / 100.0);
+            let center = key.matrix().transform_point(Point3::new(2.0, 0.0, 0.0));
+            for axis in 0..3 {
+                let (min, max) = (bounds.min[axis], bounds.max[axis]);
+                assert!(center[axis] - 1.0 >= min - 1e-4 && center[axis] + 1.0 <= max + 1e-4);
+            }
+        }
+    }
+}
@@ -21,23 +21,24 @@ impl Hittable for BvhNode {
         Some(self.bounding_box.clone())
     }
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        if self.bounding_box.hit(ray, t_min, t_max).is_none() {
+        if !self.bounding_box.intersects(ray, t_min, t_max) {
             return None;
         }
         let hit_left = self.left.hit(ray, t_min, t_max);
-        let hit_right = self.right.hit(ray, t_min, t_max);
-        match (hit_left, hit_right) {
-            (Some(hit_left), Some(hit_right)) => {
-                if hit_left.t < hit_right.t {
-                    Some(hit_left)
-                } else {
-                    Some(hit_right)
-                }
-            }
-            (Some(hit_left), None) => Some(hit_left),
-            (None, Some(hit_right)) => Some(hit_right),
-            (None, None) => None,
+        // Anything on the right has to be closer than what was found on the left
+        let closest = hit_left.as_ref().map_or(t_max, |hit| hit.t);
+        let hit_right = self.right.hit(ray, t_min, closest);
+        hit_right.or(hit_left)
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if !self.bounding_box.intersects(ray, t_min, t_max) {
+            return 1.0;
+        }
+        let left = self.left.transmittance(ray, t_min, t_max);
+        if left <= 0.0 {
+            return 0.0;
         }
+        left * self.right.transmittance(ray, t_min, t_max)
     }
 }
 
@@ -45,17 +46,9 @@ impl BvhNode {
     /// Create a new BvhNode from a list of objects
     /// # Arguments
     /// * `objects` - A list of objects to create the BvhNode from
-    /// * `start` - The start index of the objects
-    /// * `end` - The end index of the objects
     /// * `time0` - The start time
     /// * `time1` - The end time
-    pub fn from_objects(
-        objects: Vec<Box<dyn Hittable>>,
-        start: usize,
-        end: usize,
-        time0: f32,
-        time1: f32,
-    ) -> Self {
+    pub fn from_objects(objects: Vec<Box<dyn Hittable>>, time0: f32, time1: f32) -> Self {
         let axis = rand::random::<u8>() % 3;
         let mut objects = objects;
         let comparator = match axis {
@@ -71,32 +64,20 @@ impl BvhNode {
                 (left.clone(), left.clone()) as (Box<dyn Hittable>, Box<dyn Hittable>)
             }
             2 => {
-                if comparator(&objects[0], &objects[1]) == Ordering::Less {
+                if comparator(objects[0].as_ref(), objects[1].as_ref()) == Ordering::Less {
                     (objects.remove(0), objects.remove(0)) as (Box<dyn Hittable>, Box<dyn Hittable>)
                 } else {
                     (objects.remove(1), objects.remove(0)) as (Box<dyn Hittable>, Box<dyn Hittable>)
                 }
             }
             _ => {
-                objects.sort_by(comparator);
+                objects.sort_by(|a, b| comparator(a.as_ref(), b.as_ref()));
                 let mid = object_span / 2;
-                let left_objects = objects.drain(..mid).collect::<Vec<_>>();
-                let right_objects = objects.drain(..).collect::<Vec<_>>();
+                let right_objects = objects.split_off(mid);
                 (
-                    Box::new(BvhNode::from_objects(
-                        left_objects,
-                        start,
-                        end,
-                        time0,
-                        time1,
-                    )) as Box<dyn Hittable>,
-                    Box::new(BvhNode::from_objects(
-                        right_objects,
-                        start,
-                        end,
-                        time0,
-                        time1,
-                    )) as Box<dyn Hittable>,
+                    Box::new(BvhNode::from_objects(objects, time0, time1)) as Box<dyn Hittable>,
+                    Box::new(BvhNode::from_objects(right_objects, time0, time1))
+                        as Box<dyn Hittable>,
                 )
             }
         };
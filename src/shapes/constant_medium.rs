@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use crate::{
+    materials::Material,
+    shapes::aabb::AxisAlignedBoundingBox,
+    utils::{
+        helpers::random_float,
+        hittable::{HitRecord, Hittable},
+        ray::Ray,
+        vec::Vec3,
+    },
+};
+
+/// Fog or smoke of the same density everywhere inside a closed boundary.
+///
+/// Rays going through it travel a random distance before scattering, more likely
+/// to be short the denser the medium is, and go through it without hitting
+/// anything when that distance is beyond where they leave the boundary.
+#[derive(Clone)]
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Hittable>,
+    pub density: f32,
+    pub phase_function: Box<dyn Material + Send + Sync>,
+}
+
+impl ConstantMedium {
+    /// Create a new ConstantMedium
+    /// # Arguments
+    /// * `boundary` - A closed object, which the medium fills
+    /// * `density` - The chance of scattering per unit of distance
+    /// * `phase_function` - The material of the particles, usually `Isotropic`
+    pub fn new(
+        boundary: Arc<dyn Hittable>,
+        density: f32,
+        phase_function: Box<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (enter, t_enter, t_leave) = inside(self.boundary.as_ref(), ray, t_min, t_max)?;
+        let ray_length = ray.direction.length();
+        let distance_inside = (t_leave - t_enter) * ray_length;
+        let hit_distance = -(1.0 - random_float()).ln() / self.density;
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+        // The normal is arbitrary, as scattering inside a medium does not depend on it
+        let mut hit_record = HitRecord::new(
+            ray.at(t),
+            Vec3::new(1.0, 0.0, 0.0),
+            Some(self.phase_function.clone()),
+            t,
+            enter.u,
+            enter.v,
+        );
+        hit_record.front_face = true;
+        hit_record.in_medium = true;
+        Some(hit_record)
+    }
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox> {
+        self.boundary.bounding_box(time0, time1)
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        match inside(self.boundary.as_ref(), ray, t_min, t_max) {
+            Some((_, t_enter, t_leave)) => {
+                (-self.density * (t_leave - t_enter) * ray.direction.length()).exp()
+            }
+            None => 1.0,
+        }
+    }
+}
+
+/// Where `ray` goes through a closed `boundary` between `t_min` and `t_max`: the
+/// hit where its line enters the boundary, and the ray parameters of the part
+/// inside of it, `None` when there is none
+pub fn inside(
+    boundary: &dyn Hittable,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(HitRecord, f32, f32)> {
+    // Where the ray line enters and leaves the boundary, even behind its origin,
+    // so that rays starting inside the medium are handled too
+    let enter = boundary.hit(ray, f32::NEG_INFINITY, f32::INFINITY)?;
+    let leave = boundary.hit(ray, enter.t + 1e-4, f32::INFINITY)?;
+
+    let t_enter = enter.t.max(t_min).max(0.0);
+    let t_leave = leave.t.min(t_max);
+    (t_enter < t_leave).then_some((enter, t_enter, t_leave))
+}
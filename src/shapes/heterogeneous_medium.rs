@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use crate::{
+    densities::Density,
+    materials::Material,
+    shapes::{aabb::AxisAlignedBoundingBox, constant_medium::inside},
+    utils::{
+        helpers::random_float,
+        hittable::{HitRecord, Hittable},
+        ray::Ray,
+        vec::Vec3,
+    },
+};
+
+/// Clouds or smoke whose density varies inside a closed boundary.
+///
+/// Rays are tracked through a denser, homogeneous medium of the highest density,
+/// and some of the collisions found are dismissed as fictitious depending on the
+/// actual density there. Delta tracking keeps the first real collision, ratio
+/// tracking multiplies the chances of each one being fictitious to give the
+/// transmittance of shadow rays. Both are unbiased.
+#[derive(Clone)]
+pub struct HeterogeneousMedium {
+    pub boundary: Arc<dyn Hittable>,
+    pub density: Box<dyn Density>,
+    pub phase_function: Box<dyn Material + Send + Sync>,
+}
+
+impl HeterogeneousMedium {
+    /// Create a new HeterogeneousMedium
+    /// # Arguments
+    /// * `boundary` - A closed object, which the medium fills
+    /// * `density` - The chance of scattering per unit of distance, in the boundary's space
+    /// * `phase_function` - The material of the particles, `Isotropic` or `HenyeyGreenstein`
+    pub fn new(
+        boundary: Arc<dyn Hittable>,
+        density: Box<dyn Density>,
+        phase_function: Box<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+
+    // The ray parameter of the next tentative collision after `t`
+    fn step(&self, ray_length: f32, t: f32) -> f32 {
+        t - (1.0 - random_float()).ln() / (self.density.max_value() * ray_length)
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        if self.density.max_value() <= 0.0 {
+            return None;
+        }
+        let (enter, mut t, t_leave) = inside(self.boundary.as_ref(), ray, t_min, t_max)?;
+        let ray_length = ray.direction.length();
+        loop {
+            t = self.step(ray_length, t);
+            if t >= t_leave {
+                return None;
+            }
+            let point = ray.at(t);
+            if random_float() * self.density.max_value() < self.density.value(&point) {
+                // The normal is arbitrary, as scattering inside a medium does not depend on it
+                let mut hit_record = HitRecord::new(
+                    point,
+                    Vec3::new(1.0, 0.0, 0.0),
+                    Some(self.phase_function.clone()),
+                    t,
+                    enter.u,
+                    enter.v,
+                );
+                hit_record.front_face = true;
+                hit_record.in_medium = true;
+                return Some(hit_record);
+            }
+        }
+    }
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox> {
+        self.boundary.bounding_box(time0, time1)
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if self.density.max_value() <= 0.0 {
+            return 1.0;
+        }
+        let (_, mut t, t_leave) = match inside(self.boundary.as_ref(), ray, t_min, t_max) {
+            Some(inside) => inside,
+            None => return 1.0,
+        };
+        let ray_length = ray.direction.length();
+        let mut transmittance = 1.0;
+        loop {
+            t = self.step(ray_length, t);
+            if t >= t_leave {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.density.value(&ray.at(t)) / self.density.max_value();
+            // Past a point, carrying on costs more than the light left is worth
+            if transmittance < 0.1 {
+                if random_float() < 0.5 {
+                    return 0.0;
+                }
+                transmittance *= 2.0;
+            }
+        }
+    }
+}
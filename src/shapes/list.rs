@@ -37,6 +37,16 @@ impl Hittable for HittableList {
         }
         hit_record
     }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let mut transmittance = 1.0;
+        for object in &self.objects {
+            transmittance *= object.transmittance(ray, t_min, t_max);
+            if transmittance <= 0.0 {
+                break;
+            }
+        }
+        transmittance
+    }
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox> {
         if self.objects.is_empty() {
             return None;
@@ -0,0 +1,471 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::{
+    materials::Material,
+    shapes::{
+        aabb::AxisAlignedBoundingBox,
+        triangle::{area_pdf, random_in_triangle},
+    },
+    utils::{
+        helpers::random_float,
+        hittable::{HitRecord, Hittable},
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+// Triangles per BVH leaf
+const LEAF_SIZE: usize = 4;
+
+/// An indexed triangle mesh: its triangles share the vertex buffers and a
+/// single material, and are searched through a BVH of their own.
+///
+/// This is much lighter than a `BvhNode` of `Triangle`s, which each hold copies
+/// of their vertices and material, so imported meshes are built with it.
+#[derive(Clone)]
+pub struct TriangleMesh {
+    pub material: Option<Box<dyn Material + Send + Sync>>,
+    // Shared so that cloning a mesh does not copy its buffers
+    buffers: Arc<MeshBuffers>,
+}
+
+struct MeshBuffers {
+    positions: Vec<Point3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    colors: Option<Vec<Color>>,
+    // Reordered while building the BVH so that each leaf holds a contiguous range
+    triangles: Vec<[u32; 3]>,
+    nodes: Vec<MeshNode>,
+    // Running totals of the triangle areas, only built for meshes sampled as lights
+    areas: OnceLock<Vec<f32>>,
+}
+
+struct MeshNode {
+    min: Point3,
+    max: Point3,
+    // Leaves hold `count` triangles starting at `offset`. Inner nodes have a zero
+    // `count`, their left child right after them and their right child at `offset`
+    offset: u32,
+    count: u32,
+}
+
+impl TriangleMesh {
+    /// Create a new TriangleMesh from its vertex buffers
+    /// # Arguments
+    /// * `positions` - The vertex positions
+    /// * `normals` - Per-vertex normals, interpolated across triangles for smooth shading
+    /// * `uvs` - Per-vertex texture coordinates
+    /// * `colors` - Per-vertex colors, interpolated across triangles and read through `VertexColors`
+    /// * `triangles` - Three vertex indices per triangle, degenerate or out of range ones are dropped
+    /// * `material` - The material of the whole mesh
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f32, f32)>>,
+        colors: Option<Vec<Color>>,
+        triangles: Vec<[u32; 3]>,
+        material: Option<Box<dyn Material + Send + Sync>>,
+    ) -> Self {
+        let vertex_count = positions.len();
+        let normals = normals.filter(|normals| normals.len() == vertex_count);
+        let uvs = uvs.filter(|uvs| uvs.len() == vertex_count);
+        let colors = colors.filter(|colors| colors.len() == vertex_count);
+        let mut triangles = triangles
+            .into_iter()
+            .filter(|triangle| {
+                triangle.iter().all(|&i| (i as usize) < vertex_count) && {
+                    let [a, b, c] = triangle.map(|i| positions[i as usize]);
+                    !(b - a).cross(&(c - a)).near_zero()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut nodes = Vec::with_capacity(2 * triangles.len() / LEAF_SIZE + 1);
+        if !triangles.is_empty() {
+            build(&mut nodes, &positions, &mut triangles, 0);
+        }
+        Self {
+            material,
+            buffers: Arc::new(MeshBuffers {
+                positions,
+                normals,
+                uvs,
+                colors,
+                triangles,
+                nodes,
+                areas: OnceLock::new(),
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.triangles.is_empty()
+    }
+
+    fn normal(&self, triangle: usize) -> Vec3 {
+        let [a, b, c] =
+            self.buffers.triangles[triangle].map(|i| self.buffers.positions[i as usize]);
+        (b - a).cross(&(c - a))
+    }
+
+    fn areas(&self) -> &[f32] {
+        self.buffers.areas.get_or_init(|| {
+            let mut total = 0.0;
+            (0..self.buffers.triangles.len())
+                .map(|triangle| {
+                    total += self.normal(triangle).length() / 2.0;
+                    total
+                })
+                .collect()
+        })
+    }
+
+    // A triangle picked with a probability proportional to its area
+    fn random_triangle(&self) -> Option<usize> {
+        let areas = self.areas();
+        let target = random_float() * areas.last()?;
+        Some(
+            areas
+                .partition_point(|&area| area < target)
+                .min(areas.len() - 1),
+        )
+    }
+
+    // The closest triangle hit by the ray, with the distance and the barycentric
+    // weights of its second and third vertices
+    fn closest(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(usize, f32, f32, f32)> {
+        let buffers = &self.buffers;
+        if buffers.nodes.is_empty() {
+            return None;
+        }
+        let inverse_direction = Vec3::new(
+            ray.direction.x.recip(),
+            ray.direction.y.recip(),
+            ray.direction.z.recip(),
+        );
+
+        let mut closest = t_max;
+        let mut found = None;
+        // The BVH is balanced, so its depth stays far below the size of the stack
+        let mut stack = [0u32; 64];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let index = stack[len] as usize;
+            let node = &buffers.nodes[index];
+            if !node.intersects(ray, &inverse_direction, t_min, closest) {
+                continue;
+            }
+            if node.count > 0 {
+                for triangle in node.offset as usize..(node.offset + node.count) as usize {
+                    if let Some((t, u, v)) = self.intersect(triangle, ray, t_min, closest) {
+                        closest = t;
+                        found = Some((triangle, t, u, v));
+                    }
+                }
+            } else {
+                stack[len] = node.offset;
+                stack[len + 1] = index as u32 + 1;
+                len += 2;
+            }
+        }
+        found
+    }
+
+    // Möller-Trumbore intersection, returning the distance and the barycentric
+    // weights of the second and third vertices
+    fn intersect(
+        &self,
+        triangle: usize,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<(f32, f32, f32)> {
+        let [a, b, c] =
+            self.buffers.triangles[triangle].map(|i| self.buffers.positions[i as usize]);
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = ray.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let s = ray.origin - a;
+        let u = s.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&edge1);
+        let v = ray.direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(&q) * inverse;
+        (t > t_min && t < t_max).then_some((t, u, v))
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let buffers = &self.buffers;
+        let (triangle, closest, u, v) = self.closest(ray, t_min, t_max)?;
+        let indices = buffers.triangles[triangle].map(|i| i as usize);
+        let weights = [1.0 - u - v, u, v];
+        let [a, b, c] = indices.map(|i| buffers.positions[i]);
+        let (u, v) = match &buffers.uvs {
+            Some(uvs) => indices
+                .iter()
+                .zip(weights)
+                .fold((0.0, 0.0), |(u, v), (&i, w)| {
+                    (u + w * uvs[i].0, v + w * uvs[i].1)
+                }),
+            None => (u, v),
+        };
+        let outward_normal = (b - a).cross(&(c - a)).unit_vector();
+        let mut hit_record = HitRecord::new(
+            ray.at(closest),
+            outward_normal,
+            self.material.clone(),
+            closest,
+            u,
+            v,
+        );
+        hit_record.set_face_normal(ray, outward_normal);
+        hit_record.barycentric = Some((weights[1], weights[2]));
+        hit_record.color = buffers.colors.as_ref().map(|colors| {
+            indices
+                .iter()
+                .zip(weights)
+                .fold(Color::zero(), |color, (&i, w)| color + w * colors[i])
+        });
+        if let Some(normals) = &buffers.normals {
+            let shading_normal = indices
+                .iter()
+                .zip(weights)
+                .fold(Vec3::zero(), |normal, (&i, w)| normal + w * normals[i])
+                .unit_vector();
+            // Keep the interpolated normal on the side the ray came from, whatever
+            // the winding of the triangle
+            hit_record.normal = if shading_normal.dot(&hit_record.normal) < 0.0 {
+                -shading_normal
+            } else {
+                shading_normal
+            };
+        }
+        Some(hit_record)
+    }
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AxisAlignedBoundingBox> {
+        self.buffers
+            .nodes
+            .first()
+            .map(|root| AxisAlignedBoundingBox::new(root.min, root.max, None))
+    }
+    // Points are picked uniformly over the whole surface of the mesh
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        match self.closest(ray, 1e-4, f32::INFINITY) {
+            Some((triangle, t, _, _)) => {
+                let total = self.areas().last().copied().unwrap_or_default();
+                area_pdf(ray, t, &self.normal(triangle), total)
+            }
+            None => 0.0,
+        }
+    }
+    fn random(&self, origin: &Point3, _time: f32) -> Option<Vec3> {
+        let triangle = self.random_triangle()?;
+        let [a, b, c] =
+            self.buffers.triangles[triangle].map(|i| self.buffers.positions[i as usize]);
+        Some(random_in_triangle(a, b, c) - *origin)
+    }
+    fn sample_surface(&self, _time: f32) -> Option<(Point3, Vec3)> {
+        let triangle = self.random_triangle()?;
+        let [a, b, c] =
+            self.buffers.triangles[triangle].map(|i| self.buffers.positions[i as usize]);
+        Some((
+            random_in_triangle(a, b, c),
+            self.normal(triangle).unit_vector(),
+        ))
+    }
+    fn surface_pdf(&self, _point: &Point3, _normal: &Vec3, _time: f32) -> f32 {
+        match self.areas().last() {
+            Some(&total) if total > 0.0 => 1.0 / total,
+            _ => 0.0,
+        }
+    }
+}
+
+impl MeshNode {
+    fn intersects(&self, ray: &Ray, inverse_direction: &Vec3, t_min: f32, t_max: f32) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for a in 0..3 {
+            let t0 = (self.min[a] - ray.origin[a]) * inverse_direction[a];
+            let t1 = (self.max[a] - ray.origin[a]) * inverse_direction[a];
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+        t_min <= t_max
+    }
+}
+
+// Recursively split the triangles at the median of their centroids along the
+// longest axis of their bounds, appending the nodes in depth-first order
+fn build(
+    nodes: &mut Vec<MeshNode>,
+    positions: &[Point3],
+    triangles: &mut [[u32; 3]],
+    offset: usize,
+) {
+    let mut min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for &i in triangles.iter().flatten() {
+        let p = positions[i as usize];
+        min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+        max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+    }
+    // Pad the bounds so that flat parts of the mesh do not end up with a flat box
+    let padding = Vec3::new(1e-4, 1e-4, 1e-4);
+    let index = nodes.len();
+    nodes.push(MeshNode {
+        min: min - padding,
+        max: max + padding,
+        offset: offset as u32,
+        count: triangles.len() as u32,
+    });
+    if triangles.len() <= LEAF_SIZE {
+        return;
+    }
+
+    let extent = max - min;
+    let axis = if extent.x > extent.y && extent.x > extent.z {
+        0
+    } else if extent.y > extent.z {
+        1
+    } else {
+        2
+    };
+    let centroid = |triangle: &[u32; 3]| {
+        triangle
+            .iter()
+            .map(|&i| positions[i as usize][axis])
+            .sum::<f32>()
+    };
+    let mid = triangles.len() / 2;
+    triangles.select_nth_unstable_by(mid, |a, b| centroid(a).total_cmp(&centroid(b)));
+    let (left, right) = triangles.split_at_mut(mid);
+    build(nodes, positions, left, offset);
+    nodes[index].offset = nodes.len() as u32;
+    nodes[index].count = 0;
+    build(nodes, positions, right, offset + mid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The unit square in the z = 0 plane, as two triangles, with texture
+    // coordinates matching the positions
+    fn square(normals: Option<Vec<Vec3>>) -> TriangleMesh {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let uvs = positions.iter().map(|p| (p.x, p.y)).collect();
+        TriangleMesh::new(
+            positions,
+            normals,
+            Some(uvs),
+            None,
+            vec![[0, 1, 2], [0, 2, 3]],
+            None,
+        )
+    }
+
+    fn down_at(x: f32, y: f32) -> Ray {
+        Ray::new(Point3::new(x, y, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0)
+    }
+
+    #[test]
+    fn interpolates_texture_coordinates() {
+        let mesh = square(None);
+        for (x, y) in [(0.25, 0.75), (0.8, 0.1), (0.5, 0.5)] {
+            let rec = mesh.hit(&down_at(x, y), 0.001, f32::INFINITY).unwrap();
+            assert!((rec.t - 2.0).abs() < 1e-5);
+            assert!((rec.point - Point3::new(x, y, 0.0)).length() < 1e-5);
+            assert!((rec.u - x).abs() < 1e-5 && (rec.v - y).abs() < 1e-5);
+            assert!(rec.front_face);
+            assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn interpolates_vertex_normals() {
+        // Normals tilted toward -X on the left edge and +X on the right one
+        let tilted = |x: f32| Vec3::new(x, 0.0, 1.0).unit_vector();
+        let mesh = square(Some(vec![
+            tilted(-1.0),
+            tilted(1.0),
+            tilted(1.0),
+            tilted(-1.0),
+        ]));
+
+        let rec = mesh.hit(&down_at(0.5, 0.25), 0.001, f32::INFINITY).unwrap();
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        let rec = mesh.hit(&down_at(0.75, 0.5), 0.001, f32::INFINITY).unwrap();
+        // Weighted 0.25, 0.25 and 0.5 between the normals of its first triangle
+        assert!((rec.normal - tilted(0.5)).length() < 1e-5);
+
+        // Hit from below, the normal is flipped toward the ray
+        let up = Ray::new(Point3::new(0.5, 0.5, -2.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let rec = mesh.hit(&up, 0.001, f32::INFINITY).unwrap();
+        assert!(!rec.front_face && rec.normal.z < 0.0);
+    }
+
+    #[test]
+    fn finds_the_closest_of_many_triangles() {
+        // Enough layers of triangles for the BVH to have several levels
+        let mut positions = Vec::new();
+        let mut triangles = Vec::new();
+        for layer in 0..50 {
+            let z = layer as f32 * 0.1;
+            let first = positions.len() as u32;
+            positions.push(Point3::new(0.0, 0.0, z));
+            positions.push(Point3::new(1.0, 0.0, z));
+            positions.push(Point3::new(0.0, 1.0, z));
+            triangles.push([first, first + 1, first + 2]);
+        }
+        let mesh = TriangleMesh::new(positions, None, None, None, triangles, None);
+
+        let ray = Ray::new(Point3::new(0.2, 0.2, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((rec.point.z - 4.9).abs() < 1e-4);
+        let ray = Ray::new(Point3::new(0.2, 0.2, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(rec.point.z.abs() < 1e-4);
+        // Beyond the hypotenuse of every layer
+        let ray = Ray::new(Point3::new(0.6, 0.6, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(mesh.hit(&ray, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn drops_degenerate_and_out_of_range_triangles() {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+        ];
+        let mesh = TriangleMesh::new(
+            positions,
+            None,
+            None,
+            None,
+            vec![[0, 1, 2], [0, 1, 3]],
+            None,
+        );
+        assert!(mesh.is_empty());
+        assert!(mesh.bounding_box(0.0, 1.0).is_none());
+    }
+}
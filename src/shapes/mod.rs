@@ -1,9 +1,13 @@
 pub mod aabb;
 pub mod animated;
 pub mod bvh;
+pub mod constant_medium;
+pub mod heterogeneous_medium;
 pub mod list;
+pub mod mesh;
 pub mod plane;
 pub mod sphere;
+pub mod transform;
 pub mod triangle;
 
 // Declare shape struct with material
@@ -20,12 +24,12 @@ macro_rules! shape {
     }) => {
         #[derive(Clone)]
         pub struct $name {
-            pub material: Option<Box<dyn crate::materials::Material + Send + Sync>>,
+            pub material: Option<Box<dyn $crate::materials::Material + Send + Sync>>,
             $(pub $field: $type),*
         }
 
         impl $name {
-            pub fn new($($field: $type),*, material: Option<Box<dyn crate::materials::Material + Send + Sync>>) -> Self {
+            pub fn new($($field: $type),*, material: Option<Box<dyn $crate::materials::Material + Send + Sync>>) -> Self {
                 Self {
                     material,
                     $($field),*
@@ -1,9 +1,12 @@
+use std::f32::consts::PI;
+
 use crate::{
     shape,
     utils::{
+        helpers::{random_cone_direction, random_vector},
         hittable::{HitRecord, Hittable},
         ray::Ray,
-        vec::Point3,
+        vec::{Point3, Vec3},
     },
 };
 
@@ -25,16 +28,16 @@ impl Hittable for Sphere {
             return None;
         }
         let discriminant = discriminant.sqrt();
-        let t = (-half_b - discriminant) / a;
-        if t < t_max && t > t_min {
-            let point = ray.at(t);
-            let normal = (point - self.center) / self.radius;
-            let (u, v) = Self::get_sphere_uv(normal);
-            let mut hit_record = HitRecord::new(point, normal, self.material.clone(), t, u, v);
-            hit_record.set_face_normal(ray, normal);
-            return Some(hit_record);
-        }
-        None
+        // The far root is where rays starting inside the sphere leave it
+        let t = [(-half_b - discriminant) / a, (-half_b + discriminant) / a]
+            .into_iter()
+            .find(|&t| t < t_max && t > t_min)?;
+        let point = ray.at(t);
+        let normal = (point - self.center) / self.radius;
+        let (u, v) = Self::get_sphere_uv(normal);
+        let mut hit_record = HitRecord::new(point, normal, self.material.clone(), t, u, v);
+        hit_record.set_face_normal(ray, normal);
+        Some(hit_record)
     }
     fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AxisAlignedBoundingBox> {
         Some(AxisAlignedBoundingBox::new(
@@ -43,6 +46,46 @@ impl Hittable for Sphere {
             None,
         ))
     }
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        if self.hit(ray, 1e-4, f32::INFINITY).is_none() {
+            return 0.0;
+        }
+        cone_pdf(self.center, self.radius, &ray.origin)
+    }
+    fn random(&self, origin: &Point3, time: f32) -> Option<Vec3> {
+        let direction = random_in_cone(self.center, self.radius, origin)?;
+        let hit = self.hit(&Ray::new(*origin, direction, time), 1e-4, f32::INFINITY)?;
+        Some(hit.t * direction)
+    }
+    fn sample_surface(&self, _time: f32) -> Option<(Point3, Vec3)> {
+        let normal = random_vector();
+        Some((self.center + self.radius * normal, normal))
+    }
+    fn surface_pdf(&self, _point: &Point3, _normal: &Vec3, _time: f32) -> f32 {
+        1.0 / (4.0 * PI * self.radius * self.radius)
+    }
+}
+
+// Spheres are sampled through the cone of directions under which they are seen,
+// which only exists from outside of them
+fn cos_theta_max(center: Point3, radius: f32, origin: &Point3) -> Option<f32> {
+    let distance_squared = (center - *origin).length_squared();
+    let ratio = radius * radius / distance_squared;
+    (ratio < 1.0).then(|| (1.0 - ratio).sqrt())
+}
+
+/// The probability density of `random_in_cone` returning a direction toward the sphere
+pub fn cone_pdf(center: Point3, radius: f32, origin: &Point3) -> f32 {
+    match cos_theta_max(center, radius, origin) {
+        Some(cos_theta_max) => 1.0 / (2.0 * PI * (1.0 - cos_theta_max)),
+        None => 0.0,
+    }
+}
+
+/// A uniformly random unit vector toward a sphere, seen from outside of it
+pub fn random_in_cone(center: Point3, radius: f32, origin: &Point3) -> Option<Vec3> {
+    let cos_theta_max = cos_theta_max(center, radius, origin)?;
+    Some(random_cone_direction(&(center - *origin), cos_theta_max))
 }
 
 unsafe impl Send for Sphere {}
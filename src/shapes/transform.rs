@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use crate::{
+    shapes::aabb::AxisAlignedBoundingBox,
+    utils::{
+        hittable::{HitRecord, Hittable},
+        matrix::Matrix,
+        ray::Ray,
+        vec::{Point3, Vec3},
+    },
+};
+
+/// Another object translated, rotated or scaled by an affine transform.
+///
+/// Rays are brought into the object's own space instead of moving the object,
+/// so the object is only shared: the same mesh can be instanced any number of
+/// times without copying its geometry.
+#[derive(Clone)]
+pub struct Transform {
+    pub object: Arc<dyn Hittable>,
+    pub matrix: Matrix,
+    pub inverse: Matrix,
+}
+
+impl Transform {
+    /// Create a new Transform, or `None` if the matrix cannot be inverted
+    /// # Arguments
+    /// * `object` - The object to transform
+    /// * `matrix` - The transform from the object's space to the world
+    pub fn new(object: Arc<dyn Hittable>, matrix: Matrix) -> Option<Self> {
+        Some(Self {
+            object,
+            matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_transformed(
+            self.object.as_ref(),
+            &self.matrix,
+            &self.inverse,
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox> {
+        let local = self.object.bounding_box(time0, time1)?;
+        Some(transform_box(&local, &self.matrix))
+    }
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        pdf_transformed(self.object.as_ref(), &self.inverse, ray)
+    }
+    fn random(&self, origin: &Point3, time: f32) -> Option<Vec3> {
+        random_transformed(
+            self.object.as_ref(),
+            &self.matrix,
+            &self.inverse,
+            origin,
+            time,
+        )
+    }
+    fn sample_surface(&self, time: f32) -> Option<(Point3, Vec3)> {
+        sample_surface_transformed(self.object.as_ref(), &self.matrix, &self.inverse, time)
+    }
+    fn surface_pdf(&self, point: &Point3, normal: &Vec3, time: f32) -> f32 {
+        surface_pdf_transformed(
+            self.object.as_ref(),
+            &self.matrix,
+            &self.inverse,
+            point,
+            normal,
+            time,
+        )
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        transmittance_transformed(self.object.as_ref(), &self.inverse, ray, t_min, t_max)
+    }
+}
+
+/// Intersects an object moved by `matrix`, by bringing the ray into its space
+pub fn hit_transformed(
+    object: &dyn Hittable,
+    matrix: &Matrix,
+    inverse: &Matrix,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<HitRecord> {
+    // The direction is left unnormalized so that distances along the ray are
+    // the same in both spaces
+    let local_ray = Ray::new(
+        inverse.transform_point(ray.origin),
+        inverse.transform_vector(ray.direction),
+        ray.time,
+    );
+    let mut hit_record = object.hit(&local_ray, t_min, t_max)?;
+    hit_record.point = matrix.transform_point(hit_record.point);
+    // Normals go through the inverse transpose to stay perpendicular to the
+    // surface under non-uniform scaling. This keeps them facing the ray too.
+    hit_record.normal = inverse
+        .transpose()
+        .transform_vector(hit_record.normal)
+        .unit_vector();
+    Some(hit_record)
+}
+
+/// The `pdf_value` of an object moved by a transform with the given inverse
+pub fn pdf_transformed(object: &dyn Hittable, inverse: &Matrix, ray: &Ray) -> f32 {
+    let direction = ray.direction.unit_vector();
+    let local_direction = inverse.transform_vector(direction);
+    let local_ray = Ray::new(
+        inverse.transform_point(ray.origin),
+        local_direction,
+        ray.time,
+    );
+    // Densities over solid angle change with the spread of directions, by the
+    // Jacobian of the normalized linear map: 1 for rotations and uniform scales
+    object.pdf_value(&local_ray) * inverse.determinant().abs() / local_direction.length().powi(3)
+}
+
+/// The `random` direction toward an object moved by `matrix`
+pub fn random_transformed(
+    object: &dyn Hittable,
+    matrix: &Matrix,
+    inverse: &Matrix,
+    origin: &Point3,
+    time: f32,
+) -> Option<Vec3> {
+    let local = object.random(&inverse.transform_point(*origin), time)?;
+    Some(matrix.transform_vector(local))
+}
+
+/// The `sample_surface` of an object moved by `matrix`
+pub fn sample_surface_transformed(
+    object: &dyn Hittable,
+    matrix: &Matrix,
+    inverse: &Matrix,
+    time: f32,
+) -> Option<(Point3, Vec3)> {
+    let (point, normal) = object.sample_surface(time)?;
+    Some((
+        matrix.transform_point(point),
+        inverse.transpose().transform_vector(normal).unit_vector(),
+    ))
+}
+
+/// The `surface_pdf` of an object moved by `matrix`
+pub fn surface_pdf_transformed(
+    object: &dyn Hittable,
+    matrix: &Matrix,
+    inverse: &Matrix,
+    point: &Point3,
+    normal: &Vec3,
+    time: f32,
+) -> f32 {
+    let local_normal = matrix.transpose().transform_vector(*normal).unit_vector();
+    // Densities over area shrink as the surface is stretched: by the change of
+    // volume, less the stretch along the normal
+    let stretch =
+        matrix.determinant().abs() * inverse.transpose().transform_vector(local_normal).length();
+    if stretch <= 0.0 {
+        return 0.0;
+    }
+    object.surface_pdf(&inverse.transform_point(*point), &local_normal, time) / stretch
+}
+
+/// The `transmittance` of an object moved by a transform with the given inverse
+pub fn transmittance_transformed(
+    object: &dyn Hittable,
+    inverse: &Matrix,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> f32 {
+    let local_ray = Ray::new(
+        inverse.transform_point(ray.origin),
+        inverse.transform_vector(ray.direction),
+        ray.time,
+    );
+    object.transmittance(&local_ray, t_min, t_max)
+}
+
+/// The box around the transformed corners of another box
+pub fn transform_box(bounds: &AxisAlignedBoundingBox, matrix: &Matrix) -> AxisAlignedBoundingBox {
+    let corners = (0..8).map(|i| {
+        matrix.transform_point(Point3::new(
+            if i & 1 == 0 {
+                bounds.min.x
+            } else {
+                bounds.max.x
+            },
+            if i & 2 == 0 {
+                bounds.min.y
+            } else {
+                bounds.max.y
+            },
+            if i & 4 == 0 {
+                bounds.min.z
+            } else {
+                bounds.max.z
+            },
+        ))
+    });
+    corners.fold(AxisAlignedBoundingBox::default(), |bounds, corner| {
+        AxisAlignedBoundingBox::surrounding_box(
+            bounds,
+            AxisAlignedBoundingBox::new(corner, corner, None),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::sphere::Sphere;
+
+    // The unit sphere stretched twice as long along X
+    fn ellipsoid() -> Transform {
+        let sphere = Arc::new(Sphere::new(Point3::zero(), 1.0, None));
+        Transform::new(sphere, Matrix::scaling(Vec3::new(2.0, 1.0, 1.0))).unwrap()
+    }
+
+    #[test]
+    fn hits_scaled_objects_at_world_distances() {
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let rec = ellipsoid().hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-5);
+        assert!((rec.point - Point3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn keeps_normals_perpendicular_under_non_uniform_scaling() {
+        let ray = Ray::new(Point3::new(5.0, 0.5, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let rec = ellipsoid().hit(&ray, 0.001, f32::INFINITY).unwrap();
+        let x = 2.0 * 0.75_f32.sqrt();
+        assert!((rec.point - Point3::new(x, 0.5, 0.0)).length() < 1e-5);
+        // The gradient of x² / 4 + y² + z²
+        let expected = Vec3::new(x / 2.0, 1.0, 0.0).unit_vector();
+        assert!((rec.normal - expected).length() < 1e-5);
+    }
+
+    #[test]
+    fn bounds_transformed_objects() {
+        let bounds = ellipsoid().bounding_box(0.0, 1.0).unwrap();
+        assert!((bounds.min - Point3::new(-2.0, -1.0, -1.0)).length() < 1e-5);
+        assert!((bounds.max - Point3::new(2.0, 1.0, 1.0)).length() < 1e-5);
+    }
+}
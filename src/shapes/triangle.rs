@@ -2,17 +2,22 @@ use crate::{
     shape,
     shapes::aabb::AxisAlignedBoundingBox,
     utils::{
+        helpers::random_float,
         hittable::{self, Hittable},
         ray::Ray,
         vec::{Point3, Vec3},
     },
 };
 
+// `normals` and `uvs` are optional per-vertex attributes (in a, b, c order),
+// used for smooth shading and texture mapping of imported meshes.
 shape!(Triangle {
     a: Point3,
     b: Point3,
     c: Point3,
-    normal: Vec3
+    normal: Vec3,
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f32, f32); 3]>
 });
 
 impl Hittable for Triangle {
@@ -34,28 +39,58 @@ impl Hittable for Triangle {
             let point = ray.at(t);
             let mut edge = self.b - self.a;
             let mut vp = point - self.a;
-            let mut c = edge.cross(&vp);
-            if normal.dot(&c) < 0.0 {
+            let c_ab = edge.cross(&vp);
+            if normal.dot(&c_ab) < 0.0 {
                 return None;
             }
 
             edge = self.c - self.b;
             vp = point - self.b;
-            c = edge.cross(&vp);
-            if normal.dot(&c) < 0.0 {
+            let c_bc = edge.cross(&vp);
+            if normal.dot(&c_bc) < 0.0 {
                 return None;
             }
 
             edge = self.a - self.c;
             vp = point - self.c;
-            c = edge.cross(&vp);
-            if normal.dot(&c) < 0.0 {
+            let c_ca = edge.cross(&vp);
+            if normal.dot(&c_ca) < 0.0 {
                 return None;
             }
-            let (u, v) = self.get_triangle_uv(&point);
+
+            // Barycentric weights of a, b and c: each is the area of the sub-triangle
+            // opposite to the vertex, relative to the whole triangle
+            let area = normal.dot(&normal);
+            let weights = [
+                normal.dot(&c_bc) / area,
+                normal.dot(&c_ca) / area,
+                normal.dot(&c_ab) / area,
+            ];
+
+            let (u, v) = match self.uvs {
+                Some(uvs) => (
+                    weights[0] * uvs[0].0 + weights[1] * uvs[1].0 + weights[2] * uvs[2].0,
+                    weights[0] * uvs[0].1 + weights[1] * uvs[1].1 + weights[2] * uvs[2].1,
+                ),
+                None => self.get_triangle_uv(&point),
+            };
+            let outward_normal = normal.unit_vector();
             let mut hit_record =
-                hittable::HitRecord::new(point, normal, self.material.clone(), t, u, v);
-            hit_record.set_face_normal(ray, normal);
+                hittable::HitRecord::new(point, outward_normal, self.material.clone(), t, u, v);
+            hit_record.set_face_normal(ray, outward_normal);
+            hit_record.barycentric = Some((weights[1], weights[2]));
+            if let Some(normals) = self.normals {
+                let shading_normal =
+                    (weights[0] * normals[0] + weights[1] * normals[1] + weights[2] * normals[2])
+                        .unit_vector();
+                // Keep the interpolated normal on the side the ray came from, whatever
+                // the winding of the triangle
+                hit_record.normal = if shading_normal.dot(&hit_record.normal) < 0.0 {
+                    -shading_normal
+                } else {
+                    shading_normal
+                };
+            }
             return Some(hit_record);
         }
         None
@@ -71,8 +106,69 @@ impl Hittable for Triangle {
             self.a.y.max(self.b.y.max(self.c.y)),
             self.a.z.max(self.b.z.max(self.c.z)),
         );
-        Some(AxisAlignedBoundingBox::new(small, big, None))
+        // Pad the box so that axis-aligned triangles do not end up with a flat one,
+        // which the slab test would never hit
+        let padding = Vec3::new(1e-4, 1e-4, 1e-4);
+        Some(AxisAlignedBoundingBox::new(
+            small - padding,
+            big + padding,
+            None,
+        ))
+    }
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        match self.hit(ray, 1e-4, f32::INFINITY) {
+            Some(hit) => {
+                let normal = (self.b - self.a).cross(&(self.c - self.a));
+                area_pdf(ray, hit.t, &normal, normal.length() / 2.0)
+            }
+            None => 0.0,
+        }
+    }
+    fn random(&self, origin: &Point3, _time: f32) -> Option<Vec3> {
+        Some(random_in_triangle(self.a, self.b, self.c) - *origin)
+    }
+    fn sample_surface(&self, _time: f32) -> Option<(Point3, Vec3)> {
+        let normal = (self.b - self.a).cross(&(self.c - self.a));
+        if normal.near_zero() {
+            return None;
+        }
+        Some((
+            random_in_triangle(self.a, self.b, self.c),
+            normal.unit_vector(),
+        ))
+    }
+    fn surface_pdf(&self, _point: &Point3, _normal: &Vec3, _time: f32) -> f32 {
+        let area = (self.b - self.a).cross(&(self.c - self.a)).length() / 2.0;
+        if area > 0.0 {
+            1.0 / area
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A uniformly random point of a triangle
+pub fn random_in_triangle(a: Point3, b: Point3, c: Point3) -> Point3 {
+    let r1 = random_float().sqrt();
+    let r2 = random_float();
+    (1.0 - r1) * a + r1 * (1.0 - r2) * b + r1 * r2 * c
+}
+
+/// Converts the density of points picked uniformly over a surface to a density
+/// over the solid angle seen from the origin of `ray`
+/// # Arguments
+/// * `ray` - The ray hitting the surface
+/// * `t` - Where along the ray the surface is hit
+/// * `normal` - The normal of the surface there, does not need to be normalized
+/// * `area` - The area of the whole surface
+pub fn area_pdf(ray: &Ray, t: f32, normal: &Vec3, area: f32) -> f32 {
+    let direction_length = ray.direction.length();
+    let distance_squared = t * t * direction_length * direction_length;
+    let cosine = (ray.direction.dot(normal) / (direction_length * normal.length())).abs();
+    if cosine < 1e-6 || area <= 0.0 {
+        return 0.0;
     }
+    distance_squared / (cosine * area)
 }
 
 impl Triangle {
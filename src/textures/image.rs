@@ -1,4 +1,6 @@
-use image::{io::Reader, GenericImageView};
+use std::sync::Arc;
+
+use image::{io::Reader, GenericImageView, ImageResult};
 
 use crate::{
     textures::Texture,
@@ -7,7 +9,8 @@ use crate::{
 
 #[derive(Clone, Debug)]
 pub struct ImageTexture {
-    pub data: Vec<u8>,
+    // Shared so that cloning the texture into every hit record stays cheap
+    pub data: Arc<Vec<u8>>,
     pub width: usize,
     pub height: usize,
 }
@@ -40,13 +43,24 @@ impl Texture for ImageTexture {
 
 impl ImageTexture {
     pub fn new(filename: &str) -> Self {
-        let img = Reader::open(filename).unwrap().decode().unwrap();
+        Self::load(filename).unwrap()
+    }
+    /// Wraps already decoded pixels, 3 bytes per pixel, row by row from the top.
+    pub fn from_rgb(data: Vec<u8>, width: usize, height: usize) -> Self {
+        Self {
+            data: Arc::new(data),
+            width,
+            height,
+        }
+    }
+    pub fn load(filename: &str) -> ImageResult<Self> {
+        let img = Reader::open(filename)?.decode()?;
         let (width, height) = img.dimensions();
         let data = img.into_rgb8().into_raw();
-        Self {
-            data,
+        Ok(Self {
+            data: Arc::new(data),
             width: width as usize,
             height: height as usize,
-        }
+        })
     }
 }
@@ -1,15 +1,26 @@
 use dyn_clonable::clonable;
 
-use crate::utils::vec::{Point3, Vec3};
+use crate::utils::{
+    hittable::HitRecord,
+    vec::{Point3, Vec3},
+};
 
 pub mod checker;
 pub mod image;
 pub mod noise;
+pub mod scaled;
 pub mod solid;
+pub mod vertex_colors;
 
 #[clonable]
 pub trait Texture: Send + Sync + Clone {
     fn value(&self, u: f32, v: f32, point: &Point3) -> Vec3;
+
+    /// The value at a hit, for textures which need more of it than its
+    /// coordinates and position
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        self.value(rec.u, rec.v, &rec.point)
+    }
 }
 
 #[macro_export]
@@ -57,7 +57,7 @@ impl NoiseTexture {
         self.lacunarity = lacunarity;
         self
     }
-    fn noise(&self, point: &Point3) -> f32 {
+    pub fn noise(&self, point: &Point3) -> f32 {
         // Based on tutorial from https://flafla2.github.io/2014/08/09/perlinnoise.html
         // Given an octave i we define:
         //   frequency = 2^i
@@ -89,7 +89,7 @@ impl NoiseTexture {
             z *= self.lacunarity;
         }
 
-        (total / max_value) as f32
+        total / max_value
     }
 }
 
@@ -0,0 +1,25 @@
+use crate::{
+    texture,
+    textures::Texture,
+    utils::{
+        hittable::HitRecord,
+        vec::{Color, Point3},
+    },
+};
+
+// Another texture multiplied by a constant color, like glTF base colors which
+// are their factor times their texture
+texture!(Scaled {
+    texture: Box<dyn Texture>,
+    factor: Color
+});
+
+impl Texture for Scaled {
+    fn value(&self, u: f32, v: f32, point: &Point3) -> Color {
+        self.factor * self.texture.value(u, v, point)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.factor * self.texture.value_at(rec)
+    }
+}
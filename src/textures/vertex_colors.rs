@@ -0,0 +1,79 @@
+use crate::{
+    texture,
+    textures::Texture,
+    utils::{
+        hittable::HitRecord,
+        vec::{Color, Point3},
+    },
+};
+
+// The vertex colors of the mesh hit, blended at the hit point, or `fallback`
+// for shapes without vertex colors
+texture!(VertexColors { fallback: Color });
+
+impl Texture for VertexColors {
+    fn value(&self, _u: f32, _v: f32, _point: &Point3) -> Color {
+        self.fallback
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        rec.color.unwrap_or(self.fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::{metal::Metal, Material},
+        shapes::mesh::TriangleMesh,
+        textures::scaled::Scaled,
+        utils::{hittable::Hittable, ray::Ray, vec::Vec3},
+    };
+
+    fn hit(colors: Option<Vec<Color>>) -> HitRecord {
+        let mesh = TriangleMesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            None,
+            None,
+            colors,
+            vec![[0, 1, 2]],
+            None,
+        );
+        // Through the centroid, where the three colors weigh the same
+        let ray = Ray::new(
+            Point3::new(1.0 / 3.0, 1.0 / 3.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        mesh.hit(&ray, 0.001, f32::INFINITY).unwrap()
+    }
+
+    #[test]
+    fn blends_vertex_colors_for_any_material() {
+        let rec = hit(Some(vec![
+            Color::new(3.0, 0.0, 0.0),
+            Color::new(0.0, 3.0, 0.0),
+            Color::new(0.0, 0.0, 3.0),
+        ]));
+        let texture = Box::new(VertexColors::new(Color::zero()));
+        let metal = Metal::with_texture(Color::zero(), 0.0, texture);
+        let albedo = metal.albedo(&rec);
+        assert!((albedo - Color::new(1.0, 1.0, 1.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn falls_back_without_vertex_colors() {
+        let rec = hit(None);
+        let fallback = Color::new(0.2, 0.4, 0.6);
+        let texture = Scaled::new(
+            Box::new(VertexColors::new(fallback)),
+            Color::new(0.5, 0.5, 0.5),
+        );
+        assert!((texture.value_at(&rec) - 0.5 * fallback).length() < 1e-6);
+    }
+}
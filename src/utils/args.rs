@@ -24,6 +24,39 @@ pub struct Args {
     pub output: String,
     #[clap(short, long, default_value_t = 90.0)]
     pub fov: f32,
+    /// Scene description (.toml, .json, .ron) or glTF (.gltf, .glb) file to render instead of the built-in scene
+    #[clap(long)]
+    pub scene: Option<String>,
+    /// Background replacing the scene's: `black`, `sky`, a daylight sky with the sun at `sky:elevation,azimuth[,turbidity]`, a color `r,g,b`, a bottom to top gradient `r,g,b:r,g,b` or an .hdr/.exr environment map
+    #[clap(long)]
+    pub background: Option<String>,
+    /// Rotation of the environment map around the Y axis, in degrees
+    #[clap(long, default_value_t = 0.0)]
+    pub background_rotation: f32,
+    /// Brightness multiplier of the environment map or daylight sky
+    #[clap(long, default_value_t = 1.0)]
+    pub background_intensity: f32,
+    /// Light added to the scene, can be repeated: `point:x,y,z:r,g,b`, `spot:x,y,z:dx,dy,dz:r,g,b[:inner,outer]` or `sun:dx,dy,dz:r,g,b[:angle]`
+    #[clap(long = "light")]
+    pub lights: Vec<String>,
+    /// How colors are computed: `path` tracing, `bdpt` (bidirectional path tracing), or `normals`, `albedo`, `uv`, `barycentric`, `depth[:far]` or `ao[:radius]` for debugging
+    #[clap(long, default_value = "path")]
+    pub integrator: String,
+    /// Maximum number of bounces of a path
+    #[clap(long, default_value_t = 1000)]
+    pub max_depth: i32,
+    /// Number of bounces before paths carrying little light can be ended early (Russian roulette)
+    #[clap(long, default_value_t = 3)]
+    pub min_depth: i32,
+    /// Number of photons shot from the lights before rendering, to look up the caustics they leave through glass and mirrors with the `path` integrator instead of finding them by chance
+    #[clap(long, default_value_t = 0)]
+    pub photons: usize,
+    /// Number of nearby photons each lookup of the caustics is made from, more blurs them but lowers the noise
+    #[clap(long, default_value_t = 50)]
+    pub photon_nearest: usize,
+    /// Trace a single wavelength per sample, so dispersive dielectrics split light into colors
+    #[clap(long)]
+    pub spectral: bool,
     #[clap(long)]
     pub open: bool,
     #[clap(long)]
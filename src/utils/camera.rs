@@ -7,7 +7,7 @@ use crate::utils::{
 pub const MIN_TIME: f32 = 0.0;
 pub const MAX_TIME: f32 = 1.0;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Camera {
     pub origin: Point3,
     pub lower_left_corner: Point3,
@@ -15,17 +15,6 @@ pub struct Camera {
     pub vertical: Vec3,
 }
 
-impl Default for Camera {
-    fn default() -> Self {
-        Self {
-            origin: Point3::default(),
-            lower_left_corner: Point3::default(),
-            horizontal: Vec3::default(),
-            vertical: Vec3::default(),
-        }
-    }
-}
-
 impl Camera {
     pub fn new(
         look_from: Point3,
@@ -60,4 +49,43 @@ impl Camera {
             random_float_range(MIN_TIME, MAX_TIME),
         )
     }
+
+    /// The point `(u, v)` of the film through which the camera sees `point`, as
+    /// given to `get_ray`, if it is in view
+    pub fn project(&self, point: &Point3) -> Option<(f32, f32)> {
+        let direction = *point - self.origin;
+        let forward = self.forward();
+        let distance = direction.dot(&forward);
+        if distance <= 0.0 {
+            return None;
+        }
+        // Where the direction crosses the film, one unit in front of the camera
+        let on_film = direction / distance - (self.lower_left_corner - self.origin);
+        let u = on_film.dot(&self.horizontal) / self.horizontal.length_squared();
+        let v = on_film.dot(&self.vertical) / self.vertical.length_squared();
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        Some((u, v))
+    }
+
+    /// The density over solid angle of `get_ray` picking `direction` for a
+    /// uniformly random point of the film. It is also how much the camera sees
+    /// of the light coming from that direction, its importance.
+    pub fn pdf(&self, direction: &Vec3) -> f32 {
+        let cosine = direction.unit_vector().dot(&self.forward());
+        if cosine <= 0.0 {
+            return 0.0;
+        }
+        // The film is one unit away, an area on it covers less solid angle
+        // towards its edges
+        let film_area = self.horizontal.length() * self.vertical.length();
+        1.0 / (film_area * cosine * cosine * cosine)
+    }
+
+    // The unit vector pointing at the center of the film
+    fn forward(&self) -> Vec3 {
+        (self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0 - self.origin)
+            .unit_vector()
+    }
 }
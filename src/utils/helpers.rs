@@ -4,16 +4,32 @@ use std::{
     fs::File,
     io::{BufWriter, Write},
     path::Path,
+    sync::Arc,
 };
 
 use jpeg_encoder::ColorType;
 
 use crate::{
+    backgrounds::{
+        environment::EnvironmentMap, gradient::GradientBackground, sky::SkyBackground,
+        solid::SolidBackground, Background,
+    },
+    integrators::{
+        albedo::AlbedoIntegrator, ao::AmbientOcclusionIntegrator,
+        barycentric::BarycentricIntegrator, bdpt::Bdpt, depth::DepthIntegrator,
+        normals::NormalsIntegrator, path::PathTracer, uv::UvIntegrator, Integrator,
+    },
+    lights::{directional::DirectionalLight, point::PointLight, spot::SpotLight, Light},
     materials::{lambertian::Lambertian, metal::Metal, Material},
-    shapes::{list::HittableList, sphere::Sphere},
+    scene::Scene,
+    shapes::sphere::Sphere,
     utils::{
-        camera::Camera,
+        onb::Onb,
+        photons::PhotonMap,
+        ray::Ray,
         result::Res,
+        spectrum::{self, sample_wavelength, upsample},
+        splats::Splats,
         vec::{Color, Vec3},
     },
 };
@@ -52,6 +68,26 @@ pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
     }
 }
 
+/// A uniformly random unit vector within a cone
+/// # Arguments
+/// * `axis` - The axis of the cone, does not need to be normalized
+/// * `cos_theta_max` - The cosine of the angle between the axis and the side of the cone
+pub fn random_cone_direction(axis: &Vec3, cos_theta_max: f32) -> Vec3 {
+    let z = 1.0 + random_float() * (cos_theta_max - 1.0);
+    let phi = 2.0 * std::f32::consts::PI * random_float();
+    let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+    Onb::from_w(axis).to_world(&Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+}
+
+/// A unit vector around the Z axis, with a density proportional to its cosine
+pub fn random_cosine_direction() -> Vec3 {
+    let r1 = random_float();
+    let r2 = random_float();
+    let phi = 2.0 * std::f32::consts::PI * r1;
+    let z = (1.0 - r2).sqrt();
+    Vec3::new(phi.cos() * r2.sqrt(), phi.sin() * r2.sqrt(), z)
+}
+
 pub fn reflectance(cosine: f32, ref_idx: f32) -> f32 {
     // Use Schlick's approximation for reflectance.
     let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
@@ -81,6 +117,192 @@ where
     }
     (chunks, chunk_sizes)
 }
+/// Parses a `--background` value: `black`, `sky`, a daylight sky
+/// `sky:elevation,azimuth[,turbidity]`, a color `r,g,b`, a gradient
+/// `r,g,b:r,g,b` going from bottom to top, or else the path of an environment map.
+/// # Arguments
+/// * `background` - The value to parse
+/// * `rotation` - The rotation of an environment map around the Y axis, in degrees
+/// * `intensity` - The brightness multiplier of an environment map or daylight sky
+pub fn parse_background(
+    background: &str,
+    rotation: f32,
+    intensity: f32,
+) -> Result<Box<dyn Background>, String> {
+    let parse_color = parse_vector;
+    if background == "black" {
+        return Ok(Box::new(SolidBackground::black()));
+    }
+    if background == "sky" {
+        return Ok(Box::new(GradientBackground::sky()));
+    }
+    if let Some(sun) = background.strip_prefix("sky:") {
+        let numbers = sun
+            .split(',')
+            .map(|n| n.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid sky `{}`", background))?;
+        let (elevation, azimuth, turbidity) = match numbers[..] {
+            [elevation, azimuth] => (elevation, azimuth, 3.0),
+            [elevation, azimuth, turbidity] => (elevation, azimuth, turbidity),
+            _ => {
+                return Err(format!(
+                    "invalid sky `{}`, expected sky:elevation,azimuth[,turbidity]",
+                    background
+                ))
+            }
+        };
+        return Ok(Box::new(SkyBackground::new(
+            elevation,
+            azimuth,
+            turbidity,
+            intensity,
+            true,
+            Color::new(0.3, 0.3, 0.3),
+        )));
+    }
+    if let Some(color) = parse_color(background) {
+        return Ok(Box::new(SolidBackground::new(color)));
+    }
+    if let Some((bottom, top)) = background.split_once(':') {
+        if let (Some(bottom), Some(top)) = (parse_color(bottom), parse_color(top)) {
+            return Ok(Box::new(GradientBackground::new(
+                bottom,
+                top,
+                Vec3::new(0.0, 1.0, 0.0),
+            )));
+        }
+    }
+    EnvironmentMap::load(background, rotation, intensity)
+        .map(|map| Box::new(map) as Box<dyn Background>)
+        .map_err(|e| format!("unable to load environment map {}: {}", background, e))
+}
+
+/// Parses a `--light` value, whose parts are separated by colons and vectors written `x,y,z`:
+/// * `point:position:intensity`
+/// * `spot:position:direction:intensity[:inner_angle,outer_angle]`
+/// * `sun:direction:irradiance[:angle]`, or `directional:...`
+pub fn parse_light(light: &str) -> Result<Box<dyn Light>, String> {
+    let parts = light.split(':').collect::<Vec<_>>();
+    let vector = |index: usize| {
+        parts
+            .get(index)
+            .and_then(|part| parse_vector(part))
+            .ok_or_else(|| format!("expected x,y,z as part {} of light `{}`", index + 1, light))
+    };
+    let number = |part: &str| {
+        part.trim()
+            .parse::<f32>()
+            .map_err(|_| format!("invalid number `{}` in light `{}`", part, light))
+    };
+    let light: Box<dyn Light> = match parts[0] {
+        "point" if parts.len() == 3 => Box::new(PointLight::new(vector(1)?, vector(2)?)),
+        "spot" if parts.len() == 4 || parts.len() == 5 => {
+            let (inner, outer) = match parts.get(4) {
+                Some(angles) => {
+                    let (inner, outer) = angles.split_once(',').ok_or_else(|| {
+                        format!("expected inner,outer angles in light `{}`", light)
+                    })?;
+                    (number(inner)?, number(outer)?)
+                }
+                None => (20.0, 30.0),
+            };
+            Box::new(SpotLight::new(
+                vector(1)?,
+                vector(2)?,
+                vector(3)?,
+                inner,
+                outer,
+            ))
+        }
+        "sun" | "directional" if parts.len() == 3 || parts.len() == 4 => {
+            let angle = match parts.get(3) {
+                Some(angle) => number(angle)?,
+                None => 0.0,
+            };
+            Box::new(DirectionalLight::new(vector(1)?, vector(2)?, angle))
+        }
+        _ => {
+            return Err(format!(
+                "invalid light `{}`, expected point:x,y,z:r,g,b, spot:x,y,z:x,y,z:r,g,b[:inner,outer] or sun:x,y,z:r,g,b[:angle]",
+                light
+            ))
+        }
+    };
+    Ok(light)
+}
+
+/// Parses an `--integrator` value:
+/// * `path`, tracing paths of at most `max_depth` bounces, ended by Russian roulette after `min_depth`
+/// * `bdpt`, bidirectional path tracing with the same limits
+/// * `normals`, `albedo`, `uv` or `barycentric`
+/// * `depth[:far]`, black from `far` (10 by default) onward
+/// * `ao[:radius]`, ambient occlusion of the objects within `radius` (1 by default)
+///
+/// Caustics can only be given to `path`. Their photon map is only built, by
+/// calling `caustics`, once the integrator is known to be valid.
+pub fn parse_integrator(
+    integrator: &str,
+    max_depth: i32,
+    min_depth: i32,
+    caustics: Option<impl FnOnce() -> Arc<PhotonMap>>,
+) -> Result<Arc<dyn Integrator>, String> {
+    let (name, argument) = match integrator.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (integrator, None),
+    };
+    let number = |default: f32| match argument {
+        Some(argument) => argument.trim().parse::<f32>().map_err(|_| {
+            format!(
+                "invalid number `{}` in integrator `{}`",
+                argument, integrator
+            )
+        }),
+        None => Ok(default),
+    };
+    if caustics.is_some() && name != "path" {
+        return Err(format!(
+            "photons can only be used with the path integrator, not `{}`",
+            integrator
+        ));
+    }
+    let integrator: Arc<dyn Integrator> = match name {
+        "path" if argument.is_none() => {
+            let tracer = PathTracer::new(max_depth, min_depth);
+            match caustics {
+                Some(caustics) => Arc::new(tracer.with_caustics(caustics())),
+                None => Arc::new(tracer),
+            }
+        }
+        "bdpt" if argument.is_none() => Arc::new(Bdpt::new(max_depth, min_depth)),
+        "normals" if argument.is_none() => Arc::new(NormalsIntegrator),
+        "albedo" if argument.is_none() => Arc::new(AlbedoIntegrator),
+        "uv" if argument.is_none() => Arc::new(UvIntegrator),
+        "barycentric" if argument.is_none() => Arc::new(BarycentricIntegrator),
+        "depth" => Arc::new(DepthIntegrator::new(number(10.0)?)),
+        "ao" => Arc::new(AmbientOcclusionIntegrator::new(number(1.0)?)),
+        _ => {
+            return Err(format!(
+                "invalid integrator `{}`, expected path, bdpt, normals, albedo, uv, barycentric, depth[:far] or ao[:radius]",
+                integrator
+            ))
+        }
+    };
+    Ok(integrator)
+}
+
+// Parses three comma separated numbers, such as a color `r,g,b`
+fn parse_vector(vector: &str) -> Option<Vec3> {
+    let components = vector
+        .split(',')
+        .map(|c| c.trim().parse::<f32>().ok())
+        .collect::<Option<Vec<f32>>>()?;
+    match components[..] {
+        [x, y, z] => Some(Vec3::new(x, y, z)),
+        _ => None,
+    }
+}
+
 pub fn parse_aspect_ratio(aspect_ratio: &str) -> Result<f32, String> {
     let splitters = vec![":", "/", "x"];
     for splitter in splitters {
@@ -103,9 +325,22 @@ pub fn parse_aspect_ratio(aspect_ratio: &str) -> Result<f32, String> {
     Err("Failed to parse aspect ratio".to_string())
 }
 
-pub fn compute_chunk(args: (Vec<i32>, Box<Camera>, Box<HittableList>, i32, i32, i32)) -> Res {
-    let (chunk, camera, world, image_width, image_height, samples_per_pixel) = args;
+/// Rows to render, scene, integrator, width, height, samples per pixel and
+/// whether to render in spectral mode
+pub type ChunkArgs = (
+    Vec<i32>,
+    Arc<Scene>,
+    Arc<dyn Integrator>,
+    i32,
+    i32,
+    i32,
+    bool,
+);
+
+pub fn compute_chunk(args: ChunkArgs) -> Res {
+    let (chunk, scene, integrator, image_width, image_height, samples_per_pixel, spectral) = args;
     let mut buffers = Vec::with_capacity(chunk.len());
+    let mut splats = Splats::new(image_width, image_height, samples_per_pixel);
     let start_index = chunk[0];
     for j in chunk {
         let mut buffer = Vec::with_capacity(image_width as usize);
@@ -115,13 +350,23 @@ pub fn compute_chunk(args: (Vec<i32>, Box<Camera>, Box<HittableList>, i32, i32,
             for _ in 0..samples_per_pixel {
                 let u = (i as f32 + random_float()) / (image_width - 1) as f32; // 0.0 <= u <= 1.0 | u is the horizontal component of the pixel
                 let v = (j as f32 + random_float()) / (image_height - 1) as f32; // 0.0 <= v <= 1.0 | v is the vertical component of the pixel
-                let r = camera.get_ray(u, v);
-                average_color += r.color(&world, 1000);
+                let r = scene.camera.get_ray(u, v);
+                average_color += if spectral {
+                    // Each sample carries one wavelength, and adds up through CIE XYZ
+                    let (wavelength, pdf) = sample_wavelength();
+                    let r = Ray {
+                        wavelength: Some(wavelength),
+                        ..r
+                    };
+                    splats.wavelength = Some((wavelength, pdf));
+                    let value = upsample(&integrator.color(&scene, &r, &mut splats), wavelength);
+                    spectrum::to_rgb(value, wavelength, pdf)
+                } else {
+                    integrator.color(&scene, &r, &mut splats)
+                };
             }
 
             average_color /= samples_per_pixel as f32;
-            average_color = average_color.clamp(0.0, 0.999);
-            average_color = average_color.gamma_correct(2.0);
             buffer.push(average_color);
         }
         buffers.push(buffer);
@@ -130,6 +375,7 @@ pub fn compute_chunk(args: (Vec<i32>, Box<Camera>, Box<HittableList>, i32, i32,
     Res {
         buffers,
         start: start_index,
+        splats: splats.into_pixels(),
     }
 }
 
@@ -137,75 +383,69 @@ pub fn clear() {
     if env::consts::OS == "macos" || env::consts::OS == "linux" {
         std::process::Command::new("clear").status().unwrap();
     } else {
-        std::process::Command::new("clear").status().unwrap();
+        std::process::Command::new("cmd")
+            .args(["/c", "cls"])
+            .status()
+            .unwrap();
     }
 }
 
-pub fn write_to_file(path: &str, buffer: &Vec<Vec<Vec3>>, image_width: i32, image_height: i32) {
+pub fn write_to_file(path: &str, buffer: &[Vec<Vec3>], image_width: i32, image_height: i32) {
     let path = Path::new(path);
-    let extension = path.extension();
+    let extension = path.extension().and_then(|e| e.to_str());
     match extension {
-        Some(e) => match e.to_str() {
-            Some(e) => match e {
-                "jpg" | "jpeg" => {
-                    let encoder = jpeg_encoder::Encoder::new_file(path, 100)
-                        .expect("Failed to create jpeg encoder");
-                    let mut data = Vec::with_capacity((image_height * image_width * 3) as usize);
-                    for row in buffer.iter() {
-                        for pixel in row.iter() {
-                            data.push((pixel.x * 255.0) as u8);
-                            data.push((pixel.y * 255.0) as u8);
-                            data.push((pixel.z * 255.0) as u8);
-                        }
-                    }
-                    encoder
-                        .encode(
-                            &data,
-                            image_width as u16,
-                            image_height as u16,
-                            ColorType::Rgb,
-                        )
-                        .expect("Failed to encode jpeg");
+        Some("jpg" | "jpeg") => {
+            let encoder =
+                jpeg_encoder::Encoder::new_file(path, 100).expect("Failed to create jpeg encoder");
+            let mut data = Vec::with_capacity((image_height * image_width * 3) as usize);
+            for row in buffer.iter() {
+                for pixel in row.iter() {
+                    data.push((pixel.x * 255.0) as u8);
+                    data.push((pixel.y * 255.0) as u8);
+                    data.push((pixel.z * 255.0) as u8);
                 }
-                "png" => {
-                    let file_stream = File::create(path).unwrap();
-                    let ref mut writer = BufWriter::new(file_stream);
-                    let mut encoder =
-                        png::Encoder::new(writer, image_width as u32, image_height as u32);
-                    encoder.set_color(png::ColorType::Rgb);
-                    encoder.set_depth(png::BitDepth::Eight);
-                    let mut writer = encoder.write_header().unwrap();
-
-                    let mut data = Vec::with_capacity((image_height * image_width * 3) as usize);
-                    for row in buffer.iter() {
-                        for pixel in row.iter() {
-                            data.push((pixel.x * 255.0) as u8);
-                            data.push((pixel.y * 255.0) as u8);
-                            data.push((pixel.z * 255.0) as u8);
-                        }
-                    }
-                    writer.write_image_data(&data).unwrap();
+            }
+            encoder
+                .encode(
+                    &data,
+                    image_width as u16,
+                    image_height as u16,
+                    ColorType::Rgb,
+                )
+                .expect("Failed to encode jpeg");
+        }
+        Some("png") => {
+            let file_stream = File::create(path).unwrap();
+            let writer = &mut BufWriter::new(file_stream);
+            let mut encoder = png::Encoder::new(writer, image_width as u32, image_height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+
+            let mut data = Vec::with_capacity((image_height * image_width * 3) as usize);
+            for row in buffer.iter() {
+                for pixel in row.iter() {
+                    data.push((pixel.x * 255.0) as u8);
+                    data.push((pixel.y * 255.0) as u8);
+                    data.push((pixel.z * 255.0) as u8);
                 }
-                "ppm" => {
-                    let mut file = File::create(path).expect("Failed to create file");
-                    file.write_all(
-                        format!("P3\n{} {}\n255\n", image_width, image_height).as_bytes(),
-                    )
-                    .expect("Failed to write to file");
-                    for row in buffer.iter() {
-                        for pixel in row.iter() {
-                            let r = (pixel.x * 255.0) as i32;
-                            let g = (pixel.y * 255.0) as i32;
-                            let b = (pixel.z * 255.0) as i32;
-                            file.write_all(format!("{} {} {}\n", r, g, b).as_bytes())
-                                .expect("Failed to write to file");
-                        }
-                    }
+            }
+            writer.write_image_data(&data).unwrap();
+        }
+        Some("ppm") => {
+            let mut file = File::create(path).expect("Failed to create file");
+            file.write_all(format!("P3\n{} {}\n255\n", image_width, image_height).as_bytes())
+                .expect("Failed to write to file");
+            for row in buffer.iter() {
+                for pixel in row.iter() {
+                    let r = (pixel.x * 255.0) as i32;
+                    let g = (pixel.y * 255.0) as i32;
+                    let b = (pixel.z * 255.0) as i32;
+                    file.write_all(format!("{} {} {}\n", r, g, b).as_bytes())
+                        .expect("Failed to write to file");
                 }
-                _ => {}
-            },
-            _ => {}
-        },
+            }
+        }
         _ => {}
     }
 }
@@ -237,19 +477,19 @@ pub fn random_spheres(n: usize) -> Vec<Box<dyn Hittable>> {
         })
         .collect()
 }
-pub fn box_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>, axis: u8) -> Ordering {
+pub fn box_compare(a: &dyn Hittable, b: &dyn Hittable, axis: u8) -> Ordering {
     let box_a = a.bounding_box(0.0, 0.0).unwrap();
     let box_b = b.bounding_box(0.0, 0.0).unwrap();
     box_a.min[axis as usize]
         .partial_cmp(&box_b.min[axis as usize])
         .unwrap()
 }
-pub fn box_x_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>) -> Ordering {
+pub fn box_x_compare(a: &dyn Hittable, b: &dyn Hittable) -> Ordering {
     box_compare(a, b, 0)
 }
-pub fn box_y_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>) -> Ordering {
+pub fn box_y_compare(a: &dyn Hittable, b: &dyn Hittable) -> Ordering {
     box_compare(a, b, 1)
 }
-pub fn box_z_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>) -> Ordering {
+pub fn box_z_compare(a: &dyn Hittable, b: &dyn Hittable) -> Ordering {
     box_compare(a, b, 2)
 }
@@ -1,4 +1,7 @@
-use std::fmt::{Debug, Formatter, Result};
+use std::{
+    fmt::{Debug, Formatter, Result},
+    sync::Arc,
+};
 
 use dyn_clonable::clonable;
 
@@ -7,7 +10,7 @@ use crate::{
     shapes::aabb::AxisAlignedBoundingBox,
     utils::{
         ray::Ray,
-        vec::{Point3, Vec3},
+        vec::{Color, Point3, Vec3},
     },
 };
 
@@ -15,6 +18,68 @@ use crate::{
 pub trait Hittable: Clone + Send + Sync {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox>;
+
+    /// The probability density, over solid angle, of `random` picking the direction
+    /// of `ray` from its origin. 0 when the ray misses the object, or when the
+    /// object cannot be sampled.
+    fn pdf_value(&self, _ray: &Ray) -> f32 {
+        0.0
+    }
+
+    /// The vector from `origin` to a random point of the object, for lights to be
+    /// sampled directly. `None` when the object cannot be sampled from there.
+    fn random(&self, _origin: &Point3, _time: f32) -> Option<Vec3> {
+        None
+    }
+
+    /// A uniformly random point on the surface of the object and the unit normal
+    /// there, for paths to leave lights from. `None` when the object cannot be sampled.
+    fn sample_surface(&self, _time: f32) -> Option<(Point3, Vec3)> {
+        None
+    }
+
+    /// The probability density, over area, of `sample_surface` picking `point`,
+    /// where the surface has the unit `normal`. 0 when the object cannot be sampled.
+    fn surface_pdf(&self, _point: &Point3, _normal: &Vec3, _time: f32) -> f32 {
+        0.0
+    }
+
+    /// The share of light going through the object along `ray` between `t_min` and
+    /// `t_max`, for shadow rays: 0 when it is blocked, and anything in between for
+    /// participating media.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if self.hit(ray, t_min, t_max).is_some() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+// Lets objects shared between several places, such as instanced meshes, be
+// stored alongside the others
+impl Hittable for Arc<dyn Hittable> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.as_ref().hit(ray, t_min, t_max)
+    }
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AxisAlignedBoundingBox> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        self.as_ref().pdf_value(ray)
+    }
+    fn random(&self, origin: &Point3, time: f32) -> Option<Vec3> {
+        self.as_ref().random(origin, time)
+    }
+    fn sample_surface(&self, time: f32) -> Option<(Point3, Vec3)> {
+        self.as_ref().sample_surface(time)
+    }
+    fn surface_pdf(&self, point: &Point3, normal: &Vec3, time: f32) -> f32 {
+        self.as_ref().surface_pdf(point, normal, time)
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.as_ref().transmittance(ray, t_min, t_max)
+    }
 }
 
 impl Debug for dyn Hittable {
@@ -32,6 +97,13 @@ pub struct HitRecord {
     pub material: Option<Box<dyn Material + Send + Sync>>,
     pub u: f32, // used for texture mapping
     pub v: f32, // used for texture mapping
+    /// The weights of the second and third vertices of the triangle hit, if any
+    pub barycentric: Option<(f32, f32)>,
+    /// The vertex colors of the mesh hit, if it has any, blended at the hit point
+    pub color: Option<Color>,
+    /// Whether the ray scattered inside a participating medium, where there is no
+    /// surface and the normal means nothing
+    pub in_medium: bool,
 }
 
 impl HitRecord {
@@ -51,6 +123,9 @@ impl HitRecord {
             front_face: false,
             u,
             v,
+            barycentric: None,
+            color: None,
+            in_medium: false,
         }
     }
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: Vec3) {
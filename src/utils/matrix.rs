@@ -0,0 +1,216 @@
+use std::ops;
+
+use crate::utils::vec::{Point3, Vec3};
+
+/// A 4x4 matrix for affine transforms, stored row by row and applied to column vectors.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Matrix {
+    pub rows: [[f32; 4]; 4],
+}
+
+impl Matrix {
+    pub fn new(rows: [[f32; 4]; 4]) -> Self {
+        Self { rows }
+    }
+
+    pub fn identity() -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Create a matrix from its columns, the layout used by glTF files
+    pub fn from_columns(columns: [[f32; 4]; 4]) -> Self {
+        Self::new(columns).transpose()
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        Self::new([
+            [factors.x, 0.0, 0.0, 0.0],
+            [0.0, factors.y, 0.0, 0.0],
+            [0.0, 0.0, factors.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotation around the X axis
+    /// # Arguments
+    /// * `degrees` - The angle, counterclockwise when looking down the axis
+    pub fn rotation_x(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotation around the Y axis
+    /// # Arguments
+    /// * `degrees` - The angle, counterclockwise when looking down the axis
+    pub fn rotation_y(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::new([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotation around the Z axis
+    /// # Arguments
+    /// * `degrees` - The angle, counterclockwise when looking down the axis
+    pub fn rotation_z(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::new([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rows[j][i];
+            }
+        }
+        Self::new(rows)
+    }
+
+    /// The determinant of the linear part, which is that of the whole matrix for affine transforms
+    pub fn determinant(&self) -> f32 {
+        let m = &self.rows;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting, `None` when the matrix is singular
+    /// (e.g. a scaling by zero)
+    pub fn inverse(&self) -> Option<Self> {
+        let mut m = self.rows;
+        let mut inverse = Self::identity().rows;
+        for column in 0..4 {
+            let pivot =
+                (column..4).max_by(|&a, &b| m[a][column].abs().total_cmp(&m[b][column].abs()))?;
+            if m[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            m.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = m[column][column].recip();
+            for j in 0..4 {
+                m[column][j] *= scale;
+                inverse[column][j] *= scale;
+            }
+            for row in (0..4).filter(|&row| row != column) {
+                let factor = m[row][column];
+                for j in 0..4 {
+                    m[row][j] -= factor * m[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+        Some(Self::new(inverse))
+    }
+
+    pub fn transform_point(&self, point: Point3) -> Point3 {
+        self.transform_vector(point)
+            + Point3::new(self.rows[0][3], self.rows[1][3], self.rows[2][3])
+    }
+
+    /// Applies the linear part of the transform only, ignoring the translation
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        let m = &self.rows;
+        Vec3::new(
+            m[0][0] * vector.x + m[0][1] * vector.y + m[0][2] * vector.z,
+            m[1][0] * vector.x + m[1][1] * vector.y + m[1][2] * vector.z,
+            m[2][0] * vector.x + m[2][1] * vector.y + m[2][2] * vector.z,
+        )
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl ops::Mul<Matrix> for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: Matrix) -> Self::Output {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+            }
+        }
+        Matrix::new(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Matrix, b: &Matrix) {
+        for (row_a, row_b) in a.rows.iter().zip(&b.rows) {
+            for (x, y) in row_a.iter().zip(row_b) {
+                assert!((x - y).abs() < 1e-5, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn inverts_affine_transforms() {
+        let matrix = Matrix::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Matrix::rotation_y(30.0)
+            * Matrix::rotation_x(-75.0)
+            * Matrix::scaling(Vec3::new(2.0, 0.5, 3.0));
+        let inverse = matrix.inverse().unwrap();
+        assert_near(&(matrix * inverse), &Matrix::identity());
+        assert_near(&(inverse * matrix), &Matrix::identity());
+
+        let point = Point3::new(0.3, -1.2, 4.0);
+        let back = inverse.transform_point(matrix.transform_point(point));
+        assert!((back - point).length() < 1e-5);
+        assert!((matrix.determinant() - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rejects_singular_matrices() {
+        assert!(Matrix::scaling(Vec3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn reads_columns() {
+        let matrix = Matrix::from_columns([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [4.0, 5.0, 6.0, 1.0],
+        ]);
+        assert_eq!(matrix, Matrix::translation(Vec3::new(4.0, 5.0, 6.0)));
+    }
+}
@@ -2,7 +2,13 @@ pub mod args;
 pub mod camera;
 pub mod helpers;
 pub mod hittable;
+pub mod matrix;
+pub mod onb;
+pub mod photons;
+pub mod quaternion;
 pub mod ray;
 pub mod result;
+pub mod spectrum;
+pub mod splats;
 pub mod threads;
 pub mod vec;
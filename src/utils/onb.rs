@@ -0,0 +1,56 @@
+use crate::utils::vec::Vec3;
+
+/// An orthonormal basis, used to turn directions sampled around the Z axis into
+/// directions around any other axis.
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Create a new basis whose `w` axis points along `w`
+    /// # Arguments
+    /// * `w` - The main axis, does not need to be normalized
+    pub fn from_w(w: &Vec3) -> Self {
+        let w = w.unit_vector();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).unit_vector();
+        let u = w.cross(&v);
+        Self { u, v, w }
+    }
+
+    /// Create a new basis whose `w` axis points along `w`, turned around it so
+    /// that its `u` axis points as close to `u` as possible
+    /// # Arguments
+    /// * `w` - The main axis, does not need to be normalized
+    /// * `u` - The direction of the second axis, any one when it is along `w`
+    pub fn from_wu(w: &Vec3, u: &Vec3) -> Self {
+        let w = w.unit_vector();
+        let u = *u - u.dot(&w) * w;
+        if u.length_squared() < 1e-8 {
+            return Self::from_w(&w);
+        }
+        let u = u.unit_vector();
+        Self {
+            u,
+            v: w.cross(&u),
+            w,
+        }
+    }
+
+    /// Express a vector given in this basis in world coordinates
+    pub fn to_world(self, a: &Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+
+    /// Express a vector given in world coordinates in this basis
+    pub fn to_local(self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
+}
@@ -0,0 +1,314 @@
+use std::{cmp::Ordering, collections::BinaryHeap, f32::consts::PI, sync::Arc};
+
+use crate::{
+    materials::dielectric::InteriorStack,
+    scene::Scene,
+    utils::{
+        camera::{MAX_TIME, MIN_TIME},
+        helpers::{random_float, random_float_range},
+        hittable::Hittable,
+        ray::Ray,
+        spectrum::{at_wavelength, sample_wavelength, to_rgb},
+        vec::{Color, Point3, Vec3},
+    },
+};
+
+/// Light left on a surface by a path from a light
+#[derive(Debug, Copy, Clone)]
+pub struct Photon {
+    pub point: Point3,
+    /// The unit direction the photon arrived along
+    pub direction: Vec3,
+    /// The unit normal of the surface, on the side the photon arrived from
+    pub normal: Vec3,
+    /// The light carried, as a share of the power of all the lights
+    pub power: Color,
+    // The axis splitting the photons below this one in the kd-tree
+    axis: usize,
+}
+
+/// Scene, number of photons to emit, number emitted by all jobs together,
+/// maximum number of bounces of a photon and whether to trace a single
+/// wavelength per photon
+pub type PhotonArgs = (Arc<Scene>, usize, usize, i32, bool);
+
+/// Shoots photons from the lights, keeping those which reach a diffuse or
+/// glossy surface through specular bounces only: the caustics which paths
+/// traced from the camera struggle to find.
+pub fn trace_photons(args: PhotonArgs) -> Vec<Photon> {
+    let (scene, count, total, max_depth, spectral) = args;
+    let mut photons = Vec::new();
+    let lights = &scene.lights.lights;
+    if lights.is_empty() || total == 0 {
+        return photons;
+    }
+    for _ in 0..count {
+        let index = ((random_float() * lights.len() as f32) as usize).min(lights.len() - 1);
+        let emission = match lights[index].sample_emission(random_float_range(MIN_TIME, MAX_TIME)) {
+            Some(emission) if emission.pdf_position > 0.0 && emission.pdf_direction > 0.0 => {
+                emission
+            }
+            // Lights at infinity give off no photons
+            _ => continue,
+        };
+        let cosine = emission
+            .normal
+            .map_or(1.0, |normal| normal.dot(&emission.ray.direction).abs());
+        let power = emission.radiance
+            * (cosine * lights.len() as f32
+                / (emission.pdf_position * emission.pdf_direction * total as f32));
+        if !spectral {
+            photons.extend(trace_photon(&scene, emission.ray, power, max_depth));
+            continue;
+        }
+        // Dispersive dielectrics spread the wavelengths apart, and each photon
+        // keeps the color of its own
+        let (wavelength, pdf) = sample_wavelength();
+        let ray = Ray {
+            wavelength: Some(wavelength),
+            ..emission.ray
+        };
+        photons.extend(
+            trace_photon(&scene, ray, at_wavelength(power, ray.wavelength), max_depth).map(
+                |photon| Photon {
+                    power: to_rgb(photon.power.x, wavelength, pdf),
+                    ..photon
+                },
+            ),
+        );
+    }
+    photons
+}
+
+// Follows a photon through specular bounces, to the surface it is stored on
+fn trace_photon(scene: &Scene, ray: Ray, power: Color, max_depth: i32) -> Option<Photon> {
+    let mut ray = ray;
+    let mut power = power;
+    let mut interiors = InteriorStack::default();
+    for depth in 0..max_depth {
+        let hit = scene.world.hit(&ray, 1e-3, f32::INFINITY)?;
+        if let Some(interior) = interiors.current() {
+            let transmittance = interior.transmittance(hit.t * ray.direction.length());
+            power *= at_wavelength(transmittance, ray.wavelength);
+        }
+        let material = hit.material.clone()?;
+        let sample = match material.interior() {
+            Some(interior) => interiors.scatter(&ray, &hit, interior, material.as_ref())?,
+            None => material.sample(&ray, &hit)?,
+        };
+        if !sample.specular {
+            // Light reaching the first surface straight from the lights is
+            // already found by sampling them, and media keep their own
+            return (depth > 0 && !hit.in_medium).then(|| Photon {
+                point: hit.point,
+                direction: ray.direction.unit_vector(),
+                normal: hit.normal,
+                power,
+                axis: 0,
+            });
+        }
+        // Paths through glass barely lose any light, so they are only ended
+        // by how much the bounces absorb
+        let attenuation = at_wavelength(sample.throughput, ray.wavelength);
+        let survival = attenuation.max_component().min(1.0);
+        if survival <= 0.0 || random_float() >= survival {
+            return None;
+        }
+        power *= attenuation / survival;
+        ray = Ray {
+            wavelength: ray.wavelength,
+            ..Ray::new(hit.point, sample.direction, ray.time)
+        };
+    }
+    None
+}
+
+/// Photons stored in a balanced kd-tree, to find the ones around a point
+#[derive(Debug)]
+pub struct PhotonMap {
+    // Each range of photons has its median in the middle, splitting the photons
+    // before and after it along the median's axis
+    photons: Vec<Photon>,
+    nearest: usize,
+    max_distance_squared: f32,
+}
+
+impl PhotonMap {
+    /// Create a new photon map
+    /// # Arguments
+    /// * `photons` - The photons to store
+    /// * `nearest` - The number of photons each estimate of the light is made from
+    pub fn new(photons: Vec<Photon>, nearest: usize) -> Self {
+        let mut photons = photons;
+        build(&mut photons);
+        let mut map = Self {
+            photons,
+            nearest: nearest.max(1),
+            max_distance_squared: f32::INFINITY,
+        };
+        // Estimates reach no further than twice as far as they usually need to
+        // around the photons, so the few photons of a caustic are not smeared
+        // over the shadows next to it
+        let step = (map.photons.len() / 100).max(1);
+        let mut distances = (0..map.photons.len())
+            .step_by(step)
+            .filter_map(|index| {
+                let photon = &map.photons[index];
+                let mut heap = BinaryHeap::with_capacity(map.nearest + 1);
+                map.search(
+                    0..map.photons.len(),
+                    &photon.point,
+                    &photon.normal,
+                    &mut heap,
+                );
+                heap.peek().map(|neighbor| neighbor.distance_squared)
+            })
+            .collect::<Vec<f32>>();
+        if !distances.is_empty() {
+            let middle = distances.len() / 2;
+            let (_, median, _) = distances.select_nth_unstable_by(middle, f32::total_cmp);
+            map.max_distance_squared = 4.0 * *median;
+        }
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// The light leaving a surface from the photons around it, by density estimation
+    /// # Arguments
+    /// * `point` - The point of the surface
+    /// * `normal` - Its normal, on the side the light leaves from
+    /// * `reflected` - The power of a photon which the surface reflects toward the viewer
+    pub fn estimate(
+        &self,
+        point: &Point3,
+        normal: &Vec3,
+        reflected: impl Fn(&Photon) -> Color,
+    ) -> Color {
+        let mut heap = BinaryHeap::with_capacity(self.nearest + 1);
+        self.search(0..self.photons.len(), point, normal, &mut heap);
+        if heap.is_empty() {
+            return Color::zero();
+        }
+        // Past the nearest photons, the estimate covers the whole search disk
+        let radius_squared = if heap.len() == self.nearest {
+            heap.peek()
+                .map_or(self.max_distance_squared, |n| n.distance_squared)
+        } else {
+            self.max_distance_squared
+        };
+        let mut color = Color::zero();
+        for neighbor in heap {
+            color += reflected(&self.photons[neighbor.index]);
+        }
+        color / (PI * radius_squared)
+    }
+
+    // Keeps the nearest photons of a subtree in a max-heap of their distances
+    fn search(
+        &self,
+        range: std::ops::Range<usize>,
+        point: &Point3,
+        normal: &Vec3,
+        heap: &mut BinaryHeap<Neighbor>,
+    ) {
+        if range.is_empty() {
+            return;
+        }
+        let middle = range.start + range.len() / 2;
+        let photon = &self.photons[middle];
+        let offset = point[photon.axis] - photon.point[photon.axis];
+        let (near, far) = if offset < 0.0 {
+            (range.start..middle, middle + 1..range.end)
+        } else {
+            (middle + 1..range.end, range.start..middle)
+        };
+        self.search(near, point, normal, heap);
+
+        let farthest = |heap: &BinaryHeap<Neighbor>| match heap.peek() {
+            Some(neighbor) if heap.len() == self.nearest => neighbor.distance_squared,
+            _ => self.max_distance_squared,
+        };
+        let distance_squared = (photon.point - *point).length_squared();
+        // Photons on the other side of a thin wall, or around a corner, light another surface
+        if distance_squared < farthest(heap) && photon.normal.dot(normal) > 0.9 {
+            heap.push(Neighbor {
+                distance_squared,
+                index: middle,
+            });
+            if heap.len() > self.nearest {
+                heap.pop();
+            }
+        }
+        if offset * offset < farthest(heap) {
+            self.search(far, point, normal, heap);
+        }
+    }
+}
+
+struct Neighbor {
+    distance_squared: f32,
+    index: usize,
+}
+
+impl PartialEq for Neighbor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Neighbor {}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_squared.total_cmp(&other.distance_squared)
+    }
+}
+
+// Puts the median of the photons in the middle, along the axis they spread the
+// most on, and does the same on both sides
+fn build(photons: &mut [Photon]) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let (min, max) = bounds(photons);
+    let extent = max - min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    };
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| a.point[axis].total_cmp(&b.point[axis]));
+    photons[middle].axis = axis;
+    let (before, after) = photons.split_at_mut(middle);
+    build(before);
+    build(&mut after[1..]);
+}
+
+fn bounds(photons: &[Photon]) -> (Point3, Point3) {
+    let infinity = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    photons
+        .iter()
+        .fold((infinity, -infinity), |(min, max), photon| {
+            let p = photon.point;
+            (
+                Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        })
+}
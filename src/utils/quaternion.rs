@@ -0,0 +1,191 @@
+use std::ops;
+
+use crate::utils::{matrix::Matrix, vec::Vec3};
+
+/// A unit quaternion representing a rotation, used to interpolate between orientations.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quaternion {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Rotation around an axis
+    /// # Arguments
+    /// * `axis` - The axis to rotate around, does not need to be normalized
+    /// * `degrees` - The angle, counterclockwise when looking down the axis
+    pub fn from_axis_angle(axis: Vec3, degrees: f32) -> Self {
+        let (sin, cos) = (degrees.to_radians() / 2.0).sin_cos();
+        let axis = axis.unit_vector() * sin;
+        Self::new(cos, axis.x, axis.y, axis.z)
+    }
+
+    /// Rotation around the X, then Y, then Z axes, like the `rotate` field of scene transforms
+    /// # Arguments
+    /// * `degrees` - The angles around each axis
+    pub fn from_euler(degrees: Vec3) -> Self {
+        Self::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), degrees.z)
+            * Self::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), degrees.y)
+            * Self::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), degrees.x)
+    }
+
+    pub fn dot(&self, other: &Self) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalize(&self) -> Self {
+        let length = self.dot(self).sqrt();
+        Self::new(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    /// The angle in radians of the rotation from this orientation to another one
+    pub fn angle_to(&self, other: &Self) -> f32 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    /// Spherical linear interpolation, rotating at a constant speed along the shortest path
+    /// # Arguments
+    /// * `other` - The orientation reached at `t` = 1
+    /// * `t` - The interpolation parameter, between 0 and 1
+    pub fn slerp(&self, other: &Self, t: f32) -> Self {
+        let mut cos = self.dot(other);
+        // q and -q are the same rotation, pick the closest one
+        let other = if cos < 0.0 {
+            cos = -cos;
+            Self::new(-other.w, -other.x, -other.y, -other.z)
+        } else {
+            *other
+        };
+        let (a, b) = if cos > 0.9995 {
+            // Nearly identical orientations, where the sine below would vanish
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Self::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+        .normalize()
+    }
+
+    pub fn to_matrix(self) -> Matrix {
+        let Self { w, x, y, z } = self;
+        Matrix::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+// Composes rotations: `a * b` applies `b` first, then `a`
+impl ops::Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The same rotation, whichever sign the quaternions have
+    fn assert_same_rotation(a: &Quaternion, b: &Quaternion) {
+        assert!(a.dot(b).abs() > 1.0 - 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn slerps_through_the_keyframes() {
+        let from = Quaternion::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 10.0);
+        let to = Quaternion::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 120.0);
+        assert_same_rotation(&from.slerp(&to, 0.0), &from);
+        assert_same_rotation(&from.slerp(&to, 1.0), &to);
+    }
+
+    #[test]
+    fn slerps_at_a_constant_speed() {
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let from = Quaternion::identity();
+        let to = Quaternion::from_axis_angle(z, 90.0);
+        for t in [0.25, 0.5, 0.75] {
+            let expected = Quaternion::from_axis_angle(z, 90.0 * t);
+            assert_same_rotation(&from.slerp(&to, t), &expected);
+        }
+    }
+
+    #[test]
+    fn slerps_along_the_shortest_path() {
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let from = Quaternion::from_axis_angle(z, 170.0);
+        // -190 degrees is 170 degrees from the start the long way round
+        let to = Quaternion::from_axis_angle(z, -170.0);
+        assert_same_rotation(
+            &from.slerp(&to, 0.5),
+            &Quaternion::from_axis_angle(z, 180.0),
+        );
+        assert!((from.angle_to(&to) - 20f32.to_radians()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn matches_rotation_matrices() {
+        let degrees = Vec3::new(30.0, -45.0, 60.0);
+        let expected = Matrix::rotation_z(degrees.z)
+            * Matrix::rotation_y(degrees.y)
+            * Matrix::rotation_x(degrees.x);
+        let matrix = Quaternion::from_euler(degrees).to_matrix();
+        for (row, expected_row) in matrix.rows.iter().zip(&expected.rows) {
+            for (value, expected) in row.iter().zip(expected_row) {
+                assert!((value - expected).abs() < 1e-5);
+            }
+        }
+    }
+}
@@ -4,4 +4,6 @@ use super::vec::Color;
 pub struct Res {
     pub buffers: Vec<Vec<Color>>,
     pub start: i32,
+    /// Light splatted anywhere on the image, empty if there was none
+    pub splats: Vec<Color>,
 }
@@ -0,0 +1,90 @@
+//! Spectral rendering: paths carry a single wavelength, colors are turned into
+//! their value at that wavelength, and samples go back to RGB through CIE XYZ.
+
+use std::sync::OnceLock;
+
+use crate::utils::{helpers::random_float, vec::Color};
+
+/// The wavelengths sampled, in nanometers
+pub const MIN_WAVELENGTH: f32 = 360.0;
+pub const MAX_WAVELENGTH: f32 = 830.0;
+
+/// Picks a wavelength, more often where the eye is most sensitive.
+/// Returns the wavelength in nanometers and its pdf.
+pub fn sample_wavelength() -> (f32, f32) {
+    let wavelength = 538.0 - 138.888_89 * (0.856_910_6 - 1.827_502 * random_float()).atanh();
+    let wavelength = wavelength.clamp(MIN_WAVELENGTH, MAX_WAVELENGTH);
+    (wavelength, wavelength_pdf(wavelength))
+}
+
+fn wavelength_pdf(wavelength: f32) -> f32 {
+    0.003_939_804 / (0.0072 * (wavelength - 538.0)).cosh().powi(2)
+}
+
+/// The value at `wavelength` of a smooth spectrum matching `color`, made of red,
+/// green and blue bands adding up to 1 so white stays the same at every
+/// wavelength
+pub fn upsample(color: &Color, wavelength: f32) -> f32 {
+    let blue = 1.0 / (1.0 + ((wavelength - 490.0) / 6.0).exp());
+    let red = 1.0 / (1.0 + ((590.0 - wavelength) / 6.0).exp());
+    let green = (1.0 - red - blue).max(0.0);
+    color.x * red + color.y * green + color.z * blue
+}
+
+/// `color` as seen by a path of the given wavelength, the same value in every
+/// channel so it multiplies like a color. Unchanged without a wavelength.
+pub fn at_wavelength(color: Color, wavelength: Option<f32>) -> Color {
+    match wavelength {
+        Some(wavelength) => {
+            let value = upsample(&color, wavelength);
+            Color::new(value, value, value)
+        }
+        None => color,
+    }
+}
+
+/// The linear sRGB contribution of a radiance `value` found at `wavelength`,
+/// picked with the given pdf. White balanced so a flat spectrum is white.
+pub fn to_rgb(value: f32, wavelength: f32, pdf: f32) -> Color {
+    if pdf <= 0.0 {
+        return Color::zero();
+    }
+    xyz_to_rgb(&cie_xyz(wavelength)) * value / pdf * *white()
+}
+
+// The CIE 1931 color matching functions, as fitted by "Simple Analytic
+// Approximations to the CIE XYZ Color Matching Functions" (Wyman et al.)
+fn cie_xyz(wavelength: f32) -> Color {
+    let lobe = |mean: f32, below: f32, above: f32| {
+        let t = (wavelength - mean) / if wavelength < mean { below } else { above };
+        (-0.5 * t * t).exp()
+    };
+    Color::new(
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    )
+}
+
+/// CIE XYZ to linear sRGB, with a D65 white point
+pub fn xyz_to_rgb(xyz: &Color) -> Color {
+    Color::new(
+        3.240_454_2 * xyz.x - 1.537_138_5 * xyz.y - 0.498_531_4 * xyz.z,
+        -0.969_266 * xyz.x + 1.876_010_8 * xyz.y + 0.041_556 * xyz.z,
+        0.055_643_4 * xyz.x - 0.204_025_9 * xyz.y + 1.057_225_2 * xyz.z,
+    )
+}
+
+// Scales each channel so a spectrum of 1 at every wavelength comes out as white
+fn white() -> &'static Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+    WHITE.get_or_init(|| {
+        let mut xyz = Color::zero();
+        for wavelength in MIN_WAVELENGTH as i32..=MAX_WAVELENGTH as i32 {
+            xyz += cie_xyz(wavelength as f32);
+        }
+        let rgb = xyz_to_rgb(&xyz);
+        Color::new(1.0 / rgb.x, 1.0 / rgb.y, 1.0 / rgb.z)
+    })
+}
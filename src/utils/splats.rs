@@ -0,0 +1,61 @@
+use crate::utils::{
+    spectrum::{to_rgb, upsample},
+    vec::Color,
+};
+
+/// Light reaching the camera from paths traced out of the lights, which can land
+/// on any pixel rather than the one being rendered
+#[derive(Debug)]
+pub struct Splats {
+    width: i32,
+    height: i32,
+    weight: f32,
+    /// The wavelength of the current sample and its pdf, in spectral mode
+    pub wavelength: Option<(f32, f32)>,
+    pixels: Vec<Color>,
+}
+
+impl Splats {
+    /// Create a new empty image, only allocated once something is splatted
+    /// # Arguments
+    /// * `width` - The width of the image
+    /// * `height` - The height of the image
+    /// * `samples_per_pixel` - The number of samples taken for each pixel
+    pub fn new(width: i32, height: i32, samples_per_pixel: i32) -> Self {
+        // Each sample traces one light path, so a pixel gets its share of
+        // all of them, over the part of the film it covers
+        let weight = ((width - 1) * (height - 1)) as f32
+            / (width as f32 * height as f32 * samples_per_pixel as f32);
+        Self {
+            width,
+            height,
+            weight,
+            wavelength: None,
+            pixels: Vec::new(),
+        }
+    }
+
+    /// Add light to the pixel under the point `(u, v)` of the film, as given to
+    /// `Camera::get_ray`
+    pub fn add(&mut self, u: f32, v: f32, color: Color) {
+        let i = (u * (self.width - 1) as f32) as i32;
+        let j = (v * (self.height - 1) as f32) as i32;
+        if u < 0.0 || v < 0.0 || i >= self.width || j >= self.height {
+            return;
+        }
+        let color = match self.wavelength {
+            Some((wavelength, pdf)) => to_rgb(upsample(&color, wavelength), wavelength, pdf),
+            None => color,
+        };
+        if self.pixels.is_empty() {
+            self.pixels = vec![Color::zero(); (self.width * self.height) as usize];
+        }
+        self.pixels[(j * self.width + i) as usize] += color * self.weight;
+    }
+
+    /// The splatted light of each pixel, row by row from the bottom, or nothing
+    /// if no light was splatted
+    pub fn into_pixels(self) -> Vec<Color> {
+        self.pixels
+    }
+}
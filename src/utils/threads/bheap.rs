@@ -103,9 +103,9 @@ where
         }
 
         if i != idx {
-            return Some(i);
+            Some(i)
         } else {
-            return None;
+            None
         }
     }
 
@@ -155,9 +155,9 @@ where
         }
 
         if i != idx {
-            return Some(i);
+            Some(i)
         } else {
-            return None;
+            None
         }
     }
 
@@ -261,11 +261,11 @@ where
 
     /// Returns the position for element with given uid in the heap buffer.
     pub fn index_in_heap_from_uid(&self, uid: u64) -> Option<usize> {
-        self.index.get(&uid).map(|&elem_idx| elem_idx)
+        self.index.get(&uid).copied()
     }
 
     /// Returns the position for element in the heap buffer.
     pub fn index_in_heap(&self, elem: &T) -> Option<usize> {
-        self.index.get(&elem.uid()).map(|&elem_idx| elem_idx)
+        self.index.get(&elem.uid()).copied()
     }
 }
@@ -42,6 +42,12 @@ impl Display for ThreadPoolError {
 
 impl Error for ThreadPoolError {}
 
+/// Shared, lockable heap of workers ordered by their pending load.
+type SharedWorkerPool<Req, Res> = Arc<Mutex<BinaryMaxHeap<Worker<Req, Res>>>>;
+
+/// Both ends of the channel workers use to notify job completion.
+type DoneChannel = (Sender<Option<u64>>, Receiver<Option<u64>>);
+
 /// Restores the order of the workers in the worker pool after any modifications to the
 /// number of pending tasks they have.
 fn restore_worker_pool_order<Req, Res>(
@@ -66,11 +72,11 @@ where
         worker_pool.restore_heap_property(i);
     }
 
-    return if pool_restored {
+    if pool_restored {
         Ok(())
     } else {
         Err(ThreadPoolError::LookupError)
-    };
+    }
 }
 
 /// Schedules a new job to the given worker pool by picking up the least
@@ -124,7 +130,7 @@ where
     Req: Send + Debug + 'static,
     Res: Send + Debug + 'static,
 {
-    pool: Option<Arc<Mutex<BinaryMaxHeap<Worker<Req, Res>>>>>,
+    pool: Option<SharedWorkerPool<Req, Res>>,
 
     done_channel: Sender<Option<u64>>,
 
@@ -173,7 +179,7 @@ where
     /// to wrap it in a Mutex.
     pub fn balancer_thread(
         done_channel: Receiver<Option<u64>>,
-        worker_heap: Arc<Mutex<BinaryMaxHeap<Worker<Req, Res>>>>,
+        worker_heap: SharedWorkerPool<Req, Res>,
     ) -> JoinHandle<Result<(), ThreadPoolError>> {
         thread::spawn(move || -> Result<(), ThreadPoolError> {
             while let Ok(Some(uid)) = done_channel.recv() {
@@ -199,12 +205,7 @@ where
     /// are tobe used instead of sharing them with a lock. The send end of the channel
     /// is cloned and passed to each of the workers. The receiver end returned is meant
     /// to be moved to the balancer thread's closure.
-    pub fn new_workers(
-        workers: usize,
-    ) -> (
-        Vec<Worker<Req, Res>>,
-        (Sender<Option<u64>>, Receiver<Option<u64>>),
-    ) {
+    pub fn new_workers(workers: usize) -> (Vec<Worker<Req, Res>>, DoneChannel) {
         let (done_tx, done_rx) = channel::<Option<u64>>();
         let mut worker_vec = Vec::<Worker<Req, Res>>::with_capacity(workers);
 
@@ -82,6 +82,7 @@ where
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum WorkerError {
     DoneNotificationFailed,
     ResultResponseFailed,
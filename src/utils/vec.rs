@@ -3,9 +3,12 @@ use std::{
     ops,
 };
 
+use serde::Deserialize;
+
 use crate::utils::helpers::{random_float, random_float_range};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(from = "[f32; 3]")]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
@@ -69,6 +72,10 @@ impl Vec3 {
         )
     }
 
+    pub fn max_component(&self) -> f32 {
+        self.x.max(self.y).max(self.z)
+    }
+
     pub fn near_zero(&self) -> bool {
         const EPS: f32 = 1e-8;
         self.x.abs() < EPS && self.y.abs() < EPS && self.z.abs() < EPS
@@ -93,11 +100,7 @@ impl Vec3 {
         r_out_perp + r_out_parallel
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{} {} {}", self.x, self.y, self.z)
-    }
-
-    pub fn to_string_color(&self) -> String {
+    pub fn to_string_color(self) -> String {
         format!(
             "{} {} {}",
             (255.999 * self.x) as i32,
@@ -113,6 +116,12 @@ impl Default for Vec3 {
     }
 }
 
+impl From<[f32; 3]> for Vec3 {
+    fn from(v: [f32; 3]) -> Self {
+        Self::new(v[0], v[1], v[2])
+    }
+}
+
 impl ops::Add<Vec3> for Vec3 {
     type Output = Self;
 